@@ -1,5 +1,6 @@
+use phi::Phi;
 use phi::data::Rectangle;
-use phi::gfx::{Renderable, Sprite};
+use phi::gfx::Sprite;
 
 use sdl2::render::Renderer;
 
@@ -13,16 +14,18 @@ pub struct Background {
 }
 
 impl Background {
+    pub fn new(sprite: Sprite, velocity: f64) -> Background {
+        Background {
+            pos: 0.0,
+
+            vel: velocity,
+            sprite: sprite,
+        }
+    }
+
     pub fn load(renderer: &Renderer, path: &str, velocity: f64) -> Option<Background> {
         Sprite::load(&renderer, path)
-        .map(|sprite| {
-            Background {
-                pos: 0.0,
-
-                vel: velocity,
-                sprite: sprite,
-            }
-        })
+        .map(|sprite| Background::new(sprite, velocity))
     }
 
     pub fn update(&mut self, elapsed: f64) {
@@ -37,24 +40,26 @@ impl Background {
         }
     }
 
-    pub fn render(&self, renderer: &mut Renderer) {
+    /// Submits the copies of the background covering the screen to the render
+    /// queue, on the given `layer`.
+    pub fn render(&self, phi: &mut Phi, layer: i32) {
     	let size = self.sprite.size();
 
         // We determine the scale ratio of the window to the sprite.
-        let (win_w, win_h) = renderer.output_size().unwrap();
-        let scale = win_h as f64 / size.1;
+        let (win_w, win_h) = phi.output_size();
+        let scale = win_h / size.1;
 
         // We render as many copies of the background as necessary to fill
         // the screen.
         let mut physical_left = -self.pos * scale;
 
-        while physical_left < win_w as f64 {
+        while physical_left < win_w {
             //? While the left of the image is still inside of the window...
-            self.sprite.render(renderer, Rectangle {
+            phi.queue.sprite(layer, 0.0, &self.sprite, Rectangle {
                 x: physical_left,
                 y: 0.0,
                 w: size.0 * scale,
-                h: win_h as f64,
+                h: win_h,
             });
 
             physical_left += size.0 * scale;
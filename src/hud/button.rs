@@ -15,7 +15,11 @@ pub struct Button {
 impl Button {
 
 	pub fn load(renderer: &Renderer, path: &str, size: (f64, f64)) -> Option<Button> {
-		let spritesheet = tryo!(Sprite::load(renderer, path));
+		Button::new(&tryo!(Sprite::load(renderer, path)), size)
+	}
+
+	/// Slices the `spritesheet` into frames of the given size, one per state.
+	pub fn new(spritesheet: &Sprite, size: (f64, f64)) -> Option<Button> {
 		let spritesheet_size = spritesheet.size();
 		let cols = spritesheet_size.0 / size.0;
 		let rows = spritesheet_size.1 / size.1;
@@ -59,6 +63,11 @@ impl Button {
 		}
 	}
 
+	/// The number of states, that is, of frames in the spritesheet.
+	pub fn states(&self) -> usize {
+		self.sprites.len()
+	}
+
 	pub fn get_state(&self) -> usize {
 		self.state
 	}
@@ -85,6 +94,11 @@ impl Button {
 		&self.frame
 	}
 
+	/// The sprite of the current state.
+	pub fn sprite(&self) -> &Sprite {
+		&self.sprites[self.state]
+	}
+
 
 	pub fn render(&self, renderer: &mut Renderer) {
 		self.sprites[self.state].render(renderer, self.frame);
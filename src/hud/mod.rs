@@ -1,3 +1,7 @@
 
 pub mod background;
-pub mod button;
\ No newline at end of file
+pub mod button;
+pub mod nine_slice;
+pub mod parallax;
+pub mod starfield;
+pub mod widgets;
\ No newline at end of file
@@ -0,0 +1,222 @@
+use phi::data::Rectangle;
+use phi::gfx::{DrawParams, Renderable, Sprite};
+
+use sdl2::render::Renderer;
+
+
+/// How the edges and the center of a nine-slice fill their part of the
+/// destination.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SliceMode {
+	/// Scales the slice to the size of its part.
+	Stretch,
+	/// Repeats the slice at its native size, cutting the last repetition.
+	Tile,
+}
+
+
+/// A sprite cut into nine slices by border insets, so that it may be rendered
+/// at any size: the corners keep their size, the edges span the borders and
+/// the center fills whatever is left.
+///
+///     +-----+-------------+-----+
+///     |  0  |      1      |  2  |
+///     +-----+-------------+-----+
+///     |  3  |      4      |  5  |
+///     +-----+-------------+-----+
+///     |  6  |      7      |  8  |
+///     +-----+-------------+-----+
+#[derive(Clone)]
+pub struct NineSlice {
+	sprite: Sprite,
+	/// The size of the borders, as `(left, top, right, bottom)`.
+	insets: (f64, f64, f64, f64),
+
+	edges: SliceMode,
+	center: SliceMode,
+}
+
+impl NineSlice {
+
+	pub fn load(renderer: &Renderer, path: &str, insets: (f64, f64, f64, f64)) -> Option<NineSlice> {
+		NineSlice::new(&tryo!(Sprite::load(renderer, path)), insets)
+	}
+
+	/// Slices the `sprite` along the `insets`, given as `(left, top, right,
+	/// bottom)`. Returns `None` if they don't fit the sprite.
+	pub fn new(sprite: &Sprite, insets: (f64, f64, f64, f64)) -> Option<NineSlice> {
+		let (w, h) = sprite.size();
+		let (left, top, right, bottom) = insets;
+
+		if left < 0.0 || top < 0.0 || right < 0.0 || bottom < 0.0
+		|| left + right > w || top + bottom > h {
+			return None;
+		}
+		Some(NineSlice {
+			sprite: sprite.clone(),
+			insets: insets,
+
+			edges: SliceMode::Stretch,
+			center: SliceMode::Stretch,
+		})
+	}
+
+	pub fn edges(mut self, mode: SliceMode) -> NineSlice {
+		self.edges = mode;
+		self
+	}
+
+	pub fn center(mut self, mode: SliceMode) -> NineSlice {
+		self.center = mode;
+		self
+	}
+
+
+	pub fn insets(&self) -> (f64, f64, f64, f64) {
+		self.insets
+	}
+
+	/// The smallest size at which the borders don't have to shrink.
+	pub fn min_size(&self) -> (f64, f64) {
+		let (left, top, right, bottom) = self.insets;
+		(left + right, top + bottom)
+	}
+
+	/// The area left inside the borders of the given destination, where the
+	/// contents of a panel go.
+	pub fn content(&self, dest: Rectangle) -> Rectangle {
+		let (left, top, right, bottom) = self.insets;
+
+		Rectangle {
+			x: dest.x + left,
+			y: dest.y + top,
+			w: (dest.w - left - right).max(0.0),
+			h: (dest.h - top - bottom).max(0.0),
+		}
+	}
+
+	pub fn get_alpha(&self) -> f64 {
+		self.sprite.get_alpha()
+	}
+
+	pub fn set_alpha(&mut self, alpha: f64) {
+		self.sprite.set_alpha(alpha);
+	}
+
+
+	fn render_slices(&self, renderer: &mut Renderer, dest: Rectangle, params: Option<&DrawParams>) {
+		for (i, &(src, dest)) in slice_rects(self.sprite.size(), self.insets, dest).iter().enumerate() {
+			if src.w <= 0.0 || src.h <= 0.0 || dest.w <= 0.0 || dest.h <= 0.0 {
+				continue;
+			}
+			let mode = match i {
+				4 => self.center,
+				1 | 3 | 5 | 7 => self.edges,
+				_ => SliceMode::Stretch,
+			};
+			let slice = match self.sprite.region(src) {
+				Some(slice) => slice,
+				None => continue
+			};
+			match mode {
+				SliceMode::Stretch => render_piece(&slice, renderer, dest, params),
+				SliceMode::Tile => render_tiled(&slice, renderer, dest, params),
+			}
+		}
+	}
+}
+
+impl Renderable for NineSlice {
+	fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
+		self.render_slices(renderer, dest, None);
+	}
+
+	/// Tints the slices, but doesn't rotate nor flip them.
+	fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+		let params = DrawParams { angle: 0.0, pivot: None, flip_h: false, flip_v: false, ..*params };
+
+		self.render_slices(renderer, dest, Some(&params));
+	}
+}
+
+
+fn render_piece(sprite: &Sprite, renderer: &mut Renderer, dest: Rectangle, params: Option<&DrawParams>) {
+	match params {
+		Some(params) => sprite.render_ex(renderer, dest, params),
+		None => sprite.render(renderer, dest),
+	}
+}
+
+/// Repeats the sprite at its native size over the destination, cutting the
+/// last row and column to fit.
+fn render_tiled(sprite: &Sprite, renderer: &mut Renderer, dest: Rectangle, params: Option<&DrawParams>) {
+	let (w, h) = sprite.size();
+	let mut y = 0.0;
+
+	while y < dest.h {
+		let tile_h = h.min(dest.h - y);
+		let mut x = 0.0;
+
+		while x < dest.w {
+			let tile_w = w.min(dest.w - x);
+
+			if let Some(tile) = sprite.region(Rectangle { x: 0.0, y: 0.0, w: tile_w, h: tile_h }) {
+				render_piece(&tile, renderer, Rectangle { x: dest.x + x, y: dest.y + y, w: tile_w, h: tile_h }, params);
+			}
+			x += w;
+		}
+		y += h;
+	}
+}
+
+/// Computes the source and destination rectangles of the nine slices, row by
+/// row. When the destination is smaller than the borders, they shrink
+/// proportionally and the center vanishes.
+fn slice_rects(size: (f64, f64), insets: (f64, f64, f64, f64), dest: Rectangle) -> Vec<(Rectangle, Rectangle)> {
+	let (left, top, right, bottom) = insets;
+	let shrink = |a: f64, b: f64, available: f64| {
+		if a + b > available && a + b > 0.0 { (a * available / (a + b), b * available / (a + b)) } else { (a, b) }
+	};
+	let (dest_left, dest_right) = shrink(left, right, dest.w);
+	let (dest_top, dest_bottom) = shrink(top, bottom, dest.h);
+
+	let src_cols = [(0.0, left), (left, size.0 - left - right), (size.0 - right, right)];
+	let src_rows = [(0.0, top), (top, size.1 - top - bottom), (size.1 - bottom, bottom)];
+	let dest_cols = [(0.0, dest_left), (dest_left, dest.w - dest_left - dest_right), (dest.w - dest_right, dest_right)];
+	let dest_rows = [(0.0, dest_top), (dest_top, dest.h - dest_top - dest_bottom), (dest.h - dest_bottom, dest_bottom)];
+
+	let mut rects = Vec::with_capacity(9);
+
+	for row in 0..3 {
+		for col in 0..3 {
+			rects.push((
+				Rectangle { x: src_cols[col].0, y: src_rows[row].0, w: src_cols[col].1, h: src_rows[row].1 },
+				Rectangle { x: dest.x + dest_cols[col].0, y: dest.y + dest_rows[row].0, w: dest_cols[col].1, h: dest_rows[row].1 },
+			));
+		}
+	}
+	rects
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::slice_rects;
+	use phi::data::Rectangle;
+
+
+	#[test]
+	fn slices_keep_corners_and_stretch_the_rest() {
+		let rects = slice_rects((16.0, 16.0), (4.0, 4.0, 4.0, 4.0), Rectangle { x: 10.0, y: 20.0, w: 100.0, h: 50.0 });
+
+		assert_eq!(rects[0], (Rectangle { x: 0.0, y: 0.0, w: 4.0, h: 4.0 }, Rectangle { x: 10.0, y: 20.0, w: 4.0, h: 4.0 }));
+		assert_eq!(rects[4], (Rectangle { x: 4.0, y: 4.0, w: 8.0, h: 8.0 }, Rectangle { x: 14.0, y: 24.0, w: 92.0, h: 42.0 }));
+		assert_eq!(rects[8].1, Rectangle { x: 106.0, y: 66.0, w: 4.0, h: 4.0 });
+
+		// Too narrow for the borders, which shrink.
+		let rects = slice_rects((16.0, 16.0), (4.0, 4.0, 4.0, 4.0), Rectangle { x: 0.0, y: 0.0, w: 6.0, h: 50.0 });
+
+		assert_eq!(rects[3].1.w, 3.0);
+		assert_eq!(rects[4].1.w, 0.0);
+	}
+}
@@ -0,0 +1,506 @@
+extern crate rand;
+
+
+use phi::Phi;
+use phi::data::Rectangle;
+use phi::descr::{Line, Lines};
+use phi::gfx::{DrawParams, Sprite};
+use phi::procgen::{self, NebulaConfig, StarLayerConfig};
+
+use sdl2::pixels::Color;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+
+use self::rand::{Rng, XorShiftRng};
+
+
+/// How the image of a scrolling layer is scaled to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScaleMode {
+	/// Scales the image so that it spans the height of the window.
+	FitHeight,
+	/// Keeps the image at its size, repeating it over the window.
+	Native,
+	/// Scales the image so that it spans the whole window.
+	Stretch,
+}
+
+
+/// Where the image of a scrolling layer comes from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LayerImage {
+	File(PathBuf),
+	/// A tileable layer of stars, generated from the seed at the given size.
+	Stars { seed: u64, size: (u32, u32) },
+	/// Tileable clouds of gas, generated from the seed at the given size.
+	Nebula { seed: u64, size: (u32, u32) },
+}
+
+/// Describes a layer which repeats an image over the window, scrolling it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScrollingLayerDescr {
+	pub image: LayerImage,
+	/// The speed of the layer, in pixels of the image per second, towards the
+	/// left and the top of the window.
+	pub velocity: (f64, f64),
+	pub scale: ScaleMode,
+	pub tint: Color,
+	pub alpha: f64,
+	/// Whether the layer goes over the game objects rather than behind them.
+	pub foreground: bool,
+}
+
+/// Describes a layer which sends sparse props (planets, comets, ...) across
+/// the window, at random intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropLayerDescr {
+	/// The props are picked at random from those images.
+	pub images: Vec<PathBuf>,
+	/// The range of the time between two props, in seconds.
+	pub interval: (f64, f64),
+	/// The range of the speed of the props towards the left, in pixels per
+	/// second.
+	pub speed: (f64, f64),
+	/// The range of the speed of the props towards the bottom, in pixels per
+	/// second.
+	pub drift: (f64, f64),
+	/// The range of the scale the props are rendered at.
+	pub scale: (f64, f64),
+	pub tint: Color,
+	pub alpha: f64,
+	pub foreground: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParallaxLayerDescr {
+	Scrolling(ScrollingLayerDescr),
+	Props(PropLayerDescr),
+}
+
+/// The contents of a parallax descriptor file. Those are plain text files,
+/// with one layer per line, from the back to the front (`#` starts a
+/// comment):
+///
+///     layer <image> [velocity <x> <y>] [scale fit_height|native|stretch] [tint <r> <g> <b>] [alpha <a>] [foreground]
+///     stars <seed> <w> <h> [layer options]
+///     nebula <seed> <w> <h> [layer options]
+///     props <image>... [interval <min> <max>] [speed <min> <max>] [drift <min> <max>] [scale <min> <max>] [tint <r> <g> <b>] [alpha <a>] [foreground]
+///
+/// Image paths are relative to the descriptor. `stars` and `nebula` layers
+/// scroll images generated when the stack is loaded, rather than files.
+/// Scrolling layers fit the height of the window and stand still by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParallaxDescr {
+	pub layers: Vec<ParallaxLayerDescr>,
+}
+
+impl ParallaxDescr {
+
+	pub fn load(path: &Path) -> Result<ParallaxDescr, io::Error> {
+		let mut source = String::new();
+
+		try!(try!(File::open(path)).read_to_string(&mut source));
+
+		ParallaxDescr::parse(&source, path.parent().unwrap_or(Path::new("")))
+	}
+
+	/// Parses the descriptor `source`. The image paths are resolved against
+	/// the `base` directory.
+	pub fn parse(source: &str, base: &Path) -> Result<ParallaxDescr, io::Error> {
+		let mut layers = vec![];
+
+		for mut line in Lines::new(source, "parallax descriptor") {
+			match line.statement() {
+				"layer" => {
+					let image = try!(line.expect("image path"));
+					let layer = try!(parse_scrolling(&mut line, LayerImage::File(base.join(image))));
+					layers.push(ParallaxLayerDescr::Scrolling(layer));
+				},
+				"stars" => {
+					let (seed, size) = try!(parse_generated(&mut line));
+					let layer = try!(parse_scrolling(&mut line, LayerImage::Stars { seed: seed, size: size }));
+					layers.push(ParallaxLayerDescr::Scrolling(layer));
+				},
+				"nebula" => {
+					let (seed, size) = try!(parse_generated(&mut line));
+					let layer = try!(parse_scrolling(&mut line, LayerImage::Nebula { seed: seed, size: size }));
+					layers.push(ParallaxLayerDescr::Scrolling(layer));
+				},
+				"props" => {
+					let mut layer = PropLayerDescr {
+						images: vec![],
+						interval: (5.0, 15.0),
+						speed: (20.0, 40.0),
+						drift: (0.0, 0.0),
+						scale: (1.0, 1.0),
+						tint: Color::RGB(255, 255, 255),
+						alpha: 1.0,
+						foreground: false,
+					};
+					//? Images come first, up to the first option.
+					while let Some(image) = line.peek() {
+						if PROP_OPTIONS.contains(&image) {
+							break;
+						}
+						layer.images.push(base.join(image));
+						line.next();
+					}
+					while let Some(option) = line.next() {
+						match option {
+							"interval" => layer.interval = try!(parse_range(&mut line)),
+							"speed" => layer.speed = try!(parse_range(&mut line)),
+							"drift" => layer.drift = try!(parse_range(&mut line)),
+							"scale" => layer.scale = try!(parse_range(&mut line)),
+							"tint" => layer.tint = try!(parse_tint(&mut line)),
+							"alpha" => layer.alpha = try!(line.parse()),
+							"foreground" => layer.foreground = true,
+							_ => return Err(line.error(&format!("unknown option '{}'", option)))
+						}
+					}
+					if layer.images.is_empty() {
+						return Err(line.error("image path expected"));
+					}
+					if layer.interval.0 <= 0.0 {
+						return Err(line.error("interval must be positive"));
+					}
+					layers.push(ParallaxLayerDescr::Props(layer));
+				},
+				statement => {
+					return Err(line.error(&format!("unknown statement '{}'", statement)));
+				},
+			}
+		}
+		Ok(ParallaxDescr { layers: layers })
+	}
+}
+
+/// Reads the options of a scrolling layer, which shows the given image.
+fn parse_scrolling(line: &mut Line, image: LayerImage) -> Result<ScrollingLayerDescr, io::Error> {
+	let mut layer = ScrollingLayerDescr {
+		image: image,
+		velocity: (0.0, 0.0),
+		scale: ScaleMode::FitHeight,
+		tint: Color::RGB(255, 255, 255),
+		alpha: 1.0,
+		foreground: false,
+	};
+	while let Some(option) = line.next() {
+		match option {
+			"velocity" => layer.velocity = try!(parse_pair(line)),
+			"scale" => {
+				layer.scale = match line.next() {
+					Some("fit_height") => ScaleMode::FitHeight,
+					Some("native") => ScaleMode::Native,
+					Some("stretch") => ScaleMode::Stretch,
+					_ => return Err(line.error("scale mode expected"))
+				};
+			},
+			"tint" => layer.tint = try!(parse_tint(line)),
+			"alpha" => layer.alpha = try!(line.parse()),
+			"foreground" => layer.foreground = true,
+			_ => return Err(line.error(&format!("unknown option '{}'", option)))
+		}
+	}
+	Ok(layer)
+}
+
+/// Reads the seed and the size of a generated image.
+fn parse_generated(line: &mut Line) -> Result<(u64, (u32, u32)), io::Error> {
+	let seed = try!(line.parse());
+	let size: (u32, u32) = (try!(line.parse()), try!(line.parse()));
+
+	if size.0 == 0 || size.1 == 0 {
+		return Err(line.error("the image must not be empty"));
+	}
+	Ok((seed, size))
+}
+
+const PROP_OPTIONS: [&'static str; 7] = ["interval", "speed", "drift", "scale", "tint", "alpha", "foreground"];
+
+fn parse_pair(line: &mut Line) -> Result<(f64, f64), io::Error> {
+	Ok((try!(line.parse()), try!(line.parse())))
+}
+
+fn parse_range(line: &mut Line) -> Result<(f64, f64), io::Error> {
+	let (min, max) = try!(parse_pair(line));
+
+	if min > max {
+		return Err(line.error("the minimum is larger than the maximum"));
+	}
+	Ok((min, max))
+}
+
+fn parse_tint(line: &mut Line) -> Result<Color, io::Error> {
+	Ok(Color::RGB(try!(line.parse()), try!(line.parse()), try!(line.parse())))
+}
+
+
+struct ScrollingLayer {
+	sprite: Sprite,
+	/// How far the layer scrolled, in pixels of the image.
+	pos: (f64, f64),
+	descr: ScrollingLayerDescr,
+}
+
+struct Prop {
+	sprite: usize,
+	location: (f64, f64),
+	velocity: (f64, f64),
+	scale: f64,
+}
+
+struct PropLayer {
+	sprites: Vec<Sprite>,
+	props: Vec<Prop>,
+	/// The time left before the next prop shows up.
+	countdown: f64,
+	descr: PropLayerDescr,
+}
+
+enum Layer {
+	Scrolling(ScrollingLayer),
+	Props(PropLayer),
+}
+
+
+/// Layers of images, scrolled at different speeds to give an illusion of
+/// depth. Background layers are rendered behind the game objects, and
+/// foreground layers over them.
+pub struct ParallaxStack {
+	layers: Vec<Layer>,
+	rng: XorShiftRng,
+}
+
+impl ParallaxStack {
+
+	/// Loads the stack described by the file located at the given path.
+	pub fn load(phi: &mut Phi, path: &str) -> Result<ParallaxStack, io::Error> {
+		ParallaxStack::new(phi, &try!(ParallaxDescr::load(Path::new(path))))
+	}
+
+	pub fn new(phi: &mut Phi, descr: &ParallaxDescr) -> Result<ParallaxStack, io::Error> {
+		let mut rng = rand::weak_rng();
+		let mut layers = Vec::with_capacity(descr.layers.len());
+
+		for layer in &descr.layers {
+			layers.push(match *layer {
+				ParallaxLayerDescr::Scrolling(ref descr) => Layer::Scrolling(ScrollingLayer {
+					sprite: try!(layer_image(phi, &descr.image)),
+					pos: (0.0, 0.0),
+					descr: descr.clone(),
+				}),
+				ParallaxLayerDescr::Props(ref descr) => {
+					let mut sprites = Vec::with_capacity(descr.images.len());
+
+					for image in &descr.images {
+						sprites.push(try!(load_image(phi, image)));
+					}
+					Layer::Props(PropLayer {
+						sprites: sprites,
+						props: vec![],
+						//? Props wait for a whole interval before the first one
+						//? shows up, so that they don't all come at once.
+						countdown: random_in(&mut rng, descr.interval),
+						descr: descr.clone(),
+					})
+				}
+			});
+		}
+		Ok(ParallaxStack {
+			layers: layers,
+			rng: rng,
+		})
+	}
+
+
+	pub fn update(&mut self, phi: &mut Phi, elapsed: f64) {
+		let (win_w, win_h) = phi.output_size();
+		let rng = &mut self.rng;
+
+		for layer in &mut self.layers {
+			match *layer {
+				Layer::Scrolling(ref mut layer) => {
+					let (w, h) = layer.sprite.size();
+
+					layer.pos.0 = wrap(layer.pos.0 + layer.descr.velocity.0 * elapsed, w);
+					layer.pos.1 = wrap(layer.pos.1 + layer.descr.velocity.1 * elapsed, h);
+				},
+				Layer::Props(ref mut layer) => {
+					for prop in &mut layer.props {
+						prop.location.0 -= prop.velocity.0 * elapsed;
+						prop.location.1 += prop.velocity.1 * elapsed;
+					}
+					let sprites = &layer.sprites;
+
+					layer.props.retain(|prop| {
+						let (w, h) = sprites[prop.sprite].size();
+						prop.location.0 + w * prop.scale > 0.0 && prop.location.1 < win_h && prop.location.1 + h * prop.scale > 0.0
+					});
+
+					layer.countdown -= elapsed;
+
+					if layer.countdown <= 0.0 {
+						let sprite = rng.gen_range(0, layer.sprites.len());
+						let scale = random_in(rng, layer.descr.scale);
+						let h = layer.sprites[sprite].size().1 * scale;
+
+						layer.props.push(Prop {
+							sprite: sprite,
+							location: (win_w, random_in(rng, (-h / 2.0, (win_h - h / 2.0).max(-h / 2.0)))),
+							velocity: (random_in(rng, layer.descr.speed), random_in(rng, layer.descr.drift)),
+							scale: scale,
+						});
+						layer.countdown = random_in(rng, layer.descr.interval);
+					}
+				}
+			}
+		}
+	}
+
+	/// Submits the layers to the render queue: those in the background on
+	/// `background_layer`, and the others on `foreground_layer`. Layers keep
+	/// their order through their depth.
+	pub fn render(&self, phi: &mut Phi, background_layer: i32, foreground_layer: i32) {
+		let (win_w, win_h) = phi.output_size();
+
+		for (depth, layer) in self.layers.iter().enumerate() {
+			let depth = depth as f64;
+
+			match *layer {
+				Layer::Scrolling(ref layer) => {
+					let queue_layer = if layer.descr.foreground { foreground_layer } else { background_layer };
+					let params = DrawParams { color: layer.descr.tint, alpha: layer.descr.alpha, ..DrawParams::default() };
+					let (w, h) = layer.sprite.size();
+					let (scale_x, scale_y) = match layer.descr.scale {
+						ScaleMode::FitHeight => (win_h / h, win_h / h),
+						ScaleMode::Native => (1.0, 1.0),
+						ScaleMode::Stretch => (win_w / w, win_h / h),
+					};
+					let (tile_w, tile_h) = (w * scale_x, h * scale_y);
+
+					// We render as many copies of the image as necessary to
+					// fill the screen.
+					let mut y = -layer.pos.1 * scale_y;
+
+					while y < win_h {
+						let mut x = -layer.pos.0 * scale_x;
+
+						while x < win_w {
+							phi.queue.sprite_ex(queue_layer, depth, &layer.sprite, Rectangle {
+								x: x,
+								y: y,
+								w: tile_w,
+								h: tile_h,
+							}, &params);
+
+							x += tile_w;
+						}
+						y += tile_h;
+					}
+				},
+				Layer::Props(ref layer) => {
+					let queue_layer = if layer.descr.foreground { foreground_layer } else { background_layer };
+					let params = DrawParams { color: layer.descr.tint, alpha: layer.descr.alpha, ..DrawParams::default() };
+
+					for prop in &layer.props {
+						let sprite = &layer.sprites[prop.sprite];
+						let (w, h) = sprite.size();
+
+						phi.queue.sprite_ex(queue_layer, depth, sprite, Rectangle {
+							x: prop.location.0,
+							y: prop.location.1,
+							w: w * prop.scale,
+							h: h * prop.scale,
+						}, &params);
+					}
+				}
+			}
+		}
+	}
+}
+
+fn load_image(phi: &mut Phi, path: &Path) -> Result<Sprite, io::Error> {
+	path.to_str()
+	.and_then(|path| phi.assets.texture(&phi.renderer, path))
+	.ok_or(io::Error::new(io::ErrorKind::NotFound, format!("Can't load parallax image {}!", path.display())))
+}
+
+fn layer_image(phi: &mut Phi, image: &LayerImage) -> Result<Sprite, io::Error> {
+	let generated = match *image {
+		LayerImage::File(ref path) => return load_image(phi, path),
+		LayerImage::Stars { seed, size: (w, h) } => procgen::star_layer(seed, w, h, &StarLayerConfig::default()),
+		LayerImage::Nebula { seed, size: (w, h) } => procgen::nebula(seed, w, h, &NebulaConfig::default()),
+	};
+	procgen::to_sprite(&phi.renderer, generated)
+	.map_err(|err| io::Error::new(io::ErrorKind::Other, format!("Can't generate parallax image! ({})", err)))
+}
+
+/// Brings the position back into [0, size), so that it never grows unbounded.
+fn wrap(pos: f64, size: f64) -> f64 {
+	let pos = pos % size;
+	if pos < 0.0 { pos + size } else { pos }
+}
+
+fn random_in(rng: &mut XorShiftRng, range: (f64, f64)) -> f64 {
+	if range.1 > range.0 { rng.gen_range(range.0, range.1) } else { range.0 }
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sdl2::pixels::Color;
+	use std::path::Path;
+
+
+	#[test]
+	fn descriptor_parses() {
+		let descr = ParallaxDescr::parse("
+			# Back to front
+			layer far.png velocity 20 -5 scale native alpha 0.5
+			props planet.png comet.png interval 2 4 drift 10 30
+			layer near.png tint 255 128 0 foreground
+			nebula 7 512 256 velocity 10 0
+		", Path::new("assets")).unwrap();
+
+		assert_eq!(descr.layers.len(), 4);
+
+		match descr.layers[0] {
+			ParallaxLayerDescr::Scrolling(ref layer) => {
+				assert_eq!(layer.image, LayerImage::File(Path::new("assets/far.png").to_path_buf()));
+				assert_eq!(layer.velocity, (20.0, -5.0));
+				assert_eq!(layer.scale, ScaleMode::Native);
+				assert_eq!(layer.alpha, 0.5);
+				assert!(!layer.foreground);
+			},
+			_ => panic!("expected a scrolling layer")
+		}
+		match descr.layers[1] {
+			ParallaxLayerDescr::Props(ref layer) => {
+				assert_eq!(layer.images.len(), 2);
+				assert_eq!(layer.interval, (2.0, 4.0));
+				assert_eq!(layer.drift, (10.0, 30.0));
+			},
+			_ => panic!("expected a prop layer")
+		}
+		match descr.layers[2] {
+			ParallaxLayerDescr::Scrolling(ref layer) => {
+				assert_eq!(layer.tint, Color::RGB(255, 128, 0));
+				assert!(layer.foreground);
+			},
+			_ => panic!("expected a scrolling layer")
+		}
+		match descr.layers[3] {
+			ParallaxLayerDescr::Scrolling(ref layer) => {
+				assert_eq!(layer.image, LayerImage::Nebula { seed: 7, size: (512, 256) });
+				assert_eq!(layer.velocity, (10.0, 0.0));
+			},
+			_ => panic!("expected a scrolling layer")
+		}
+		assert!(ParallaxDescr::parse("props interval 1 2", Path::new("")).is_err());
+		assert!(ParallaxDescr::parse("stars 1 0 64", Path::new("")).is_err());
+	}
+}
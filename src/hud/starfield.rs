@@ -0,0 +1,126 @@
+extern crate rand;
+
+
+use phi::Phi;
+use phi::data::Point3;
+
+use sdl2::pixels::Color;
+use sdl2::rect::Point as SdlPoint;
+use sdl2::render::Renderer;
+
+
+use self::rand::XorShiftRng;
+use self::rand::distributions::{IndependentSample, Normal, Range};
+
+
+/// The fraction of the stars which must have gone past the screen before new
+/// ones are spawned, so that they come in waves rather than one by one.
+const REFILL_THRESHOLD: f64 = 0.095;
+
+
+/// Stars flying towards the viewer, out of the center of the screen.
+pub struct Starfield {
+	stars: Vec<Point3>,
+	points: Vec<SdlPoint>,
+
+	/// The largest number of stars on screen.
+	density: usize,
+	/// How fast the stars come closer, in depth units per second.
+	speed: f64,
+	color: Color,
+
+	rng: XorShiftRng,
+}
+
+impl Starfield {
+	pub fn new(density: usize, speed: f64, color: Color) -> Starfield {
+		Starfield::with_rng(density, speed, color, rand::weak_rng())
+	}
+
+	/// Creates a starfield which always spawns the same stars for the same
+	/// seed and the same window size.
+	pub fn seeded(seed: u64, density: usize, speed: f64, color: Color) -> Starfield {
+		Starfield::with_rng(density, speed, color, ::phi::procgen::seeded_rng(seed))
+	}
+
+	fn with_rng(density: usize, speed: f64, color: Color, rng: XorShiftRng) -> Starfield {
+		Starfield {
+			stars: Vec::with_capacity(density),
+			points: Vec::with_capacity(density),
+
+			density: density,
+			speed: speed,
+			color: color,
+
+			rng: rng,
+		}
+	}
+
+
+	pub fn set_density(&mut self, density: usize) {
+		self.density = density;
+		self.stars.truncate(density);
+	}
+
+	pub fn set_speed(&mut self, speed: f64) {
+		self.speed = speed;
+	}
+
+	pub fn set_color(&mut self, color: Color) {
+		self.color = color;
+	}
+
+
+	pub fn update(&mut self, output_size: (f64, f64), elapsed: f64) {
+		let speed = self.speed;
+
+		self.stars.retain(|star| star.z < -1.0);
+
+		for star in &mut self.stars {
+			star.z += elapsed * speed;
+		}
+		let center = (output_size.0 * 0.5, output_size.1 * 0.5);
+		let shortage = self.density - self.stars.len();
+
+		if shortage > (self.density as f64 * REFILL_THRESHOLD) as usize {
+			let depth = Range::new(0.0, 1.0);
+			let plane = Normal::new(0.0, 1.0);
+			//? The farther the stars start, the more of them there are on
+			//? screen at once.
+			let max_depth = -6.46 * ((output_size.0 * output_size.1) as usize / self.density.max(1)) as f64;
+
+			for _ in 0..shortage {
+				self.stars.push(Point3 {
+					x: center.0 * plane.ind_sample(&mut self.rng),
+					y: center.1 * plane.ind_sample(&mut self.rng),
+					z: max_depth * depth.ind_sample(&mut self.rng),
+				});
+			}
+		}
+		self.points.clear();
+
+		for star in &self.stars {
+			let mut star = star.projected(1.0);
+
+			star.x = (star.x + 0.5) * output_size.0;
+			star.y = (star.y + 0.5) * output_size.1;
+
+			self.points.push(star.to_sdl());
+		}
+	}
+
+	pub fn render(&self, renderer: &mut Renderer) {
+		renderer.set_draw_color(self.color);
+		renderer.draw_points(&self.points).unwrap();
+	}
+
+	/// Submits the stars to the render queue.
+	pub fn queue(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		let (points, color) = (self.points.clone(), self.color);
+
+		phi.queue.custom(layer, depth, move |renderer| {
+			renderer.set_draw_color(color);
+			renderer.draw_points(&points).unwrap();
+		});
+	}
+}
@@ -0,0 +1,675 @@
+use phi::{Phi, ViewAction};
+use phi::data::Rectangle;
+use phi::gfx::Sprite;
+use phi::text::{Text, TextStyle};
+use phi::tween::{Animation, Easing, Tween};
+
+use sdl2::pixels::Color;
+
+use hud::button::Button;
+
+use super::{Anchor, Callback, Input, Reply, Theme, Widget};
+
+
+/// How much wider the selected item of a list grows as it pulses.
+const HOVER_GROWTH: f64 = 0.16;
+/// How long the selected item takes to grow, or to shrink back.
+const HOVER_PULSE_DURATION: f64 = ::std::f64::consts::PI / 12.0;
+
+
+/// Calls the callback, if any, and passes on what it asks the game loop to do.
+fn click(phi: &mut Phi, callback: &mut Option<Callback>) -> Reply {
+	match callback.as_mut().and_then(|callback| callback(phi)) {
+		Some(action) => Reply::Action(action),
+		None => Reply::Handled,
+	}
+}
+
+/// Draws a bar, filled from the left up to `value`, in [0, 1].
+fn render_bar(phi: &mut Phi, layer: i32, depth: f64, frame: Rectangle, value: f64, track: Color, fill: Color) {
+	phi.queue.fill_rect(layer, depth, frame, track);
+	phi.queue.fill_rect(layer, depth + 1.0, Rectangle { w: (frame.w * value).round(), ..frame }, fill);
+}
+
+
+/// Takes some room, and shows nothing.
+pub struct Spacer {
+	frame: Rectangle,
+	size: (f64, f64),
+}
+
+impl Spacer {
+	pub fn new(w: f64, h: f64) -> Spacer {
+		Spacer {
+			frame: Rectangle::with_size(w, h),
+			size: (w, h),
+		}
+	}
+}
+
+impl Widget for Spacer {
+	fn preferred_size(&self) -> (f64, f64) {
+		self.size
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+	}
+}
+
+
+/// A piece of text.
+pub struct Label {
+	frame: Rectangle,
+	text: Text,
+}
+
+impl Label {
+	pub fn new(phi: &mut Phi, theme: &Theme, text: &str) -> Result<Label, String> {
+		Label::with_style(phi, text, theme.text_style())
+	}
+
+	pub fn with_style(phi: &mut Phi, text: &str, style: TextStyle) -> Result<Label, String> {
+		Ok(Label {
+			frame: Rectangle::with_size(0.0, 0.0),
+			text: try!(Text::new(phi, text, style)),
+		})
+	}
+
+	pub fn text(&self) -> &str {
+		self.text.text()
+	}
+
+	pub fn set_text(&mut self, phi: &mut Phi, text: &str) -> Result<(), String> {
+		self.text.set_text(phi, text)
+	}
+}
+
+impl Widget for Label {
+	fn preferred_size(&self) -> (f64, f64) {
+		self.text.size()
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+	}
+
+	fn set_alpha(&mut self, alpha: f64) {
+		self.text.set_alpha(alpha);
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		self.text.queue(phi, layer, depth, self.frame.location());
+	}
+}
+
+
+/// A sprite, shown at its own size.
+pub struct Image {
+	frame: Rectangle,
+	sprite: Sprite,
+}
+
+impl Image {
+	pub fn new(sprite: Sprite) -> Image {
+		let (w, h) = sprite.size();
+
+		Image {
+			frame: Rectangle::with_size(w, h),
+			sprite: sprite,
+		}
+	}
+}
+
+impl Widget for Image {
+	fn preferred_size(&self) -> (f64, f64) {
+		self.sprite.size()
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		let (w, h) = self.sprite.size();
+		self.frame = Rectangle { x: frame.x, y: frame.y, w: w, h: h };
+	}
+
+	fn set_alpha(&mut self, alpha: f64) {
+		self.sprite.set_alpha(alpha);
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		phi.queue.sprite(layer, depth, &self.sprite, self.frame);
+	}
+}
+
+
+/// A button drawn from a spritesheet: its first state is shown normally, and
+/// its second one, if any, while it has the focus.
+pub struct ImageButton {
+	frame: Rectangle,
+	button: Button,
+	label: Option<Text>,
+	/// Where the label is pinned within the button, and how far from its
+	/// edges.
+	label_anchor: (Anchor, (f64, f64)),
+	on_click: Option<Callback>,
+}
+
+impl ImageButton {
+	pub fn new(button: Button) -> ImageButton {
+		ImageButton {
+			frame: *button.frame(),
+			button: button,
+			label: None,
+			label_anchor: (Anchor::Center, (0.0, 0.0)),
+			on_click: None,
+		}
+	}
+
+	/// Draws the label at the center of the button.
+	pub fn label(mut self, label: Text) -> ImageButton {
+		self.label = Some(label);
+		self
+	}
+
+	/// Pins the label to the given point of the button, `margin` away from
+	/// its edges.
+	pub fn label_anchor(mut self, anchor: Anchor, margin: (f64, f64)) -> ImageButton {
+		self.label_anchor = (anchor, margin);
+		self
+	}
+
+	pub fn on_click<F>(mut self, on_click: F) -> ImageButton where F: FnMut(&mut Phi) -> Option<ViewAction> + 'static {
+		self.on_click = Some(Box::new(on_click));
+		self
+	}
+}
+
+impl Widget for ImageButton {
+	fn preferred_size(&self) -> (f64, f64) {
+		self.button.size()
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.button.set_location(frame.x, frame.y);
+		self.frame = *self.button.frame();
+	}
+
+	fn is_focusable(&self) -> bool {
+		true
+	}
+
+	fn set_focused(&mut self, focused: bool) {
+		let state = if focused { 1 } else { 0 };
+		self.button.set_state(state.min(self.button.states() - 1));
+	}
+
+	fn handle(&mut self, phi: &mut Phi, input: Input) -> Reply {
+		match input {
+			Input::Activate => click(phi, &mut self.on_click),
+			_ => Reply::Ignored,
+		}
+	}
+
+	fn set_alpha(&mut self, alpha: f64) {
+		self.button.set_alpha(alpha);
+
+		if let Some(ref mut label) = self.label {
+			label.set_alpha(alpha);
+		}
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		phi.queue.sprite(layer, depth, self.button.sprite(), self.frame);
+
+		if let Some(ref label) = self.label {
+			let (anchor, margin) = self.label_anchor;
+			let (x, y) = anchor.place(self.frame, label.size(), margin);
+
+			label.queue(phi, layer, depth + 1.0, (x.floor(), y.floor()));
+		}
+	}
+}
+
+
+/// Shows how far something went, such as loading or a cooldown.
+pub struct ProgressBar {
+	frame: Rectangle,
+	size: (f64, f64),
+	/// How full the bar is, in [0, 1].
+	value: f64,
+
+	track: Color,
+	fill: Color,
+}
+
+impl ProgressBar {
+	pub fn new(theme: &Theme, size: (f64, f64)) -> ProgressBar {
+		ProgressBar {
+			frame: Rectangle::with_size(size.0, size.1),
+			size: size,
+			value: 0.0,
+
+			track: theme.track,
+			fill: theme.fill,
+		}
+	}
+
+	pub fn value(&self) -> f64 {
+		self.value
+	}
+
+	pub fn set_value(&mut self, value: f64) {
+		self.value = value.max(0.0).min(1.0);
+	}
+}
+
+impl Widget for ProgressBar {
+	fn preferred_size(&self) -> (f64, f64) {
+		self.size
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		render_bar(phi, layer, depth, self.frame, self.value, self.track, self.fill);
+	}
+}
+
+
+/// A box which is checked, or not, followed by its label. Activating it
+/// toggles it.
+pub struct Checkbox {
+	frame: Rectangle,
+	label: Text,
+	checked: bool,
+	focused: bool,
+
+	text: Color,
+	focused_text: Color,
+	track: Color,
+	fill: Color,
+	focused_fill: Color,
+
+	on_toggle: Option<Box<FnMut(&mut Phi, bool)>>,
+}
+
+impl Checkbox {
+	pub fn new(phi: &mut Phi, theme: &Theme, label: &str) -> Result<Checkbox, String> {
+		Checkbox::with_style(phi, theme, label, theme.text_style())
+	}
+
+	/// Creates a checkbox whose label has its own style. Its color is taken
+	/// from the theme all the same.
+	pub fn with_style(phi: &mut Phi, theme: &Theme, label: &str, style: TextStyle) -> Result<Checkbox, String> {
+		Ok(Checkbox {
+			frame: Rectangle::with_size(0.0, 0.0),
+			label: try!(Text::new(phi, label, TextStyle { color: theme.text, ..style })),
+			checked: false,
+			focused: false,
+
+			text: theme.text,
+			focused_text: theme.focused_text,
+			track: theme.track,
+			fill: theme.fill,
+			focused_fill: theme.focused_fill,
+
+			on_toggle: None,
+		})
+	}
+
+	pub fn checked(mut self, checked: bool) -> Checkbox {
+		self.checked = checked;
+		self
+	}
+
+	/// Calls `on_toggle` with whether the box is checked, whenever it's toggled.
+	pub fn on_toggle<F>(mut self, on_toggle: F) -> Checkbox where F: FnMut(&mut Phi, bool) + 'static {
+		self.on_toggle = Some(Box::new(on_toggle));
+		self
+	}
+
+	pub fn is_checked(&self) -> bool {
+		self.checked
+	}
+
+	/// The length of the sides of the box, which is as high as the label's
+	/// letters.
+	fn side(&self) -> f64 {
+		(self.label.size().1 * 0.6).round()
+	}
+}
+
+impl Widget for Checkbox {
+	fn preferred_size(&self) -> (f64, f64) {
+		let (w, h) = self.label.size();
+		let side = self.side();
+
+		(side * 1.5 + w, h.max(side))
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+	}
+
+	fn is_focusable(&self) -> bool {
+		true
+	}
+
+	fn set_focused(&mut self, focused: bool) {
+		self.focused = focused;
+		self.label.set_color(if focused { self.focused_text } else { self.text });
+	}
+
+	fn handle(&mut self, phi: &mut Phi, input: Input) -> Reply {
+		if input != Input::Activate {
+			return Reply::Ignored;
+		}
+		self.checked = !self.checked;
+
+		if let Some(ref mut on_toggle) = self.on_toggle {
+			on_toggle(phi, self.checked);
+		}
+		Reply::Handled
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		let side = self.side();
+		let fill = if self.focused { self.focused_fill } else { self.fill };
+		let outer = Rectangle { x: self.frame.x, y: self.frame.y + ((self.frame.h - side) / 2.0).floor(), w: side, h: side };
+		let inset = |rect: Rectangle, by: f64| Rectangle { x: rect.x + by, y: rect.y + by, w: rect.w - 2.0 * by, h: rect.h - 2.0 * by };
+
+		//? The box is drawn as nested squares: the border, the inside, and the
+		//? check mark if it's checked.
+		phi.queue.fill_rect(layer, depth, outer, fill);
+		phi.queue.fill_rect(layer, depth + 1.0, inset(outer, 3.0), self.track);
+
+		if self.checked {
+			phi.queue.fill_rect(layer, depth + 2.0, inset(outer, 6.0), fill);
+		}
+		self.label.queue(phi, layer, depth, (self.frame.x + side * 1.5, self.frame.y));
+	}
+}
+
+
+/// A bar which the left and right keys fill or empty, step by step.
+pub struct Slider {
+	frame: Rectangle,
+	size: (f64, f64),
+	/// Where the slider is, in [0, 1].
+	value: f64,
+	step: f64,
+	focused: bool,
+
+	track: Color,
+	fill: Color,
+	focused_fill: Color,
+
+	on_change: Option<Box<FnMut(&mut Phi, f64)>>,
+}
+
+impl Slider {
+	pub fn new(theme: &Theme, size: (f64, f64), value: f64) -> Slider {
+		Slider {
+			frame: Rectangle::with_size(size.0, size.1),
+			size: size,
+			value: value.max(0.0).min(1.0),
+			step: 0.1,
+			focused: false,
+
+			track: theme.track,
+			fill: theme.fill,
+			focused_fill: theme.focused_fill,
+
+			on_change: None,
+		}
+	}
+
+	/// Sets how far the slider moves on every key press.
+	pub fn step(mut self, step: f64) -> Slider {
+		self.step = step;
+		self
+	}
+
+	/// Calls `on_change` with the new value, whenever the slider moves.
+	pub fn on_change<F>(mut self, on_change: F) -> Slider where F: FnMut(&mut Phi, f64) + 'static {
+		self.on_change = Some(Box::new(on_change));
+		self
+	}
+
+	pub fn value(&self) -> f64 {
+		self.value
+	}
+}
+
+impl Widget for Slider {
+	fn preferred_size(&self) -> (f64, f64) {
+		self.size
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+	}
+
+	fn is_focusable(&self) -> bool {
+		true
+	}
+
+	fn set_focused(&mut self, focused: bool) {
+		self.focused = focused;
+	}
+
+	fn handle(&mut self, phi: &mut Phi, input: Input) -> Reply {
+		let value = match input {
+			Input::Left => self.value - self.step,
+			Input::Right => self.value + self.step,
+			_ => return Reply::Ignored,
+		};
+		//? Steps are rounded, so that the slider never strays from them.
+		let value = ((value / self.step).round() * self.step).max(0.0).min(1.0);
+
+		if value != self.value {
+			self.value = value;
+
+			if let Some(ref mut on_change) = self.on_change {
+				on_change(phi, value);
+			}
+		}
+		Reply::Handled
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		let fill = if self.focused { self.focused_fill } else { self.fill };
+		render_bar(phi, layer, depth, self.frame, self.value, self.track, fill);
+	}
+}
+
+
+struct Item {
+	idle: Sprite,
+	focused: Sprite,
+	on_select: Callback,
+}
+
+/// A column of items, one of which is selected with the up and down keys, and
+/// picked by activating the list. The selected item pulses while the list has
+/// the focus.
+pub struct List {
+	frame: Rectangle,
+	items: Vec<Item>,
+	selected: usize,
+	focused: bool,
+	/// Whether going past the last item selects the first one, and back,
+	/// rather than letting the focus move on.
+	wrap: bool,
+	spacing: f64,
+
+	style: TextStyle,
+	focused_style: TextStyle,
+	hover: Tween<f64>,
+}
+
+impl List {
+	pub fn new(theme: &Theme) -> List {
+		List {
+			frame: Rectangle::with_size(0.0, 0.0),
+			items: vec![],
+			selected: 0,
+			focused: false,
+			wrap: false,
+			spacing: 0.0,
+
+			style: theme.text_style(),
+			focused_style: theme.focused_text_style(),
+			hover: Tween::new(1.0, 1.0 + HOVER_GROWTH, HOVER_PULSE_DURATION)
+				.easing(Easing::SineOut)
+				.yoyo(true)
+				.repeat_forever(),
+		}
+	}
+
+	pub fn wrapping(mut self, wrap: bool) -> List {
+		self.wrap = wrap;
+		self
+	}
+
+	pub fn spacing(mut self, spacing: f64) -> List {
+		self.spacing = spacing;
+		self
+	}
+
+	/// Adds an item at the end of the list, which calls `on_select` when
+	/// picked.
+	pub fn push<F>(&mut self, phi: &mut Phi, label: &str, on_select: F) -> Result<(), String>
+	where F: FnMut(&mut Phi) -> Option<ViewAction> + 'static {
+		let render = |phi: &mut Phi, style: &TextStyle| phi.ttf_str_sprite(label, &style.font, style.size, style.color)
+			.ok_or(format!("Can't render item {}", label));
+
+		self.items.push(Item {
+			idle: try!(render(phi, &self.style)),
+			focused: try!(render(phi, &self.focused_style)),
+			on_select: Box::new(on_select),
+		});
+		Ok(())
+	}
+
+	pub fn selected(&self) -> usize {
+		self.selected
+	}
+
+	pub fn select(&mut self, index: usize) {
+		if index < self.items.len() && index != self.selected {
+			self.selected = index;
+			//? The newly selected item starts pulsing from its resting size.
+			self.hover.reset();
+		}
+	}
+
+	/// The height of every row, which fits the largest idle item.
+	fn row_height(&self) -> f64 {
+		self.items.iter().fold(0.0, |height, item| f64::max(height, item.idle.size().1))
+	}
+}
+
+impl Widget for List {
+	fn preferred_size(&self) -> (f64, f64) {
+		let width = self.items.iter().fold(0.0, |width, item| f64::max(width, item.focused.size().0 * (1.0 + HOVER_GROWTH)));
+		let count = self.items.len() as f64;
+
+		(width.ceil(), (self.row_height() * count + self.spacing * (count - 1.0).max(0.0)).ceil())
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+	}
+
+	fn is_focusable(&self) -> bool {
+		!self.items.is_empty()
+	}
+
+	fn set_focused(&mut self, focused: bool) {
+		if focused && !self.focused {
+			self.hover.reset();
+		}
+		self.focused = focused;
+	}
+
+	fn handle(&mut self, phi: &mut Phi, input: Input) -> Reply {
+		let count = self.items.len();
+
+		let selected = match input {
+			Input::Up if self.selected > 0 => self.selected - 1,
+			Input::Up if self.wrap => count - 1,
+			Input::Down if self.selected + 1 < count => self.selected + 1,
+			Input::Down if self.wrap => 0,
+			Input::Activate => {
+				//? We must use the `(self.items[i].on_select)(phi)` syntax so
+				//? that Rust doesn't confuse it with the invocation of a method
+				//? called `on_select`.
+				return match (self.items[self.selected].on_select)(phi) {
+					Some(action) => Reply::Action(action),
+					None => Reply::Handled,
+				};
+			},
+			_ => return Reply::Ignored,
+		};
+		self.select(selected);
+		Reply::Handled
+	}
+
+	fn update(&mut self, elapsed: f64) {
+		self.hover.advance(elapsed);
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		let row_height = self.row_height();
+
+		//? Items are centered, and placed one under the other.
+		for (i, item) in self.items.iter().enumerate() {
+			let y = self.frame.y + (row_height + self.spacing) * i as f64;
+
+			if self.focused && self.selected == i {
+				let (w, h) = item.focused.size();
+				let w = w * self.hover.value();
+
+				phi.queue.sprite(layer, depth, &item.focused, Rectangle { x: self.frame.x + (self.frame.w - w) * 0.5, y: y, w: w, h: h });
+			} else {
+				let (w, h) = item.idle.size();
+
+				phi.queue.sprite(layer, depth, &item.idle, Rectangle { x: self.frame.x + (self.frame.w - w) * 0.5, y: y, w: w, h: h });
+			}
+		}
+	}
+}
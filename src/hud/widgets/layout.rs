@@ -0,0 +1,306 @@
+use phi::data::Rectangle;
+
+use super::Widget;
+
+
+/// Where children are placed across a box, that is, horizontally in a `VBox`
+/// and vertically in an `HBox`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alignment {
+	Start,
+	Center,
+	End,
+	/// Children take the whole breadth of the box.
+	Stretch,
+}
+
+impl Alignment {
+	/// Places a child of the given `size` within the `available` space, which
+	/// starts at `start`. Returns its position and its size.
+	fn place(&self, start: f64, available: f64, size: f64) -> (f64, f64) {
+		match *self {
+			Alignment::Start => (start, size),
+			Alignment::Center => (start + ((available - size) / 2.0).floor(), size),
+			Alignment::End => (start + available - size, size),
+			Alignment::Stretch => (start, available),
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Axis {
+	Horizontal,
+	Vertical,
+}
+
+/// Children placed one after the other along an axis, every one of them at
+/// its preferred length.
+struct Stack {
+	axis: Axis,
+	frame: Rectangle,
+	spacing: f64,
+	alignment: Alignment,
+	children: Vec<Box<Widget>>,
+}
+
+impl Stack {
+	fn new(axis: Axis, spacing: f64) -> Stack {
+		Stack {
+			axis: axis,
+			frame: Rectangle::with_size(0.0, 0.0),
+			spacing: spacing,
+			alignment: Alignment::Start,
+			children: vec![],
+		}
+	}
+
+	fn preferred_size(&self) -> (f64, f64) {
+		let gaps = self.spacing * self.children.len().saturating_sub(1) as f64;
+		let (across, along) = self.children.iter()
+			.map(|child| orient(self.axis, child.preferred_size()))
+			.fold((0.0, gaps), |(across, along), (w, h)| (f64::max(across, w), along + h));
+
+		orient(self.axis, (across, along))
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+
+		let axis = self.axis;
+		let (start, mut position) = orient(axis, (frame.x, frame.y));
+		let (breadth, _) = orient(axis, (frame.w, frame.h));
+
+		for child in &mut self.children {
+			let (child_breadth, child_length) = orient(axis, child.preferred_size());
+			let (across, child_breadth) = self.alignment.place(start, breadth, child_breadth);
+			let (x, y) = orient(axis, (across, position));
+			let (w, h) = orient(axis, (child_breadth, child_length));
+
+			child.layout(Rectangle { x: x, y: y, w: w, h: h });
+			position += child_length + self.spacing;
+		}
+	}
+}
+
+/// Swaps the coordinates along a horizontal axis, so that the layout of
+/// stacks may be computed as if they were all vertical: `(across, along)`.
+fn orient(axis: Axis, (a, b): (f64, f64)) -> (f64, f64) {
+	match axis {
+		Axis::Vertical => (a, b),
+		Axis::Horizontal => (b, a),
+	}
+}
+
+
+macro_rules! stack_widget {
+	( $name: ident, $axis: expr ) => {
+		impl $name {
+			pub fn new(spacing: f64) -> $name {
+				$name { stack: Stack::new($axis, spacing) }
+			}
+
+			pub fn align(mut self, alignment: Alignment) -> $name {
+				self.stack.alignment = alignment;
+				self
+			}
+
+			pub fn child<W: Widget + 'static>(mut self, child: W) -> $name {
+				self.push(child);
+				self
+			}
+
+			pub fn push<W: Widget + 'static>(&mut self, child: W) {
+				self.stack.children.push(Box::new(child));
+			}
+		}
+
+		impl Widget for $name {
+			fn preferred_size(&self) -> (f64, f64) {
+				self.stack.preferred_size()
+			}
+
+			fn frame(&self) -> Rectangle {
+				self.stack.frame
+			}
+
+			fn layout(&mut self, frame: Rectangle) {
+				self.stack.layout(frame);
+			}
+
+			fn children(&self) -> &[Box<Widget>] {
+				&self.stack.children
+			}
+
+			fn children_mut(&mut self) -> &mut [Box<Widget>] {
+				&mut self.stack.children
+			}
+		}
+	}
+}
+
+/// Stacks its children from top to bottom.
+pub struct VBox {
+	stack: Stack,
+}
+
+stack_widget!(VBox, Axis::Vertical);
+
+/// Lines its children up from left to right.
+pub struct HBox {
+	stack: Stack,
+}
+
+stack_widget!(HBox, Axis::Horizontal);
+
+
+/// A point of a frame which a widget may be pinned to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+	TopLeft,
+	Top,
+	TopRight,
+	Left,
+	Center,
+	Right,
+	BottomLeft,
+	Bottom,
+	BottomRight,
+}
+
+impl Anchor {
+	/// How the anchor aligns things horizontally, then vertically.
+	fn alignments(&self) -> (Alignment, Alignment) {
+		use self::Alignment::*;
+
+		match *self {
+			Anchor::TopLeft => (Start, Start),
+			Anchor::Top => (Center, Start),
+			Anchor::TopRight => (End, Start),
+			Anchor::Left => (Start, Center),
+			Anchor::Center => (Center, Center),
+			Anchor::Right => (End, Center),
+			Anchor::BottomLeft => (Start, End),
+			Anchor::Bottom => (Center, End),
+			Anchor::BottomRight => (End, End),
+		}
+	}
+
+	/// The location of something of the given size, pinned to this point of
+	/// the frame, `margin` away from the edges.
+	pub fn place(&self, frame: Rectangle, size: (f64, f64), margin: (f64, f64)) -> (f64, f64) {
+		let (horizontal, vertical) = self.alignments();
+
+		(pin(horizontal, frame.x, frame.w, size.0, margin.0), pin(vertical, frame.y, frame.h, size.1, margin.1))
+	}
+}
+
+/// Pins its child, at its preferred size, to a point of the frame it's given,
+/// usually the whole window.
+pub struct Anchored {
+	frame: Rectangle,
+	anchor: Anchor,
+	/// The distance kept from the edges the child is pinned to.
+	margin: (f64, f64),
+	/// The one child, kept in a vector so that it may be lent as a slice.
+	children: Vec<Box<Widget>>,
+}
+
+impl Anchored {
+	pub fn new<W: Widget + 'static>(anchor: Anchor, child: W) -> Anchored {
+		Anchored {
+			frame: Rectangle::with_size(0.0, 0.0),
+			anchor: anchor,
+			margin: (0.0, 0.0),
+			children: vec![Box::new(child)],
+		}
+	}
+
+	pub fn margin(mut self, x: f64, y: f64) -> Anchored {
+		self.margin = (x, y);
+		self
+	}
+}
+
+impl Widget for Anchored {
+	fn preferred_size(&self) -> (f64, f64) {
+		let (w, h) = self.children[0].preferred_size();
+		(w + 2.0 * self.margin.0, h + 2.0 * self.margin.1)
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		self.frame = frame;
+
+		let (w, h) = self.children[0].preferred_size();
+		let (x, y) = self.anchor.place(frame, (w, h), self.margin);
+
+		self.children[0].layout(Rectangle { x: x, y: y, w: w, h: h });
+	}
+
+	fn children(&self) -> &[Box<Widget>] {
+		&self.children
+	}
+
+	fn children_mut(&mut self) -> &mut [Box<Widget>] {
+		&mut self.children
+	}
+}
+
+/// The position of something of the given size, pinned within the space which
+/// starts at `start`. The margin pushes it away from the edge it's pinned to,
+/// but only nudges it when centered.
+fn pin(alignment: Alignment, start: f64, length: f64, size: f64, margin: f64) -> f64 {
+	match alignment {
+		Alignment::Center => alignment.place(start, length, size).0 + margin,
+		_ => alignment.place(start + margin, length - 2.0 * margin, size).0,
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Alignment, Anchor, Anchored, HBox, VBox};
+	use super::super::Widget;
+	use super::super::controls::Spacer;
+	use phi::data::Rectangle;
+
+
+	#[test]
+	fn boxes_stack_their_children() {
+		let mut vbox = VBox::new(10.0).align(Alignment::Center)
+			.child(Spacer::new(100.0, 20.0))
+			.child(Spacer::new(50.0, 30.0));
+
+		assert_eq!(vbox.preferred_size(), (100.0, 60.0));
+
+		vbox.layout(Rectangle { x: 0.0, y: 100.0, w: 200.0, h: 100.0 });
+		assert_eq!(vbox.children()[0].frame(), Rectangle { x: 50.0, y: 100.0, w: 100.0, h: 20.0 });
+		assert_eq!(vbox.children()[1].frame(), Rectangle { x: 75.0, y: 130.0, w: 50.0, h: 30.0 });
+
+		let mut hbox = HBox::new(5.0).align(Alignment::Stretch)
+			.child(Spacer::new(10.0, 20.0))
+			.child(Spacer::new(30.0, 40.0));
+
+		assert_eq!(hbox.preferred_size(), (45.0, 40.0));
+
+		hbox.layout(Rectangle { x: 10.0, y: 0.0, w: 100.0, h: 50.0 });
+		assert_eq!(hbox.children()[1].frame(), Rectangle { x: 25.0, y: 0.0, w: 30.0, h: 50.0 });
+	}
+
+	#[test]
+	fn anchors_pin_children_to_the_frame() {
+		let window = Rectangle::with_size(800.0, 600.0);
+		let mut corner = Anchored::new(Anchor::BottomRight, Spacer::new(100.0, 50.0)).margin(8.0, 8.0);
+		let mut top = Anchored::new(Anchor::Top, Spacer::new(100.0, 50.0)).margin(0.0, 40.0);
+
+		corner.layout(window);
+		top.layout(window);
+
+		assert_eq!(corner.children()[0].frame(), Rectangle { x: 692.0, y: 542.0, w: 100.0, h: 50.0 });
+		assert_eq!(top.children()[0].frame(), Rectangle { x: 350.0, y: 40.0, w: 100.0, h: 50.0 });
+	}
+}
@@ -0,0 +1,340 @@
+//! A retained-mode widget toolkit: views build a tree of widgets once, then
+//! hand it to a `Ui`, which lays it out, moves the keyboard focus around and
+//! renders it on every frame.
+//!
+//!     let theme = Theme::default();
+//!     let ui = Ui::new(Anchored::new(Anchor::Center, VBox::new(theme.spacing)
+//!         .child(try!(Label::new(phi, &theme, "Options")))
+//!         .child(try!(Checkbox::new(phi, &theme, "Mute"))
+//!             .on_toggle(|phi, muted| phi.audio.set_muted(muted)))));
+
+pub mod controls;
+pub mod layout;
+pub mod panel;
+pub mod theme;
+
+
+use phi::{Events, Phi, ViewAction};
+use phi::data::Rectangle;
+
+
+pub use self::controls::{Checkbox, Image, ImageButton, Label, List, Slider, Spacer};
+pub use self::layout::{Alignment, Anchor, Anchored, HBox, VBox};
+pub use self::panel::Panel;
+pub use self::theme::Theme;
+
+
+/// What the focused widget is asked to do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+	Up,
+	Down,
+	Left,
+	Right,
+	/// Presses the widget, or picks the item it shows.
+	Activate,
+}
+
+impl Input {
+	/// The inputs whose keys were pressed since the last frame.
+	pub fn pressed(events: &Events) -> Vec<Input> {
+		let keys = [
+			(events.now.key_up, Input::Up),
+			(events.now.key_down, Input::Down),
+			(events.now.key_left, Input::Left),
+			(events.now.key_right, Input::Right),
+			(events.now.key_return, Input::Activate),
+			(events.now.key_space, Input::Activate),
+		];
+
+		keys.iter().filter(|&&(key, _)| key == Some(true)).map(|&(_, input)| input).collect()
+	}
+}
+
+
+/// How a widget dealt with an input.
+pub enum Reply {
+	/// The input means nothing to the widget, which lets the focus move on.
+	Ignored,
+	Handled,
+	/// A callback of the widget asks the game loop to act.
+	Action(ViewAction),
+}
+
+/// Called when a button or an item is activated. It may return what the game
+/// loop should do next, such as rendering another view.
+pub type Callback = Box<FnMut(&mut Phi) -> Option<ViewAction>>;
+
+
+/// An element of the interface. Widgets are sized bottom-up, from what they
+/// show, then placed top-down by their parents.
+pub trait Widget {
+	/// The size the widget would like to take.
+	fn preferred_size(&self) -> (f64, f64);
+
+	/// The area where the widget was last placed.
+	fn frame(&self) -> Rectangle;
+
+	/// Places the widget, and then its children, within the given frame.
+	fn layout(&mut self, frame: Rectangle);
+
+	fn children(&self) -> &[Box<Widget>] {
+		&[]
+	}
+
+	fn children_mut(&mut self) -> &mut [Box<Widget>] {
+		&mut []
+	}
+
+	/// Whether the widget takes the keyboard focus.
+	fn is_focusable(&self) -> bool {
+		false
+	}
+
+	fn set_focused(&mut self, _focused: bool) {}
+
+	/// Handles an input sent to the widget while it has the focus.
+	fn handle(&mut self, _phi: &mut Phi, _input: Input) -> Reply {
+		Reply::Ignored
+	}
+
+	/// Advances the animations of the widget and of its children.
+	fn update(&mut self, elapsed: f64) {
+		for child in self.children_mut() {
+			child.update(elapsed);
+		}
+	}
+
+	/// Fades the sprites and the text of the widget and of its children.
+	/// Plain colors, such as those of panels and bars, keep their opacity.
+	fn set_alpha(&mut self, alpha: f64) {
+		for child in self.children_mut() {
+			child.set_alpha(alpha);
+		}
+	}
+
+	/// Submits the widget to the render queue. Children are drawn deeper, so
+	/// that they show over their parents.
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		for child in self.children() {
+			child.render(phi, layer, depth + 1.0);
+		}
+	}
+}
+
+
+/// The root of a widget tree, which owns the keyboard focus. Focusable widgets
+/// are visited in the order they appear in the tree, depth first.
+pub struct Ui {
+	root: Box<Widget>,
+	layer: i32,
+	/// The index of the focused widget among the focusable ones.
+	focus: Option<usize>,
+}
+
+impl Ui {
+	/// Creates the interface, with the focus on its first focusable widget.
+	pub fn new<W: Widget + 'static>(root: W) -> Ui {
+		let mut ui = Ui {
+			root: Box::new(root),
+			layer: 0,
+			focus: None,
+		};
+		ui.focus(0);
+		ui
+	}
+
+	/// Sets the layer of the render queue the interface is drawn on.
+	pub fn layer(mut self, layer: i32) -> Ui {
+		self.layer = layer;
+		self
+	}
+
+	pub fn root(&self) -> &Widget {
+		&*self.root
+	}
+
+	pub fn root_mut(&mut self) -> &mut Widget {
+		&mut *self.root
+	}
+
+
+	pub fn focused(&self) -> Option<usize> {
+		self.focus
+	}
+
+	/// Moves the focus to the focusable widget of the given index, if there's
+	/// such a widget.
+	pub fn focus(&mut self, index: usize) {
+		if index >= count_focusable(&*self.root) {
+			return;
+		}
+		if let Some(previous) = self.focus {
+			if let Some(widget) = self.focusable_mut(previous) {
+				widget.set_focused(false);
+			}
+		}
+		if let Some(widget) = self.focusable_mut(index) {
+			widget.set_focused(true);
+		}
+		self.focus = Some(index);
+	}
+
+	/// Moves the focus to the next focusable widget, going 'round to the
+	/// first one past the last.
+	pub fn focus_next(&mut self) {
+		let count = count_focusable(&*self.root);
+
+		if count > 0 {
+			let index = self.focus.map_or(0, |focus| (focus + 1) % count);
+			self.focus(index);
+		}
+	}
+
+	/// Moves the focus to the previous focusable widget, going 'round to the
+	/// last one past the first.
+	pub fn focus_previous(&mut self) {
+		let count = count_focusable(&*self.root);
+
+		if count > 0 {
+			let index = self.focus.map_or(0, |focus| (focus + count - 1) % count);
+			self.focus(index);
+		}
+	}
+
+
+	/// Places the widgets within the given frame.
+	pub fn layout(&mut self, frame: Rectangle) {
+		self.root.layout(frame);
+	}
+
+	/// Sends the input to the focused widget. Inputs it ignores move the focus
+	/// instead.
+	pub fn handle(&mut self, phi: &mut Phi, input: Input) -> Option<ViewAction> {
+		let focus = self.focus;
+		let reply = match focus.and_then(|index| self.focusable_mut(index)) {
+			Some(widget) => widget.handle(phi, input),
+			None => Reply::Ignored
+		};
+
+		match reply {
+			Reply::Action(action) => return Some(action),
+			Reply::Handled => {},
+			Reply::Ignored => match input {
+				Input::Up | Input::Left => self.focus_previous(),
+				Input::Down | Input::Right => self.focus_next(),
+				Input::Activate => {},
+			},
+		}
+		None
+	}
+
+	/// Lays the interface out over the whole window, animates it, and handles
+	/// the keys pressed since the last frame. Returns what the callbacks asked
+	/// the game loop to do, if anything.
+	pub fn update(&mut self, phi: &mut Phi, elapsed: f64) -> Option<ViewAction> {
+		let (w, h) = phi.output_size();
+
+		self.layout(Rectangle::with_size(w, h));
+		self.root.update(elapsed);
+
+		for input in Input::pressed(&phi.events) {
+			if let Some(action) = self.handle(phi, input) {
+				return Some(action);
+			}
+		}
+		None
+	}
+
+	pub fn render(&self, phi: &mut Phi) {
+		self.root.render(phi, self.layer, 0.0);
+	}
+
+
+	fn focusable_mut(&mut self, index: usize) -> Option<&mut Widget> {
+		let mut index = index;
+		nth_focusable(&mut *self.root, &mut index)
+	}
+}
+
+
+fn count_focusable(widget: &Widget) -> usize {
+	let own = if widget.is_focusable() { 1 } else { 0 };
+
+	widget.children().iter().fold(own, |count, child| count + count_focusable(&**child))
+}
+
+/// Finds the focusable widget of the given index, counting down the index as
+/// focusable widgets are passed.
+fn nth_focusable<'a>(widget: &'a mut Widget, index: &mut usize) -> Option<&'a mut Widget> {
+	if widget.is_focusable() {
+		if *index == 0 {
+			return Some(widget);
+		}
+		*index -= 1;
+	}
+	for child in widget.children_mut() {
+		if let Some(found) = nth_focusable(&mut **child, index) {
+			return Some(found);
+		}
+	}
+	None
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Ui, Widget};
+	use super::controls::Spacer;
+	use super::layout::VBox;
+	use phi::data::Rectangle;
+
+	use std::cell::Cell;
+	use std::rc::Rc;
+
+
+	/// A focusable widget which tells whether it has the focus.
+	struct Focusable {
+		frame: Rectangle,
+		focused: Rc<Cell<bool>>,
+	}
+
+	impl Widget for Focusable {
+		fn preferred_size(&self) -> (f64, f64) { (10.0, 10.0) }
+		fn frame(&self) -> Rectangle { self.frame }
+		fn layout(&mut self, frame: Rectangle) { self.frame = frame; }
+		fn is_focusable(&self) -> bool { true }
+		fn set_focused(&mut self, focused: bool) { self.focused.set(focused); }
+	}
+
+	fn focusable() -> (Focusable, Rc<Cell<bool>>) {
+		let focused = Rc::new(Cell::new(false));
+		(Focusable { frame: Rectangle::with_size(0.0, 0.0), focused: focused.clone() }, focused)
+	}
+
+
+	#[test]
+	fn focus_goes_round_the_focusable_widgets() {
+		let (a, a_focused) = focusable();
+		let (b, b_focused) = focusable();
+		let mut ui = Ui::new(VBox::new(0.0)
+			.child(Spacer::new(10.0, 10.0))
+			.child(a)
+			.child(VBox::new(0.0).child(b)));
+
+		assert_eq!(ui.focused(), Some(0));
+		assert!(a_focused.get());
+
+		ui.focus_next();
+		assert_eq!(ui.focused(), Some(1));
+		assert!(!a_focused.get() && b_focused.get());
+
+		ui.focus_next();
+		assert_eq!(ui.focused(), Some(0));
+
+		ui.focus_previous();
+		assert!(b_focused.get());
+
+		assert_eq!(Ui::new(Spacer::new(1.0, 1.0)).focused(), None);
+	}
+}
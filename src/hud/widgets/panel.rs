@@ -0,0 +1,137 @@
+use phi::Phi;
+use phi::data::Rectangle;
+use phi::gfx::Renderable;
+
+use sdl2::pixels::Color;
+use sdl2::render::{BlendMode, Renderer};
+
+use hud::nine_slice::NineSlice;
+
+use super::{Theme, Widget};
+
+
+/// The width of the border drawn around panels without a background image.
+const BORDER_WIDTH: f64 = 2.0;
+
+
+/// Frames its child, over a background.
+pub struct Panel {
+	frame: Rectangle,
+	padding: f64,
+
+	color: Color,
+	border: Color,
+	/// Drawn instead of the plain color, if any.
+	background: Option<NineSlice>,
+
+	/// The one child, kept in a vector so that it may be lent as a slice.
+	children: Vec<Box<Widget>>,
+}
+
+impl Panel {
+	pub fn new<W: Widget + 'static>(theme: &Theme, child: W) -> Panel {
+		Panel {
+			frame: Rectangle::with_size(0.0, 0.0),
+			padding: theme.padding,
+
+			color: theme.panel,
+			border: theme.border,
+			background: None,
+
+			children: vec![Box::new(child)],
+		}
+	}
+
+	pub fn padding(mut self, padding: f64) -> Panel {
+		self.padding = padding;
+		self
+	}
+
+	/// Draws the panel with the nine-slice, whose borders add to the padding.
+	pub fn background(mut self, background: NineSlice) -> Panel {
+		self.background = Some(background);
+		self
+	}
+
+	/// The space left around the child: `(left, top, right, bottom)`.
+	fn insets(&self) -> (f64, f64, f64, f64) {
+		let (left, top, right, bottom) = self.background.as_ref().map_or((0.0, 0.0, 0.0, 0.0), |background| background.insets());
+		(left + self.padding, top + self.padding, right + self.padding, bottom + self.padding)
+	}
+}
+
+impl Widget for Panel {
+	fn preferred_size(&self) -> (f64, f64) {
+		let (w, h) = self.children[0].preferred_size();
+		let (left, top, right, bottom) = self.insets();
+
+		(w + left + right, h + top + bottom)
+	}
+
+	fn frame(&self) -> Rectangle {
+		self.frame
+	}
+
+	fn layout(&mut self, frame: Rectangle) {
+		let (left, top, right, bottom) = self.insets();
+
+		self.frame = frame;
+		self.children[0].layout(Rectangle {
+			x: frame.x + left,
+			y: frame.y + top,
+			w: (frame.w - left - right).max(0.0),
+			h: (frame.h - top - bottom).max(0.0),
+		});
+	}
+
+	fn children(&self) -> &[Box<Widget>] {
+		&self.children
+	}
+
+	fn children_mut(&mut self) -> &mut [Box<Widget>] {
+		&mut self.children
+	}
+
+	fn render(&self, phi: &mut Phi, layer: i32, depth: f64) {
+		let frame = self.frame;
+
+		match self.background {
+			Some(ref background) => {
+				let background = background.clone();
+				phi.queue.custom(layer, depth, move |renderer| background.render(renderer, frame));
+			},
+			None => {
+				let (color, border) = (self.color, self.border);
+				phi.queue.custom(layer, depth, move |renderer| fill_panel(renderer, frame, color, border));
+			},
+		}
+		self.children[0].render(phi, layer, depth + 1.0);
+	}
+}
+
+
+/// Fills the frame with the color, which may be translucent, then draws its
+/// border.
+fn fill_panel(renderer: &mut Renderer, frame: Rectangle, color: Color, border: Color) {
+	let Rectangle { x, y, w, h } = frame;
+	let blend_mode = renderer.blend_mode();
+	let borders = [
+		Rectangle { x: x, y: y, w: w, h: BORDER_WIDTH },
+		Rectangle { x: x, y: y + h - BORDER_WIDTH, w: w, h: BORDER_WIDTH },
+		Rectangle { x: x, y: y, w: BORDER_WIDTH, h: h },
+		Rectangle { x: x + w - BORDER_WIDTH, y: y, w: BORDER_WIDTH, h: h },
+	];
+
+	renderer.set_blend_mode(BlendMode::Blend);
+
+	if let Some(rect) = frame.to_sdl() {
+		renderer.set_draw_color(color);
+		renderer.fill_rect(rect).unwrap();
+	}
+	renderer.set_draw_color(border);
+
+	for rect in borders.iter().filter_map(|rect| rect.to_sdl()) {
+		renderer.fill_rect(rect).unwrap();
+	}
+	renderer.set_blend_mode(blend_mode);
+}
@@ -0,0 +1,100 @@
+use phi::color::Palette;
+use phi::text::TextStyle;
+
+use sdl2::pixels::Color;
+
+
+/// How widgets look. Widgets take what they need from the theme when they're
+/// created, so changing it means building the interface again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+	pub font: String,
+	pub font_size: u16,
+	/// The font size of the focused items, which stand out.
+	pub focused_font_size: u16,
+
+	pub text: Color,
+	pub focused_text: Color,
+
+	pub panel: Color,
+	pub border: Color,
+	/// The empty part of bars and boxes.
+	pub track: Color,
+	/// The filled part of bars and boxes.
+	pub fill: Color,
+	pub focused_fill: Color,
+
+	/// The space left between the children of boxes, in pixels.
+	pub spacing: f64,
+	/// The space left inside the borders of panels, in pixels.
+	pub padding: f64,
+}
+
+impl Default for Theme {
+	fn default() -> Theme {
+		Theme {
+			font: "assets/fonts/BlackOpsOne-Regular.ttf".to_string(),
+			font_size: 34,
+			focused_font_size: 38,
+
+			text: Color::RGB(97, 132, 162),
+			focused_text: Color::RGB(255, 255, 0),
+
+			panel: Color::RGBA(8, 10, 20, 200),
+			border: Color::RGB(97, 132, 162),
+			track: Color::RGB(36, 44, 60),
+			fill: Color::RGB(97, 132, 162),
+			focused_fill: Color::RGB(255, 255, 0),
+
+			spacing: 12.0,
+			padding: 16.0,
+		}
+	}
+}
+
+impl Theme {
+	/// The default theme, with the colors the palette defines. They go by the
+	/// name of the field prefixed with `ui_`, such as `ui_focused_text`.
+	pub fn from_palette(palette: &Palette) -> Theme {
+		let default = Theme::default();
+
+		Theme {
+			text: palette.color_or("ui_text", default.text),
+			focused_text: palette.color_or("ui_focused_text", default.focused_text),
+
+			panel: palette.color_or("ui_panel", default.panel),
+			border: palette.color_or("ui_border", default.border),
+			track: palette.color_or("ui_track", default.track),
+			fill: palette.color_or("ui_fill", default.fill),
+			focused_fill: palette.color_or("ui_focused_fill", default.focused_fill),
+
+			..default
+		}
+	}
+
+	pub fn text_style(&self) -> TextStyle {
+		TextStyle::new(&self.font, self.font_size, self.text)
+	}
+
+	pub fn focused_text_style(&self) -> TextStyle {
+		TextStyle::new(&self.font, self.focused_font_size, self.focused_text)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::Theme;
+	use phi::color::Palette;
+	use sdl2::pixels::Color;
+
+
+	#[test]
+	fn palettes_override_colors() {
+		let palette = Palette::parse("rgb ui_text 1 2 3").unwrap();
+		let theme = Theme::from_palette(&palette);
+
+		assert_eq!(theme.text, Color::RGB(1, 2, 3));
+		assert_eq!(theme.fill, Theme::default().fill);
+	}
+}
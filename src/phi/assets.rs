@@ -0,0 +1,517 @@
+use phi::backend::{Sound, Track};
+use phi::color::Palette;
+use phi::gfx::{AlphaChannel, Atlas, AtlasDescr, Sprite};
+use phi::text::GlyphAtlas;
+
+use sdl2::image::{ImageRWops, LoadTexture};
+use sdl2::render::Renderer;
+use sdl2::rwops::RWops;
+use sdl2::ttf::{Font, Sdl2TtfContext};
+
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+
+/// A cheap, shared handle to a cached asset. Cloning the handle doesn't copy
+/// the asset, which may be replaced in place behind every existing handle.
+pub struct Handle<T> {
+	value: Rc<RefCell<T>>,
+}
+
+impl<T> Handle<T> {
+
+	pub fn new(value: T) -> Handle<T> {
+		Handle {
+			value: Rc::new(RefCell::new(value)),
+		}
+	}
+
+
+	pub fn borrow<'a>(&'a self) -> Ref<'a, T> {
+		self.value.borrow()
+	}
+
+	/// Swaps the asset for the given one, for every handle sharing it.
+	pub fn replace(&self, value: T) {
+		*self.value.borrow_mut() = value;
+	}
+
+	/// Identifies the asset shared by this handle and its clones.
+	pub fn id(&self) -> usize {
+		&*self.value as *const RefCell<T> as usize
+	}
+}
+
+impl<T> Clone for Handle<T> {
+	fn clone(&self) -> Handle<T> {
+		Handle {
+			value: self.value.clone(),
+		}
+	}
+}
+
+
+/// Identifies an asset, so that it may be requested ahead of time.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Asset<'a> {
+	Texture(&'a str),
+	Font(&'a str, u16),
+	Sound(&'a str),
+	Music(&'a str),
+	/// A collision mask of the image, along with its alpha threshold.
+	Mask(&'a str, f64),
+	Atlas(&'a str),
+	/// The collision mask of the atlas's image, along with its alpha
+	/// threshold.
+	AtlasMask(&'a str, f64),
+	Palette(&'a str),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum AssetKey {
+	Texture(String),
+	Font(String, u16),
+	Sound(String),
+	Music(String),
+	/// The path of the image, and the bits of the alpha threshold.
+	Mask(String, u64),
+	Atlas(String),
+	Palette(String),
+}
+
+/// Loads assets on demand, and caches them by their path (and size, for
+/// fonts), so that every subsequent request is served from memory.
+///
+/// Assets may be gathered into named groups, which are preloaded when a view
+/// is created and unloaded when it's left. Unloading an asset only drops it
+/// from the cache: handles held elsewhere stay valid.
+///
+/// During development, the assets directory may be watched, so that changed
+/// files get reloaded in place, behind the existing handles.
+pub struct Assets<'window> {
+	ttf_context: &'window Sdl2TtfContext,
+
+	textures: HashMap<String, Sprite>,
+	fonts: HashMap<(String, u16), Handle<Font<'window, 'static>>>,
+	glyphs: HashMap<(String, u16), Handle<GlyphAtlas>>,
+	sounds: HashMap<String, Handle<Sound>>,
+	music: HashMap<String, Handle<Track>>,
+	masks: HashMap<(String, u64), Handle<AlphaChannel>>,
+	atlases: HashMap<String, Rc<Atlas>>,
+	palettes: HashMap<String, Handle<Palette>>,
+
+	groups: HashMap<String, Vec<AssetKey>>,
+
+	watcher: Option<Watcher>,
+}
+
+impl<'window> Assets<'window> {
+
+	pub fn new(ttf_context: &'window Sdl2TtfContext) -> Assets<'window> {
+		Assets {
+			ttf_context: ttf_context,
+
+			textures: HashMap::new(),
+			fonts: HashMap::new(),
+			glyphs: HashMap::new(),
+			sounds: HashMap::new(),
+			music: HashMap::new(),
+			masks: HashMap::new(),
+			atlases: HashMap::new(),
+			palettes: HashMap::new(),
+
+			groups: HashMap::new(),
+
+			watcher: None,
+		}
+	}
+
+
+	/// Returns the sprite spanning the whole image located at the given path.
+	pub fn texture(&mut self, renderer: &Renderer, path: &str) -> Option<Sprite> {
+		if let Some(sprite) = self.textures.get(path) {
+			return Some(sprite.clone());
+		}
+		let sprite = tryo!(Sprite::load(renderer, path));
+
+		self.textures.insert(path.to_string(), sprite.clone());
+		Some(sprite)
+	}
+
+	pub fn font(&mut self, path: &str, size: u16) -> Result<Handle<Font<'window, 'static>>, String> {
+		let key = (path.to_string(), size);
+
+		if let Some(font) = self.fonts.get(&key) {
+			return Ok(font.clone());
+		}
+		let font = Handle::new(try!(self.ttf_context.load_font(Path::new(path), size)));
+
+		self.fonts.insert(key, font.clone());
+		Ok(font)
+	}
+
+	/// Returns the glyph atlas of the font at the given size, which holds
+	/// (at least) the printable ASCII characters and those of the `text`.
+	/// Atlases lacking some of them are rebuilt in place.
+	pub fn glyphs(&mut self, renderer: &Renderer, path: &str, size: u16, text: &str) -> Result<Handle<GlyphAtlas>, String> {
+		let key = (path.to_string(), size);
+
+		let mut chars: Vec<char> = match self.glyphs.get(&key) {
+			Some(atlas) if atlas.borrow().contains_all(text) => return Ok(atlas.clone()),
+			Some(atlas) => atlas.borrow().chars(),
+			None => (' ' as u8..'~' as u8 + 1).map(|byte| byte as char).collect(),
+		};
+		chars.extend(text.chars().filter(|ch| !ch.is_control()));
+		chars.sort();
+		chars.dedup();
+
+		let font = try!(self.font(path, size));
+		let atlas = try!(GlyphAtlas::build(renderer, &font.borrow(), &chars));
+
+		match self.glyphs.get(&key) {
+			Some(handle) => {
+				handle.replace(atlas);
+				return Ok(handle.clone());
+			},
+			None => {}
+		}
+		let handle = Handle::new(atlas);
+
+		self.glyphs.insert(key, handle.clone());
+		Ok(handle)
+	}
+
+	/// Returns the sound located at the given path. Sounds described by `.sfx`
+	/// files are synthesized on load.
+	pub fn sound(&mut self, path: &str) -> Result<Handle<Sound>, String> {
+		if let Some(sound) = self.sounds.get(path) {
+			return Ok(sound.clone());
+		}
+		let sound = Handle::new(try!(Sound::load(Path::new(path))));
+
+		self.sounds.insert(path.to_string(), sound.clone());
+		Ok(sound)
+	}
+
+	pub fn music(&mut self, path: &str) -> Result<Handle<Track>, String> {
+		if let Some(music) = self.music.get(path) {
+			return Ok(music.clone());
+		}
+		let music = Handle::new(try!(Track::load(Path::new(path))));
+
+		self.music.insert(path.to_string(), music.clone());
+		Ok(music)
+	}
+
+	/// Returns the collision mask of the image located at the given path. The
+	/// mask is read from the `.acl0` file next to the image, if there is one,
+	/// and computed (then saved to such a file) otherwise.
+	pub fn mask(&mut self, path: &str, alpha_threshold: f64) -> Result<Handle<AlphaChannel>, io::Error> {
+		let key = (path.to_string(), alpha_threshold.to_bits());
+
+		if let Some(mask) = self.masks.get(&key) {
+			return Ok(mask.clone());
+		}
+		let mask = Handle::new(try!(load_mask(path, alpha_threshold)));
+
+		self.masks.insert(key, mask.clone());
+		Ok(mask)
+	}
+
+	/// Returns the atlas described by the file located at the given path. Its
+	/// image is cached as any other texture.
+	pub fn atlas(&mut self, renderer: &Renderer, path: &str) -> Result<Rc<Atlas>, io::Error> {
+		if let Some(atlas) = self.atlases.get(path) {
+			return Ok(atlas.clone());
+		}
+		let descr = try!(AtlasDescr::load(Path::new(path)));
+		let sheet = try!(descr.image.to_str()
+			.and_then(|image_path| self.texture(renderer, image_path))
+			.ok_or(io::Error::new(io::ErrorKind::NotFound, "Can't load atlas image!")));
+		let atlas = Rc::new(try!(Atlas::new(&sheet, &descr)
+			.ok_or(io::Error::new(io::ErrorKind::InvalidData, "Atlas frames don't fit the image!"))));
+
+		self.atlases.insert(path.to_string(), atlas.clone());
+		Ok(atlas)
+	}
+
+	/// Returns the collision mask of the image the `atlas` is sliced from, so
+	/// that the image path is written in the atlas descriptor only.
+	pub fn atlas_mask(&mut self, atlas: &Atlas, alpha_threshold: f64) -> Result<Handle<AlphaChannel>, io::Error> {
+		let path = try!(atlas.image().to_str()
+			.ok_or(io::Error::new(io::ErrorKind::InvalidInput, "Can't read atlas image path!")));
+
+		self.mask(path, alpha_threshold)
+	}
+
+	/// Returns the palette described by the file located at the given path.
+	pub fn palette(&mut self, path: &str) -> Result<Handle<Palette>, io::Error> {
+		if let Some(palette) = self.palettes.get(path) {
+			return Ok(palette.clone());
+		}
+		let palette = Handle::new(try!(Palette::load(Path::new(path))));
+
+		self.palettes.insert(path.to_string(), palette.clone());
+		Ok(palette)
+	}
+
+
+	/// Loads every asset of the list, and binds them to the `group`. Returns
+	/// the description of the first failure, if any.
+	pub fn preload(&mut self, renderer: &Renderer, group: &str, assets: &[Asset]) -> Result<(), String> {
+		for asset in assets {
+			let key = try!(match *asset {
+				Asset::Texture(path) => self.texture(renderer, path)
+					.map(|_| AssetKey::Texture(path.to_string()))
+					.ok_or(format!("Can't load texture {}", path)),
+				Asset::Font(path, size) => self.font(path, size)
+					.map(|_| AssetKey::Font(path.to_string(), size)),
+				Asset::Sound(path) => self.sound(path)
+					.map(|_| AssetKey::Sound(path.to_string())),
+				Asset::Music(path) => self.music(path)
+					.map(|_| AssetKey::Music(path.to_string())),
+				Asset::Mask(path, alpha_threshold) => self.mask(path, alpha_threshold)
+					.map(|_| AssetKey::Mask(path.to_string(), alpha_threshold.to_bits()))
+					.map_err(|e| e.to_string()),
+				Asset::Atlas(path) => self.atlas(renderer, path)
+					.map(|_| AssetKey::Atlas(path.to_string()))
+					.map_err(|e| e.to_string()),
+				//? The mask is bound to the group by the path of the image, as
+				//? the atlas may well be unloaded first.
+				Asset::AtlasMask(path, alpha_threshold) => self.atlas(renderer, path)
+					.and_then(|atlas| {
+						try!(self.atlas_mask(&atlas, alpha_threshold));
+						Ok(AssetKey::Mask(atlas.image().to_string_lossy().into_owned(), alpha_threshold.to_bits()))
+					})
+					.map_err(|e| e.to_string()),
+				Asset::Palette(path) => self.palette(path)
+					.map(|_| AssetKey::Palette(path.to_string()))
+					.map_err(|e| e.to_string()),
+			});
+			let keys = self.groups.entry(group.to_string()).or_insert(vec![]);
+
+			if !keys.contains(&key) {
+				keys.push(key);
+			}
+		}
+		Ok(())
+	}
+
+	/// Drops the assets of the `group` from the cache, unless another group
+	/// refers to them as well.
+	pub fn unload(&mut self, group: &str) {
+		let keys = match self.groups.remove(group) {
+			Some(keys) => keys,
+			None => return
+		};
+		for key in keys {
+			if self.groups.values().any(|keys| keys.contains(&key)) {
+				continue;
+			}
+			match key {
+				AssetKey::Texture(path) => { self.textures.remove(&path); },
+				AssetKey::Font(path, size) => {
+					self.glyphs.remove(&(path.clone(), size));
+					self.fonts.remove(&(path, size));
+				},
+				AssetKey::Sound(path) => { self.sounds.remove(&path); },
+				AssetKey::Music(path) => { self.music.remove(&path); },
+				AssetKey::Mask(path, bits) => { self.masks.remove(&(path, bits)); },
+				AssetKey::Atlas(path) => { self.atlases.remove(&path); },
+				AssetKey::Palette(path) => { self.palettes.remove(&path); },
+			}
+		}
+	}
+
+
+	/// Starts watching the files under the `root` directory. Their changes are
+	/// checked for every `interval` seconds by `reload_changed`.
+	pub fn watch(&mut self, root: &str, interval: f64) {
+		self.watcher = Some(Watcher::new(Path::new(root), interval));
+	}
+
+	/// Reloads the cached assets, which files have changed since the last
+	/// check. Does nothing unless the assets directory is being watched.
+	///
+	/// Textures, fonts, sounds, palettes and collision masks are replaced in
+	/// place, so that existing sprites and handles use the new data right away.
+	/// Masks are regenerated along with their `.acl0` files. Atlases are
+	/// rebuilt, and served to subsequent requests only.
+	pub fn reload_changed(&mut self, renderer: &Renderer, elapsed: f64) {
+		let changed = match self.watcher {
+			Some(ref mut watcher) => watcher.poll(elapsed),
+			None => return
+		};
+		for path in changed {
+			self.reload(renderer, &path);
+		}
+	}
+
+	fn reload(&mut self, renderer: &Renderer, path: &Path) {
+		let matches = |key: &str| Path::new(key) == path;
+
+		for (key, sprite) in self.textures.iter().filter(|&(key, _)| matches(key)) {
+			match renderer.load_texture(path) {
+				Ok(texture) => sprite.replace_texture(texture),
+				Err(e) => println!("Can't reload texture {}: {}", key, e)
+			}
+		}
+		for (&(ref key, size), font) in self.fonts.iter().filter(|&(&(ref key, _), _)| matches(key)) {
+			match self.ttf_context.load_font(path, size) {
+				Ok(new_font) => font.replace(new_font),
+				Err(e) => println!("Can't reload font {}: {}", key, e)
+			}
+			//? Glyphs are rasterized again from the new font, behind the
+			//? handles held by the existing texts.
+			if let Some(atlas) = self.glyphs.get(&(key.clone(), size)) {
+				let chars = atlas.borrow().chars();
+
+				match GlyphAtlas::build(renderer, &font.borrow(), &chars) {
+					Ok(new_atlas) => atlas.replace(new_atlas),
+					Err(e) => println!("Can't rebuild glyphs of {}: {}", key, e)
+				}
+			}
+		}
+		for (key, sound) in self.sounds.iter().filter(|&(key, _)| matches(key)) {
+			match Sound::load(path) {
+				Ok(new_sound) => sound.replace(new_sound),
+				Err(e) => println!("Can't reload sound {}: {}", key, e)
+			}
+		}
+		for (key, palette) in self.palettes.iter().filter(|&(key, _)| matches(key)) {
+			match Palette::load(path) {
+				Ok(new_palette) => palette.replace(new_palette),
+				Err(e) => println!("Can't reload palette {}: {}", key, e)
+			}
+		}
+		for (key, music) in self.music.iter().filter(|&(key, _)| matches(key)) {
+			match Track::load(path) {
+				Ok(new_music) => {
+					//? Freeing the music being played halts it, and we only
+					//? ever loop music tracks, thus we restart it. Other
+					//? tracks are left alone, whatever is playing.
+					let was_playing = music.borrow().is_playing();
+
+					music.replace(new_music);
+					if was_playing {
+						music.borrow().play(-1).unwrap_or(());
+					}
+				},
+				Err(e) => println!("Can't reload music {}: {}", key, e)
+			}
+		}
+		for (&(ref key, bits), mask) in self.masks.iter().filter(|&(&(ref key, _), _)| matches(key)) {
+			match compute_mask(key, f64::from_bits(bits)) {
+				Ok(new_mask) => mask.replace(new_mask),
+				Err(e) => println!("Can't regenerate mask {}: {}", key, e)
+			}
+		}
+		let atlases: Vec<String> = self.atlases.keys()
+		.filter(|key| matches(key))
+		.cloned()
+		.collect();
+
+		for key in atlases {
+			self.atlases.remove(&key);
+
+			if let Err(e) = self.atlas(renderer, &key) {
+				println!("Can't reload atlas {}: {}", key, e);
+			}
+		}
+	}
+}
+
+
+/// Polls the modification times of the files under a directory.
+struct Watcher {
+	root: PathBuf,
+	stamps: HashMap<PathBuf, SystemTime>,
+
+	interval: f64,
+	elapsed: f64,
+}
+
+impl Watcher {
+
+	fn new(root: &Path, interval: f64) -> Watcher {
+		let mut stamps = HashMap::new();
+
+		scan_stamps(root, &mut stamps);
+
+		Watcher {
+			root: root.to_path_buf(),
+			stamps: stamps,
+
+			interval: interval,
+			elapsed: 0.0,
+		}
+	}
+
+	/// Returns the files, which were modified or created since the last poll.
+	/// The directory is only scanned once per `interval`.
+	fn poll(&mut self, elapsed: f64) -> Vec<PathBuf> {
+		self.elapsed += elapsed;
+
+		if self.elapsed < self.interval {
+			return vec![];
+		}
+		self.elapsed = 0.0;
+
+		let mut stamps = HashMap::with_capacity(self.stamps.len());
+
+		scan_stamps(&self.root, &mut stamps);
+
+		let changed = stamps.iter()
+		.filter(|&(path, stamp)| self.stamps.get(path) != Some(stamp))
+		.map(|(path, _)| path.clone())
+		.collect();
+
+		self.stamps = stamps;
+		changed
+	}
+}
+
+fn scan_stamps(dir: &Path, stamps: &mut HashMap<PathBuf, SystemTime>) {
+	if let Ok(entries) = fs::read_dir(dir) {
+		for entry in entries.filter_map(|entry| entry.ok()) {
+			let path = entry.path();
+
+			if path.is_dir() {
+				scan_stamps(&path, stamps);
+			}
+			//? Masks are generated by ourselves, so we don't watch them.
+			else if path.extension().map_or(true, |extension| extension != "acl0") {
+				if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+					stamps.insert(path, modified);
+				}
+			}
+		}
+	}
+}
+
+
+fn load_mask(path: &str, alpha_threshold: f64) -> Result<AlphaChannel, io::Error> {
+	match AlphaChannel::from_file(&Path::new(path).with_extension("acl0")) {
+		Ok(alpha) => Ok(alpha),
+		_ => compute_mask(path, alpha_threshold)
+	}
+}
+
+/// Computes the mask from the image itself, and saves it next to the image.
+fn compute_mask(path: &str, alpha_threshold: f64) -> Result<AlphaChannel, io::Error> {
+	let surface_reader = try!(RWops::from_file(path, "rb")
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+	let surface = try!(surface_reader.load()
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e)));
+	let alpha = try!(unsafe { AlphaChannel::from_surface(&surface, Some(alpha_threshold)) }
+		.ok_or(io::Error::new(io::ErrorKind::InvalidData, "Can't read alpha channel of this image! (PIXEL FORMAT)")));
+
+	try!(alpha.save_to(&Path::new(path).with_extension("acl0")));
+
+	Ok(alpha)
+}
@@ -0,0 +1,626 @@
+extern crate rand;
+
+
+use phi::assets::Handle;
+use phi::backend::{AudioBackend, Sound};
+use phi::data::Rectangle;
+use phi::descr::Lines;
+use phi::variants::Variants;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+
+use self::rand::XorShiftRng;
+
+
+/// The number of channels allocated when the audio starts.
+const INITIAL_CHANNELS: usize = 32;
+/// The number of channels added whenever they're all busy.
+const CHANNELS_GROWTH: usize = 16;
+/// Past this number of channels, new sounds steal the channels of older ones
+/// rather than allocating more.
+const MAX_CHANNELS: usize = 128;
+/// How far outside of the listener's area, in pixels, positional sounds fade
+/// out completely.
+const DEFAULT_FALLOFF: f64 = 400.0;
+
+
+/// The groups of sounds which share a volume.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bus {
+	/// Applies to every sound, music included.
+	Master,
+	Music,
+	/// Sound effects.
+	Sfx,
+}
+
+
+/// The volumes of the buses, in [0, 1], and whether the audio is muted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioSettings {
+	pub master: f64,
+	pub music: f64,
+	pub sfx: f64,
+	pub muted: bool,
+}
+
+impl Default for AudioSettings {
+	fn default() -> AudioSettings {
+		AudioSettings {
+			master: 1.0,
+			music: 0.8,
+			sfx: 1.0,
+			muted: false,
+		}
+	}
+}
+
+impl AudioSettings {
+	/// Reads settings written by `save`. Missing entries keep their default.
+	pub fn load(path: &Path) -> Result<AudioSettings, io::Error> {
+		let mut source = String::new();
+
+		try!(try!(File::open(path)).read_to_string(&mut source));
+
+		AudioSettings::parse(&source)
+	}
+
+	/// Parses the settings, given as one `<name> <value>` pair per line.
+	pub fn parse(source: &str) -> Result<AudioSettings, io::Error> {
+		let mut settings = AudioSettings::default();
+
+		for mut line in Lines::new(source, "audio settings") {
+			match line.statement() {
+				"master" => settings.master = try!(line.parse()),
+				"music" => settings.music = try!(line.parse()),
+				"sfx" => settings.sfx = try!(line.parse()),
+				"muted" => settings.muted = try!(line.parse()),
+				name => return Err(line.error(&format!("unknown setting '{}'", name)))
+			}
+		}
+		Ok(settings)
+	}
+
+	pub fn to_string(&self) -> String {
+		format!("master {}\nmusic {}\nsfx {}\nmuted {}\n", self.master, self.music, self.sfx, self.muted)
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), io::Error> {
+		if let Some(parent) = path.parent() {
+			try!(fs::create_dir_all(parent));
+		}
+		try!(File::create(path)).write_all(self.to_string().as_bytes())
+	}
+
+	pub fn volume(&self, bus: Bus) -> f64 {
+		match bus {
+			Bus::Master => self.master,
+			Bus::Music => self.music,
+			Bus::Sfx => self.sfx,
+		}
+	}
+
+	/// The volume the sounds of the bus are actually played at, once the
+	/// master volume and the mute are applied.
+	pub fn effective_volume(&self, bus: Bus) -> f64 {
+		if self.muted {
+			return 0.0;
+		}
+		match bus {
+			Bus::Master => self.master,
+			_ => self.master * self.volume(bus),
+		}
+	}
+}
+
+
+/// Which voice gives way when a sound is played more times at once than it
+/// may be.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Steal {
+	/// Stops the voice which started first.
+	Oldest,
+	/// Stops the voice played at the lowest volume.
+	Quietest,
+	/// Drops the new sound instead.
+	None,
+}
+
+/// How often a sound may be played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoundLimits {
+	/// The largest number of voices playing the sound at once.
+	pub max_voices: usize,
+	pub steal: Steal,
+	/// The time, in seconds, during which playing the sound again does
+	/// nothing, so that sounds triggered on the same frame don't stack.
+	pub cooldown: f64,
+}
+
+impl Default for SoundLimits {
+	fn default() -> SoundLimits {
+		SoundLimits {
+			max_voices: 8,
+			steal: Steal::Oldest,
+			cooldown: 0.02,
+		}
+	}
+}
+
+
+/// A sound being played on a channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Voice {
+	sound: usize,
+	/// The time the voice started at, in seconds.
+	started: f64,
+	/// The volume of the voice itself, before the buses are applied.
+	volume: f64,
+}
+
+/// Picks the voice to stop among the `candidates`, given as channel indices.
+fn pick_victim(voices: &[Option<Voice>], candidates: &[usize], steal: Steal) -> Option<usize> {
+	let voice = |channel: &usize| voices[*channel].unwrap();
+
+	match steal {
+		Steal::Oldest => candidates.iter()
+			.min_by(|a, b| voice(a).started.partial_cmp(&voice(b).started).unwrap()).cloned(),
+		Steal::Quietest => candidates.iter()
+			.min_by(|a, b| voice(a).volume.partial_cmp(&voice(b).volume).unwrap()).cloned(),
+		Steal::None => None,
+	}
+}
+
+
+/// The volume in [0, 1] and the stereo panning in [-1, 1] (from left to
+/// right) of a sound played at the `position`, heard over the `listener`'s
+/// area. Sounds fade out linearly outside of it, over the `falloff` distance.
+pub fn spatialize(position: (f64, f64), listener: Rectangle, falloff: f64) -> (f64, f64) {
+	let (center_x, _) = listener.center();
+	let pan = ((position.0 - center_x) / (listener.w / 2.0).max(1.0)).max(-1.0).min(1.0);
+
+	let outside_x = (listener.x - position.0).max(position.0 - (listener.x + listener.w)).max(0.0);
+	let outside_y = (listener.y - position.1).max(position.1 - (listener.y + listener.h)).max(0.0);
+	let distance = (outside_x * outside_x + outside_y * outside_y).sqrt();
+	let volume = if falloff > 0.0 { (1.0 - distance / falloff).max(0.0) } else if distance > 0.0 { 0.0 } else { 1.0 };
+
+	(volume, pan)
+}
+
+/// The gains of the left and right speakers for the stereo panning in
+/// [-1, 1]. They follow the sin/cos law, scaled so that the louder side is
+/// at full gain: centered sounds play as loud as unpanned ones, and panned
+/// ones fade out on the far side only.
+fn panning_gains(pan: f64) -> (u8, u8) {
+	let angle = (pan.max(-1.0).min(1.0) + 1.0) * ::std::f64::consts::PI / 4.0;
+	let (left, right) = (angle.cos().max(0.0), angle.sin().max(0.0));
+	let louder = left.max(right);
+
+	((left / louder * 255.0).round() as u8, (right / louder * 255.0).round() as u8)
+}
+
+
+/// Plays sounds through the volume buses, within the limits given for every
+/// sound, and keeps track of the channels they play on.
+///
+/// The sounds are played by a backend: SDL_mixer, or nothing at all.
+pub struct Audio {
+	backend: Box<AudioBackend>,
+	settings: AudioSettings,
+	settings_path: Option<PathBuf>,
+
+	/// The voice playing on every allocated channel, if any.
+	voices: Vec<Option<Voice>>,
+	/// The number of channels, from the first one, which sound effects don't
+	/// play on.
+	reserved: usize,
+	limits: HashMap<usize, SoundLimits>,
+	/// The last time every sound was played at.
+	last_played: HashMap<usize, f64>,
+	variants: HashMap<usize, Variants>,
+	rng: XorShiftRng,
+
+	/// The area of the world which is heard centered and at full volume.
+	listener: Rectangle,
+	falloff: f64,
+
+	time: f64,
+}
+
+impl Audio {
+	pub fn new(mut backend: Box<AudioBackend>) -> Audio {
+		let channels = backend.allocate_channels(INITIAL_CHANNELS);
+
+		let mut audio = Audio {
+			backend: backend,
+			settings: AudioSettings::default(),
+			settings_path: None,
+
+			voices: vec![None; channels],
+			reserved: 0,
+			limits: HashMap::new(),
+			last_played: HashMap::new(),
+			variants: HashMap::new(),
+			rng: rand::weak_rng(),
+
+			listener: Rectangle { x: 0.0, y: 0.0, w: 1.0, h: 1.0 },
+			falloff: DEFAULT_FALLOFF,
+
+			time: 0.0,
+		};
+		audio.apply_music_volume();
+		audio
+	}
+
+	/// Loads the settings from the file located at the given path, if there's
+	/// one, and saves them there whenever they change.
+	pub fn persist_to(&mut self, path: &str) {
+		let path = PathBuf::from(path);
+
+		if path.exists() {
+			match AudioSettings::load(&path) {
+				Ok(settings) => self.settings = settings,
+				Err(e) => println!("Can't load audio settings: {}", e)
+			}
+		}
+		self.settings_path = Some(path);
+		self.apply_volumes();
+	}
+
+
+	pub fn settings(&self) -> &AudioSettings {
+		&self.settings
+	}
+
+	pub fn set_volume(&mut self, bus: Bus, volume: f64) {
+		let volume = volume.max(0.0).min(1.0);
+
+		match bus {
+			Bus::Master => self.settings.master = volume,
+			Bus::Music => self.settings.music = volume,
+			Bus::Sfx => self.settings.sfx = volume,
+		}
+		self.settings_changed();
+	}
+
+	pub fn is_muted(&self) -> bool {
+		self.settings.muted
+	}
+
+	pub fn set_muted(&mut self, muted: bool) {
+		self.settings.muted = muted;
+		self.settings_changed();
+	}
+
+	pub fn toggle_mute(&mut self) {
+		let muted = !self.settings.muted;
+		self.set_muted(muted);
+	}
+
+	/// Limits how often the sound may be played. Sounds without limits get
+	/// the default ones.
+	pub fn set_limits(&mut self, sound: &Handle<Sound>, limits: SoundLimits) {
+		self.limits.insert(sound.id(), limits);
+	}
+
+	/// Plays one of the variants whenever the sound is requested. The
+	/// variants share the limits of the sound.
+	pub fn set_variants(&mut self, sound: &Handle<Sound>, variants: Variants) {
+		self.variants.insert(sound.id(), variants);
+	}
+
+	/// Stops the sound, and forgets its limits and variants. Sounds are known
+	/// by the identity of their handle, which may be reused once they're
+	/// unloaded: this must be called beforehand.
+	pub fn forget(&mut self, sound: &Handle<Sound>) {
+		let id = sound.id();
+
+		for channel in self.reserved..self.voices.len() {
+			if self.voices[channel].as_ref().map_or(false, |voice| voice.sound == id) {
+				self.backend.halt(channel);
+				self.voices[channel] = None;
+			}
+		}
+		self.limits.remove(&id);
+		self.last_played.remove(&id);
+		self.variants.remove(&id);
+	}
+
+
+	/// Sets the area of the world heard centered and at full volume by
+	/// positional sounds, usually the part shown in the window.
+	pub fn set_listener(&mut self, listener: Rectangle) {
+		self.listener = listener;
+	}
+
+	/// Sets how far outside of the listener's area positional sounds fade out
+	/// completely, in pixels.
+	pub fn set_falloff(&mut self, falloff: f64) {
+		self.falloff = falloff.max(0.0);
+	}
+
+
+	/// Moves the clock of the audio forward, and frees the channels of the
+	/// sounds which are done playing.
+	pub fn update(&mut self, elapsed: f64) {
+		self.time += elapsed;
+		self.backend.update(elapsed);
+
+		for (channel, voice) in self.voices.iter_mut().enumerate() {
+			if voice.is_some() && !self.backend.is_playing(channel) {
+				*voice = None;
+			}
+		}
+	}
+
+	/// Plays a sound effect once, at full volume.
+	pub fn play(&mut self, sound: &Handle<Sound>) -> Option<usize> {
+		self.play_at_volume(sound, 1.0)
+	}
+
+	/// Plays a sound effect once, at the given volume in [0, 1]. Returns the
+	/// channel it plays on, unless the limits of the sound prevented it.
+	pub fn play_at_volume(&mut self, sound: &Handle<Sound>, volume: f64) -> Option<usize> {
+		self.play_voice(sound, volume, 0.0)
+	}
+
+	/// Plays a sound effect once, panned and attenuated according to where it
+	/// happens, relative to the listener.
+	pub fn play_at(&mut self, sound: &Handle<Sound>, position: (f64, f64)) -> Option<usize> {
+		self.play_at_position_volume(sound, position, 1.0)
+	}
+
+	pub fn play_at_position_volume(&mut self, sound: &Handle<Sound>, position: (f64, f64), volume: f64) -> Option<usize> {
+		let (gain, pan) = spatialize(position, self.listener, self.falloff);
+
+		//? Sounds too far away to be heard don't take a voice from the
+		//? audible ones.
+		if gain <= 0.0 {
+			return None;
+		}
+		self.play_voice(sound, volume * gain, pan)
+	}
+
+	fn play_voice(&mut self, sound: &Handle<Sound>, volume: f64, pan: f64) -> Option<usize> {
+		let id = sound.id();
+		let limits = self.limits.get(&id).cloned().unwrap_or(SoundLimits::default());
+
+		if let Some(&last) = self.last_played.get(&id) {
+			if self.time - last < limits.cooldown {
+				return None;
+			}
+		}
+		let channel = tryo!(self.reserve_channel(id, &limits));
+		let (variant, jitter) = match self.variants.get_mut(&id) {
+			Some(variants) => variants.pick(&mut self.rng),
+			None => (sound.clone(), 1.0)
+		};
+		let volume = (volume * jitter).max(0.0).min(1.0);
+		let sfx_volume = self.sfx_volume(volume);
+
+		if let Err(e) = self.backend.play(channel, &variant.borrow(), 0, sfx_volume, panning_gains(pan)) {
+			println!("Can't play sound: {}", e);
+			return None;
+		}
+		self.voices[channel] = Some(Voice { sound: id, started: self.time, volume: volume });
+		self.last_played.insert(id, self.time);
+
+		Some(channel)
+	}
+
+	/// Stops every sound effect.
+	pub fn stop_all(&mut self) {
+		for channel in self.reserved..self.voices.len() {
+			self.backend.halt(channel);
+			self.voices[channel] = None;
+		}
+	}
+
+
+	/// Sets the first `count` channels aside, for sounds which are played
+	/// on their own terms rather than as effects, and returns them. The
+	/// effects playing there are stopped.
+	pub fn reserve_channels(&mut self, count: usize) -> Vec<usize> {
+		let required = (count + CHANNELS_GROWTH).min(MAX_CHANNELS);
+
+		if self.voices.len() < required {
+			let allocated = self.backend.allocate_channels(required);
+			self.voices.resize(allocated, None);
+		}
+		let count = count.min(self.voices.len());
+
+		for channel in 0..count {
+			if self.voices[channel].take().is_some() {
+				self.backend.halt(channel);
+			}
+		}
+		self.reserved = count;
+
+		(0..count).collect()
+	}
+
+	/// Loops the sounds forever on reserved channels, starting them on the
+	/// same sample. Every sound comes with its channel and its volume, which
+	/// the buses don't apply to.
+	pub fn loop_reserved(&mut self, sounds: &[(usize, Handle<Sound>, f64)]) {
+		let reserved = self.reserved;
+
+		self.backend.lock();
+
+		for &(channel, ref sound, volume) in sounds.iter().filter(|&&(channel, _, _)| channel < reserved) {
+			if let Err(e) = self.backend.play(channel, &sound.borrow(), -1, volume, panning_gains(0.0)) {
+				println!("Can't play {}: {}", sound.borrow().name(), e);
+			}
+		}
+		self.backend.unlock();
+	}
+
+	/// Sets the volume of a reserved channel, which the buses don't apply to.
+	pub fn set_reserved_volume(&mut self, channel: usize, volume: f64) {
+		if channel < self.reserved {
+			self.backend.set_volume(channel, volume);
+		}
+	}
+
+	/// Stops the reserved channels, and gives them back to sound effects.
+	pub fn release_channels(&mut self) {
+		for channel in 0..self.reserved {
+			self.backend.halt(channel);
+		}
+		self.reserved = 0;
+	}
+
+
+	/// Finds the channel a new voice of the sound plays on, stopping another
+	/// voice if necessary.
+	fn reserve_channel(&mut self, sound: usize, limits: &SoundLimits) -> Option<usize> {
+		let playing: Vec<usize> = (0..self.voices.len())
+			.filter(|&channel| self.voices[channel].map_or(false, |voice| voice.sound == sound))
+			.collect();
+
+		if playing.len() >= limits.max_voices {
+			let victim = tryo!(pick_victim(&self.voices, &playing, limits.steal));
+
+			self.backend.halt(victim);
+			return Some(victim);
+		}
+		if let Some(free) = (self.reserved..self.voices.len()).find(|&channel| self.voices[channel].is_none()) {
+			return Some(free);
+		}
+		//? Every channel is busy: we allocate more of them, up to a point past
+		//? which the oldest sound gives way.
+		let count = self.voices.len();
+
+		if count < MAX_CHANNELS {
+			let count = self.backend.allocate_channels((count + CHANNELS_GROWTH).min(MAX_CHANNELS));
+
+			if count > self.voices.len() {
+				self.voices.resize(count, None);
+				return Some(count - 1);
+			}
+		}
+		let all: Vec<usize> = (self.reserved..self.voices.len()).collect();
+		let victim = tryo!(pick_victim(&self.voices, &all, Steal::Oldest));
+
+		self.backend.halt(victim);
+		Some(victim)
+	}
+
+	/// The volume a sound effect is played at, given its own volume.
+	fn sfx_volume(&self, volume: f64) -> f64 {
+		volume * self.settings.effective_volume(Bus::Sfx)
+	}
+
+	fn apply_music_volume(&mut self) {
+		let volume = self.settings.effective_volume(Bus::Music);
+		self.backend.set_music_volume(volume);
+	}
+
+	fn apply_volumes(&mut self) {
+		self.apply_music_volume();
+
+		for channel in 0..self.voices.len() {
+			if let Some(voice) = self.voices[channel] {
+				let volume = self.sfx_volume(voice.volume);
+				self.backend.set_volume(channel, volume);
+			}
+		}
+	}
+
+	fn settings_changed(&mut self) {
+		self.apply_volumes();
+
+		if let Some(ref path) = self.settings_path {
+			if let Err(e) = self.settings.save(path) {
+				println!("Can't save audio settings: {}", e);
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{panning_gains, pick_victim, spatialize, Audio, AudioSettings, Bus, SoundLimits, Steal, Voice};
+	use phi::assets::Handle;
+	use phi::backend::{AudioLog, NullAudio, Sound};
+	use phi::data::Rectangle;
+
+
+	#[test]
+	fn settings_round_trip() {
+		let settings = AudioSettings { master: 0.5, music: 0.25, sfx: 1.0, muted: true };
+
+		assert_eq!(AudioSettings::parse(&settings.to_string()).unwrap(), settings);
+		assert_eq!(AudioSettings::parse("sfx 0.5").unwrap().music, AudioSettings::default().music);
+		assert!(AudioSettings::parse("volume 11").is_err());
+		assert_eq!(settings.effective_volume(Bus::Sfx), 0.0);
+	}
+
+	#[test]
+	fn sounds_are_panned_and_attenuated() {
+		let listener = Rectangle { x: 0.0, y: 0.0, w: 800.0, h: 600.0 };
+
+		assert_eq!(spatialize((400.0, 300.0), listener, 400.0), (1.0, 0.0));
+		assert_eq!(spatialize((800.0, 0.0), listener, 400.0), (1.0, 1.0));
+		assert_eq!(spatialize((1000.0, 300.0), listener, 400.0), (0.5, 1.0));
+		assert_eq!(spatialize((-400.0, 300.0), listener, 400.0), (0.0, -1.0));
+		assert_eq!(panning_gains(0.0), (255, 255));
+		assert_eq!(panning_gains(1.0), (0, 255));
+		assert_eq!(panning_gains(0.5), (106, 255));
+		assert_eq!(panning_gains(-0.5), (255, 106));
+	}
+
+	#[test]
+	fn victims_are_picked_by_age_or_volume() {
+		let voices = vec![
+			Some(Voice { sound: 1, started: 2.0, volume: 0.2 }),
+			None,
+			Some(Voice { sound: 1, started: 1.0, volume: 0.9 }),
+		];
+
+		assert_eq!(pick_victim(&voices, &[0, 2], Steal::Oldest), Some(2));
+		assert_eq!(pick_victim(&voices, &[0, 2], Steal::Quietest), Some(0));
+		assert_eq!(pick_victim(&voices, &[0, 2], Steal::None), None);
+	}
+
+	#[test]
+	fn sounds_are_recorded_within_their_limits() {
+		let log = AudioLog::new();
+		let mut audio = Audio::new(Box::new(NullAudio::recording(log.clone())));
+		let boom = Handle::new(Sound::silent("boom"));
+
+		audio.set_limits(&boom, SoundLimits { max_voices: 2, steal: Steal::None, cooldown: 0.1 });
+		audio.play(&boom);
+		audio.play(&boom);
+		audio.update(0.25);
+		audio.play(&boom);
+
+		assert_eq!(log.count("boom"), 2);
+		assert_eq!(log.played()[1].time, 0.25);
+
+		let channels = audio.reserve_channels(1);
+		audio.loop_reserved(&[(channels[0], boom.clone(), 0.5)]);
+		assert!(log.played()[2].looping);
+	}
+
+	#[test]
+	fn forgotten_sounds_lose_their_limits() {
+		let log = AudioLog::new();
+		let mut audio = Audio::new(Box::new(NullAudio::recording(log.clone())));
+		let boom = Handle::new(Sound::silent("boom"));
+
+		audio.set_limits(&boom, SoundLimits { max_voices: 1, steal: Steal::None, cooldown: 1.0 });
+		audio.play(&boom);
+		audio.play(&boom);
+		assert_eq!(log.count("boom"), 1);
+
+		audio.forget(&boom);
+		audio.play(&boom);
+		assert_eq!(log.count("boom"), 2);
+	}
+}
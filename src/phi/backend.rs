@@ -0,0 +1,357 @@
+extern crate sdl2_sys;
+
+
+use phi::synth::SynthParams;
+
+use sdl2::mixer::{self, Chunk, LoaderRWops, Music, MAX_VOLUME};
+use sdl2::rwops::RWops;
+
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
+
+
+use self::sdl2_sys::audio::{SDL_LockAudio, SDL_UnlockAudio};
+
+
+/// Whether the mixer was opened, that is, whether sounds may be loaded.
+fn mixer_open() -> bool {
+	mixer::query_spec().is_ok()
+}
+
+
+/// A sound effect. Sounds loaded while there's no mixer hold no samples, but
+/// still go by their name, so that playing them can be traced.
+pub struct Sound {
+	name: String,
+	chunk: Option<Chunk>,
+}
+
+impl Sound {
+	/// Loads the sound file, or synthesizes the sound described by the `.sfx`
+	/// file.
+	pub fn load(path: &Path) -> Result<Sound, String> {
+		let name = path.display().to_string();
+
+		if path.extension().map_or(false, |extension| extension == "sfx") {
+			return SynthParams::load(path).map_err(|e| e.to_string()).and_then(|params| params.to_sound(&name));
+		}
+		if !mixer_open() {
+			//? Even silent, a missing sound is a mistake worth reporting.
+			return if path.exists() { Ok(Sound::silent(&name)) } else { Err(format!("Can't find sound {}", name)) };
+		}
+		Chunk::from_file(path).map(|chunk| Sound::from_chunk(&name, chunk))
+	}
+
+	/// Loads the sound from the content of a WAV file.
+	pub fn from_wav(name: &str, wav: &[u8]) -> Result<Sound, String> {
+		if !mixer_open() {
+			return Ok(Sound::silent(name));
+		}
+		let rwops = try!(RWops::from_bytes(wav));
+
+		rwops.load_wav().map(|chunk| Sound::from_chunk(name, chunk))
+	}
+
+	pub fn from_chunk(name: &str, chunk: Chunk) -> Sound {
+		Sound {
+			name: name.to_string(),
+			chunk: Some(chunk),
+		}
+	}
+
+	pub fn silent(name: &str) -> Sound {
+		Sound {
+			name: name.to_string(),
+			chunk: None,
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// The samples of the sound, unless it's silent.
+	pub fn chunk(&self) -> Option<&Chunk> {
+		self.chunk.as_ref()
+	}
+}
+
+
+thread_local! {
+	/// The address of the music last played, as SDL_mixer only tells whether
+	/// some music is playing, not which.
+	static PLAYING_MUSIC: Cell<usize> = Cell::new(0)
+}
+
+
+/// A music track, streamed from its file. Like sounds, tracks loaded while
+/// there's no mixer are silent.
+pub struct Track {
+	name: String,
+	music: Option<Music<'static>>,
+}
+
+impl Track {
+	pub fn load(path: &Path) -> Result<Track, String> {
+		let name = path.display().to_string();
+
+		if !mixer_open() {
+			return if path.exists() { Ok(Track::silent(&name)) } else { Err(format!("Can't find music {}", name)) };
+		}
+		Music::from_file(path).map(|music| Track { name: name, music: Some(music) })
+	}
+
+	pub fn silent(name: &str) -> Track {
+		Track {
+			name: name.to_string(),
+			music: None,
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn is_silent(&self) -> bool {
+		self.music.is_none()
+	}
+
+	/// Whether this very track is the music being played.
+	pub fn is_playing(&self) -> bool {
+		self.id().map_or(false, |id| Music::is_playing() && PLAYING_MUSIC.with(|playing| playing.get()) == id)
+	}
+
+	/// Plays the track `loops` times, forever if -1.
+	pub fn play(&self, loops: i32) -> Result<(), String> {
+		self.started(|music| music.play(loops))
+	}
+
+	/// Plays the track, fading it in over `ms` milliseconds.
+	pub fn fade_in(&self, loops: i32, ms: i32) -> Result<(), String> {
+		self.started(|music| music.fade_in(loops, ms))
+	}
+
+	/// Plays the track from the given position, in seconds, fading it in.
+	pub fn fade_in_from_pos(&self, loops: i32, ms: i32, position: f64) -> Result<(), String> {
+		self.started(|music| music.fade_in_from_pos(loops, ms, position))
+	}
+
+	fn id(&self) -> Option<usize> {
+		self.music.as_ref().map(|music| music.raw as usize)
+	}
+
+	/// Starts the music with `play`, and remembers it's the one playing.
+	fn started<F>(&self, play: F) -> Result<(), String> where F: FnOnce(&Music<'static>) -> Result<(), String> {
+		let music = match self.music {
+			Some(ref music) => music,
+			None => return Ok(())
+		};
+		try!(play(music));
+		PLAYING_MUSIC.with(|playing| playing.set(music.raw as usize));
+		Ok(())
+	}
+}
+
+impl Drop for Track {
+	fn drop(&mut self) {
+		//? Another track may be allocated at the same address.
+		if let Some(id) = self.id() {
+			PLAYING_MUSIC.with(|playing| if playing.get() == id { playing.set(0) });
+		}
+	}
+}
+
+
+/// Where sound effects are played. The channels are numbered from zero, and
+/// volumes are in [0, 1].
+pub trait AudioBackend {
+	/// Moves the clock of the backend forward, in seconds.
+	fn update(&mut self, _elapsed: f64) {}
+
+	/// Allocates the given number of channels, and returns how many there
+	/// actually are.
+	fn allocate_channels(&mut self, count: usize) -> usize;
+
+	/// Plays the sound on the channel `loops` more times after the first,
+	/// forever if -1, with the stereo gains of the left and right speakers.
+	fn play(&mut self, channel: usize, sound: &Sound, loops: i32, volume: f64, panning: (u8, u8)) -> Result<(), String>;
+
+	fn is_playing(&self, channel: usize) -> bool;
+	fn halt(&mut self, channel: usize);
+	fn set_volume(&mut self, channel: usize, volume: f64);
+	fn set_music_volume(&mut self, volume: f64);
+
+	/// Holds the playback between `lock` and `unlock`, so that the sounds
+	/// played in between start on the same sample.
+	fn lock(&mut self) {}
+	fn unlock(&mut self) {}
+}
+
+
+/// Plays the sounds through SDL_mixer, which must have been opened.
+pub struct SdlMixer;
+
+impl AudioBackend for SdlMixer {
+	fn allocate_channels(&mut self, count: usize) -> usize {
+		mixer::allocate_channels(count as i32) as usize
+	}
+
+	fn play(&mut self, channel: usize, sound: &Sound, loops: i32, volume: f64, panning: (u8, u8)) -> Result<(), String> {
+		let chunk = match sound.chunk() {
+			Some(chunk) => chunk,
+			None => return Ok(())
+		};
+		let channel = mixer::channel(channel as i32);
+
+		//? Panning is an effect of the channel, which outlives the sound: we
+		//? set it for every sound, centered ones included.
+		if let Err(e) = channel.set_panning(panning.0, panning.1) {
+			println!("Can't pan sound: {}", e);
+		}
+		channel.set_volume(to_mixer_volume(volume));
+		channel.play(chunk, loops).map(|_| ())
+	}
+
+	fn is_playing(&self, channel: usize) -> bool {
+		mixer::channel(channel as i32).is_playing()
+	}
+
+	fn halt(&mut self, channel: usize) {
+		mixer::channel(channel as i32).halt();
+	}
+
+	fn set_volume(&mut self, channel: usize, volume: f64) {
+		mixer::channel(channel as i32).set_volume(to_mixer_volume(volume));
+	}
+
+	fn set_music_volume(&mut self, volume: f64) {
+		Music::set_volume(to_mixer_volume(volume));
+	}
+
+	fn lock(&mut self) {
+		unsafe { SDL_LockAudio(); }
+	}
+
+	fn unlock(&mut self) {
+		unsafe { SDL_UnlockAudio(); }
+	}
+}
+
+fn to_mixer_volume(volume: f64) -> i32 {
+	(volume.max(0.0).min(1.0) * MAX_VOLUME as f64).round() as i32
+}
+
+
+/// A sound requested from the null backend.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Played {
+	/// The time it was requested at, in seconds since the backend started.
+	pub time: f64,
+	pub sound: String,
+	pub channel: usize,
+	pub volume: f64,
+	pub looping: bool,
+}
+
+/// The sounds requested from a null backend, shared with whoever wants to
+/// know about them.
+#[derive(Clone)]
+pub struct AudioLog {
+	played: Rc<RefCell<Vec<Played>>>,
+}
+
+impl AudioLog {
+	pub fn new() -> AudioLog {
+		AudioLog {
+			played: Rc::new(RefCell::new(vec![])),
+		}
+	}
+
+	pub fn played(&self) -> Vec<Played> {
+		self.played.borrow().clone()
+	}
+
+	/// The number of times the sound was requested.
+	pub fn count(&self, sound: &str) -> usize {
+		self.played.borrow().iter().filter(|played| played.sound == sound).count()
+	}
+
+	pub fn was_played(&self, sound: &str) -> bool {
+		self.count(sound) > 0
+	}
+
+	pub fn clear(&self) {
+		self.played.borrow_mut().clear();
+	}
+}
+
+
+/// Plays nothing, for machines without an audio device and for tests. The
+/// sounds requested may be recorded, along with when they were.
+///
+/// Sounds are over as soon as they're played, unless they loop forever.
+pub struct NullAudio {
+	log: Option<AudioLog>,
+	/// Whether every channel loops a sound.
+	looping: Vec<bool>,
+	time: f64,
+}
+
+impl NullAudio {
+	pub fn new() -> NullAudio {
+		NullAudio {
+			log: None,
+			looping: vec![],
+			time: 0.0,
+		}
+	}
+
+	/// Creates a backend which records the sounds requested into the `log`.
+	pub fn recording(log: AudioLog) -> NullAudio {
+		NullAudio { log: Some(log), ..NullAudio::new() }
+	}
+}
+
+impl AudioBackend for NullAudio {
+	fn update(&mut self, elapsed: f64) {
+		self.time += elapsed;
+	}
+
+	fn allocate_channels(&mut self, count: usize) -> usize {
+		self.looping.resize(count, false);
+		count
+	}
+
+	fn play(&mut self, channel: usize, sound: &Sound, loops: i32, volume: f64, _: (u8, u8)) -> Result<(), String> {
+		if channel >= self.looping.len() {
+			return Err(format!("No channel {}!", channel));
+		}
+		self.looping[channel] = loops < 0;
+
+		if let Some(ref log) = self.log {
+			log.played.borrow_mut().push(Played {
+				time: self.time,
+				sound: sound.name().to_string(),
+				channel: channel,
+				volume: volume,
+				looping: loops < 0,
+			});
+		}
+		Ok(())
+	}
+
+	fn is_playing(&self, channel: usize) -> bool {
+		self.looping.get(channel).cloned().unwrap_or(false)
+	}
+
+	fn halt(&mut self, channel: usize) {
+		if let Some(looping) = self.looping.get_mut(channel) {
+			*looping = false;
+		}
+	}
+
+	fn set_volume(&mut self, _: usize, _: f64) {}
+
+	fn set_music_volume(&mut self, _: f64) {}
+}
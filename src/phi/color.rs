@@ -0,0 +1,507 @@
+use phi::descr::{Line, Lines};
+use phi::tween::Lerp;
+
+use sdl2::pixels::Color;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+
+/// A color given by its hue in degrees, in [0, 360), along with its
+/// saturation, value and alpha, in [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsv {
+	pub h: f64,
+	pub s: f64,
+	pub v: f64,
+	pub a: f64,
+}
+
+impl Hsv {
+	/// Creates an opaque color.
+	pub fn new(h: f64, s: f64, v: f64) -> Hsv {
+		Hsv { h: wrap_hue(h), s: s, v: v, a: 1.0 }
+	}
+
+	pub fn from_color(color: Color) -> Hsv {
+		let (r, g, b, a) = unit_rgba(color);
+		let max = r.max(g).max(b);
+		let chroma = max - r.min(g).min(b);
+
+		Hsv {
+			h: hue(r, g, b, max, chroma),
+			s: if max > 0.0 { chroma / max } else { 0.0 },
+			v: max,
+			a: a,
+		}
+	}
+
+	pub fn to_color(&self) -> Color {
+		let chroma = self.v * self.s;
+
+		from_hue(self.h, chroma, self.v - chroma, self.a)
+	}
+}
+
+impl Lerp for Hsv {
+	/// Goes around the hue wheel the shortest way.
+	fn lerp(&self, other: &Hsv, t: f64) -> Hsv {
+		Hsv {
+			h: lerp_hue(self.h, other.h, t),
+			s: self.s.lerp(&other.s, t),
+			v: self.v.lerp(&other.v, t),
+			a: self.a.lerp(&other.a, t),
+		}
+	}
+}
+
+
+/// A color given by its hue in degrees, in [0, 360), along with its
+/// saturation, lightness and alpha, in [0, 1].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hsl {
+	pub h: f64,
+	pub s: f64,
+	pub l: f64,
+	pub a: f64,
+}
+
+impl Hsl {
+	/// Creates an opaque color.
+	pub fn new(h: f64, s: f64, l: f64) -> Hsl {
+		Hsl { h: wrap_hue(h), s: s, l: l, a: 1.0 }
+	}
+
+	pub fn from_color(color: Color) -> Hsl {
+		let (r, g, b, a) = unit_rgba(color);
+		let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+		let chroma = max - min;
+		let l = (max + min) / 2.0;
+
+		Hsl {
+			h: hue(r, g, b, max, chroma),
+			s: if l > 0.0 && l < 1.0 { chroma / (1.0 - (2.0 * l - 1.0).abs()) } else { 0.0 },
+			l: l,
+			a: a,
+		}
+	}
+
+	pub fn to_color(&self) -> Color {
+		let chroma = (1.0 - (2.0 * self.l - 1.0).abs()) * self.s;
+
+		from_hue(self.h, chroma, self.l - chroma / 2.0, self.a)
+	}
+}
+
+impl Lerp for Hsl {
+	/// Goes around the hue wheel the shortest way.
+	fn lerp(&self, other: &Hsl, t: f64) -> Hsl {
+		Hsl {
+			h: lerp_hue(self.h, other.h, t),
+			s: self.s.lerp(&other.s, t),
+			l: self.l.lerp(&other.l, t),
+			a: self.a.lerp(&other.a, t),
+		}
+	}
+}
+
+
+fn wrap_hue(h: f64) -> f64 {
+	let h = h % 360.0;
+	if h < 0.0 { h + 360.0 } else { h }
+}
+
+fn lerp_hue(from: f64, to: f64, t: f64) -> f64 {
+	let mut delta = (to - from) % 360.0;
+
+	if delta > 180.0 {
+		delta -= 360.0;
+	} else if delta < -180.0 {
+		delta += 360.0;
+	}
+	wrap_hue(from + delta * t)
+}
+
+fn unit_rgba(color: Color) -> (f64, f64, f64, f64) {
+	let (r, g, b, a) = color.rgba();
+	(r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0, a as f64 / 255.0)
+}
+
+fn to_channel(value: f64) -> u8 {
+	(value.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+/// The hue of the color, given its largest channel and its chroma.
+fn hue(r: f64, g: f64, b: f64, max: f64, chroma: f64) -> f64 {
+	if chroma == 0.0 {
+		return 0.0;
+	}
+	let sector = if max == r { ((g - b) / chroma) % 6.0 }
+		else if max == g { (b - r) / chroma + 2.0 }
+		else { (r - g) / chroma + 4.0 };
+
+	wrap_hue(60.0 * sector)
+}
+
+/// Creates a color from its channels. Opaque colors are made `RGB`, as
+/// `Color::RGBA(r, g, b, 255)` doesn't compare equal to `Color::RGB(r, g, b)`.
+pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+	if a == 255 { Color::RGB(r, g, b) } else { Color::RGBA(r, g, b, a) }
+}
+
+/// Builds a color from its hue and chroma, `offset` being added to every
+/// channel to match its lightness or value.
+fn from_hue(h: f64, chroma: f64, offset: f64, alpha: f64) -> Color {
+	//? The hue wheel is split into six sectors, in which one channel is at
+	//? its largest, one at its smallest, and the other one goes in between.
+	let sector = wrap_hue(h) / 60.0;
+	let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+	let (r, g, b) = match sector as u32 {
+		0 => (chroma, x, 0.0),
+		1 => (x, chroma, 0.0),
+		2 => (0.0, chroma, x),
+		3 => (0.0, x, chroma),
+		4 => (x, 0.0, chroma),
+		_ => (chroma, 0.0, x),
+	};
+	rgba(to_channel(r + offset), to_channel(g + offset), to_channel(b + offset), to_channel(alpha))
+}
+
+
+/// Converts a channel from sRGB, as stored in images and on screen, to linear
+/// light intensity, in [0, 1].
+pub fn srgb_to_linear(channel: u8) -> f64 {
+	let c = channel as f64 / 255.0;
+
+	if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Converts a linear light intensity, in [0, 1], back to an sRGB channel.
+pub fn linear_to_srgb(intensity: f64) -> u8 {
+	let c = intensity.max(0.0).min(1.0);
+
+	to_channel(if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 })
+}
+
+/// Blends two colors in linear light, which avoids the dark band the plain
+/// blend of sRGB channels (`Lerp`) shows between saturated colors.
+pub fn mix_linear(from: Color, to: Color, t: f64) -> Color {
+	let (r_a, g_a, b_a, a_a) = from.rgba();
+	let (r_b, g_b, b_b, a_b) = to.rgba();
+	let channel = |a: u8, b: u8| linear_to_srgb(srgb_to_linear(a).lerp(&srgb_to_linear(b), t));
+
+	rgba(channel(r_a, r_b), channel(g_a, g_b), channel(b_a, b_b), (a_a as f64).lerp(&(a_b as f64), t).round() as u8)
+}
+
+
+/// How a gradient blends its stops together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+	/// Blends the sRGB channels, as most image editors do.
+	Srgb,
+	/// Blends in linear light, which is gamma-correct.
+	Linear,
+	/// Goes around the hue wheel, keeping colors saturated.
+	Hsv,
+}
+
+impl Interpolation {
+	pub fn mix(&self, from: Color, to: Color, t: f64) -> Color {
+		match *self {
+			Interpolation::Srgb => from.lerp(&to, t),
+			Interpolation::Linear => mix_linear(from, to, t),
+			Interpolation::Hsv => Hsv::from_color(from).lerp(&Hsv::from_color(to), t).to_color(),
+		}
+	}
+}
+
+
+/// Colors placed along [0, 1], blended in between.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+	stops: Vec<(f64, Color)>,
+	interpolation: Interpolation,
+}
+
+impl Gradient {
+	/// Creates a gradient through the given `(position, color)` stops, blended
+	/// in sRGB. Panics if there's none, or if a position is NaN.
+	pub fn new(stops: Vec<(f64, Color)>) -> Gradient {
+		if stops.is_empty() {
+			panic!("Passed no stops to Gradient::new");
+		}
+		if stops.iter().any(|stop| stop.0.is_nan()) {
+			panic!("Passed a NaN stop to Gradient::new");
+		}
+		let mut stops = stops;
+
+		stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+		Gradient { stops: stops, interpolation: Interpolation::Srgb }
+	}
+
+	/// Creates a gradient going through the given colors, evenly spaced.
+	pub fn even(colors: &[Color]) -> Gradient {
+		let last = (colors.len().max(2) - 1) as f64;
+
+		Gradient::new(colors.iter().enumerate().map(|(i, &color)| (i as f64 / last, color)).collect())
+	}
+
+	/// Every hue, from red back to red.
+	pub fn rainbow() -> Gradient {
+		Gradient::even(&[
+			Color::RGB(255, 0, 0), Color::RGB(255, 255, 0), Color::RGB(0, 255, 0),
+			Color::RGB(0, 255, 255), Color::RGB(0, 0, 255), Color::RGB(255, 0, 255),
+			Color::RGB(255, 0, 0)])
+	}
+
+	pub fn interpolation(mut self, interpolation: Interpolation) -> Gradient {
+		self.interpolation = interpolation;
+		self
+	}
+
+	pub fn stops(&self) -> &[(f64, Color)] {
+		&self.stops
+	}
+
+
+	/// The color at `t`, clamped to [0, 1]. Non-finite values of `t` give the
+	/// first color.
+	pub fn sample(&self, t: f64) -> Color {
+		let first = self.stops[0];
+		let last = self.stops[self.stops.len() - 1];
+
+		if !t.is_finite() || t <= first.0 {
+			return first.1;
+		}
+		if t >= last.0 {
+			return last.1;
+		}
+		let next = self.stops.iter().position(|stop| stop.0 > t).unwrap();
+		let (from, to) = (self.stops[next - 1], self.stops[next]);
+
+		self.interpolation.mix(from.1, to.1, (t - from.0) / (to.0 - from.0))
+	}
+
+	/// The color at the given time, going through the whole gradient once
+	/// every `period` seconds. The first color is kept unless the period is
+	/// positive.
+	pub fn sample_cycle(&self, time: f64, period: f64) -> Color {
+		if period <= 0.0 {
+			return self.stops[0].1;
+		}
+		let t = (time / period) % 1.0;
+		self.sample(if t < 0.0 { t + 1.0 } else { t })
+	}
+
+	/// Like `sample_cycle`, but goes back and forth along the gradient.
+	pub fn sample_pingpong(&self, time: f64, period: f64) -> Color {
+		if period <= 0.0 {
+			return self.stops[0].1;
+		}
+		let t = ((time / period) % 2.0).abs();
+		self.sample(if t > 1.0 { 2.0 - t } else { t })
+	}
+}
+
+
+/// Named colors and gradients, shared by the effects and themes which use
+/// them. Loaded from plain text files, with one statement per line (`#`
+/// starts a comment):
+///
+///     rgb <name> <r> <g> <b> [<a>]
+///     hex <name> <rrggbb or rrggbbaa>
+///     hsv <name> <h> <s> <v> [<a>]
+///     hsl <name> <h> <s> <l> [<a>]
+///     gradient <name> [srgb|linear|hsv] <position> <color>...
+///
+/// Channels go from 0 to 255, hues are in degrees, other components (as well
+/// as the positions of gradient stops) go from 0 to 1. Gradient stops refer
+/// to colors declared above them, or are given as hexadecimal values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+	colors: HashMap<String, Color>,
+	gradients: HashMap<String, Gradient>,
+}
+
+impl Palette {
+	pub fn new() -> Palette {
+		Palette {
+			colors: HashMap::new(),
+			gradients: HashMap::new(),
+		}
+	}
+
+	pub fn load(path: &Path) -> Result<Palette, io::Error> {
+		let mut source = String::new();
+
+		try!(try!(File::open(path)).read_to_string(&mut source));
+
+		Palette::parse(&source)
+	}
+
+	pub fn parse(source: &str) -> Result<Palette, io::Error> {
+		let mut palette = Palette::new();
+
+		for mut line in Lines::new(source, "palette") {
+			let statement = line.statement();
+			let name = try!(line.expect("name")).to_string();
+			let args = line.rest();
+
+			match statement {
+				"rgb" => {
+					let channels = try!(parse_components(&line, &args));
+					let alpha = channels.get(3).cloned().unwrap_or(255.0);
+
+					palette.colors.insert(name, rgba(
+						channels[0].max(0.0).min(255.0) as u8,
+						channels[1].max(0.0).min(255.0) as u8,
+						channels[2].max(0.0).min(255.0) as u8,
+						alpha.max(0.0).min(255.0) as u8));
+				},
+				"hex" => {
+					let color = try!(args.first().and_then(|hex| parse_hex(hex))
+						.ok_or(line.error("hexadecimal color expected")));
+
+					palette.colors.insert(name, color);
+				},
+				"hsv" | "hsl" => {
+					let components = try!(parse_components(&line, &args));
+					let alpha = components.get(3).cloned().unwrap_or(1.0);
+					let color = if statement == "hsv" {
+						Hsv { a: alpha, .. Hsv::new(components[0], components[1], components[2]) }.to_color()
+					} else {
+						Hsl { a: alpha, .. Hsl::new(components[0], components[1], components[2]) }.to_color()
+					};
+					palette.colors.insert(name, color);
+				},
+				"gradient" => {
+					let (interpolation, args) = match args.first() {
+						Some(&"srgb") => (Interpolation::Srgb, &args[1..]),
+						Some(&"linear") => (Interpolation::Linear, &args[1..]),
+						Some(&"hsv") => (Interpolation::Hsv, &args[1..]),
+						_ => (Interpolation::Srgb, &args[..]),
+					};
+					if args.is_empty() || args.len() % 2 != 0 {
+						return Err(line.error("pairs of positions and colors expected"));
+					}
+					let mut stops = vec![];
+
+					for stop in args.chunks(2) {
+						//? NaN can't be sorted, and infinite positions can't be
+						//? blended from: palettes are edited while the game runs,
+						//? so we report them rather than crash.
+						let position = match stop[0].parse::<f64>() {
+							Ok(position) if position.is_finite() => position,
+							_ => return Err(line.error(&format!("malformed position '{}'", stop[0])))
+						};
+						let color = try!(palette.colors.get(stop[1]).cloned().or_else(|| parse_hex(stop[1]))
+							.ok_or(line.error(&format!("unknown color '{}'", stop[1]))));
+
+						stops.push((position, color));
+					}
+					palette.gradients.insert(name, Gradient::new(stops).interpolation(interpolation));
+				},
+				_ => return Err(line.error(&format!("unknown statement '{}'", statement)))
+			}
+		}
+		Ok(palette)
+	}
+
+
+	pub fn color(&self, name: &str) -> Option<Color> {
+		self.colors.get(name).cloned()
+	}
+
+	/// Returns the named color, or `fallback` if the palette lacks it, so
+	/// that a palette missing an entry doesn't break the game.
+	pub fn color_or(&self, name: &str, fallback: Color) -> Color {
+		self.color(name).unwrap_or(fallback)
+	}
+
+	pub fn gradient(&self, name: &str) -> Option<&Gradient> {
+		self.gradients.get(name)
+	}
+
+	pub fn set_color(&mut self, name: &str, color: Color) {
+		self.colors.insert(name.to_string(), color);
+	}
+
+	pub fn set_gradient(&mut self, name: &str, gradient: Gradient) {
+		self.gradients.insert(name.to_string(), gradient);
+	}
+}
+
+/// Parses three components, and an optional fourth one.
+fn parse_components(line: &Line, args: &[&str]) -> Result<Vec<f64>, io::Error> {
+	if args.len() < 3 || args.len() > 4 {
+		return Err(line.error("three or four components expected"));
+	}
+	args.iter().map(|arg| {
+		arg.parse().map_err(|_| line.error(&format!("malformed value '{}'", arg)))
+	}).collect()
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+	if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_digit(16)) {
+		return None;
+	}
+	let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+	let alpha = if hex.len() == 8 { channel(6) } else { 255 };
+
+	Some(rgba(channel(0), channel(2), channel(4), alpha))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sdl2::pixels::Color;
+
+
+	#[test]
+	fn hsv_and_hsl_round_trip() {
+		for &color in &[Color::RGB(255, 0, 0), Color::RGB(12, 200, 90), Color::RGB(97, 132, 162), Color::RGBA(0, 0, 0, 128)] {
+			assert_eq!(Hsv::from_color(color).to_color(), color);
+			assert_eq!(Hsl::from_color(color).to_color(), color);
+		}
+		assert_eq!(Hsv::new(120.0, 1.0, 1.0).to_color(), Color::RGB(0, 255, 0));
+		assert_eq!(Hsl::new(240.0, 1.0, 0.5).to_color(), Color::RGB(0, 0, 255));
+	}
+
+	#[test]
+	fn gradients_blend_their_stops() {
+		let gradient = Gradient::new(vec![(0.0, Color::RGB(255, 0, 0)), (1.0, Color::RGB(0, 0, 255))]);
+
+		assert_eq!(gradient.sample(-1.0), Color::RGB(255, 0, 0));
+		assert_eq!(gradient.sample(0.5), Color::RGB(128, 0, 128));
+		assert_eq!(gradient.clone().interpolation(Interpolation::Linear).sample(0.5), Color::RGB(188, 0, 188));
+		assert_eq!(gradient.interpolation(Interpolation::Hsv).sample(0.5), Color::RGB(255, 0, 255));
+		assert_eq!(Gradient::rainbow().sample_cycle(2.5, 3.0), Color::RGB(255, 0, 255));
+
+		assert_eq!(Gradient::rainbow().sample(::std::f64::NAN), Color::RGB(255, 0, 0));
+		assert_eq!(Gradient::rainbow().sample_cycle(2.5, 0.0), Color::RGB(255, 0, 0));
+		assert_eq!(Gradient::rainbow().sample_pingpong(2.5, 0.0), Color::RGB(255, 0, 0));
+	}
+
+	#[test]
+	fn palettes_parse() {
+		let palette = Palette::parse("
+			# The hot end of things
+			rgb flame 255 120 20
+			hex ember 781400cc
+			hsv sky 200 0.5 1
+			gradient fire linear 0 flame 1 ember
+		").unwrap();
+
+		assert_eq!(palette.color("flame"), Some(Color::RGB(255, 120, 20)));
+		assert_eq!(palette.color("ember"), Some(Color::RGBA(120, 20, 0, 204)));
+		assert_eq!(palette.color("sky"), Some(Color::RGB(128, 212, 255)));
+		assert_eq!(palette.gradient("fire").unwrap().sample(1.0), Color::RGBA(120, 20, 0, 204));
+		assert!(Palette::parse("gradient broken 0 nowhere").is_err());
+		assert!(Palette::parse("gradient broken nan 000000 1 ffffff").is_err());
+		assert!(Palette::parse("gradient broken 0 000000 inf ffffff").is_err());
+	}
+}
@@ -0,0 +1,151 @@
+use std::io;
+use std::iter::{Enumerate, Peekable};
+use std::str::{self, FromStr, SplitWhitespace};
+
+
+/// Reads the statements of a descriptor, such as an atlas or a palette. Those
+/// are plain text files, with one statement per line: a name followed by its
+/// arguments, separated by whitespace. `#` starts a comment, and empty lines
+/// are skipped.
+///
+/// ```text
+/// for mut line in Lines::new(source, "palette") {
+///     match line.statement() {
+///         "rgb" => { let name = try!(line.expect("name")); ... },
+///         statement => return Err(line.error(&format!("unknown statement '{}'", statement))),
+///     }
+/// }
+/// ```
+pub struct Lines<'a> {
+	/// What the source describes, as told by error messages.
+	kind: &'static str,
+	lines: Enumerate<str::Lines<'a>>,
+}
+
+impl<'a> Lines<'a> {
+	pub fn new(source: &'a str, kind: &'static str) -> Lines<'a> {
+		Lines {
+			kind: kind,
+			lines: source.lines().enumerate(),
+		}
+	}
+}
+
+impl<'a> Iterator for Lines<'a> {
+	type Item = Line<'a>;
+
+	fn next(&mut self) -> Option<Line<'a>> {
+		for (i, line) in &mut self.lines {
+			let mut tokens = line.split('#').next().unwrap().split_whitespace().peekable();
+
+			if let Some(statement) = tokens.next() {
+				return Some(Line {
+					kind: self.kind,
+					number: i + 1,
+					statement: statement,
+					tokens: tokens,
+				});
+			}
+		}
+		None
+	}
+}
+
+
+/// A statement of a descriptor. Iterating over it yields its arguments.
+pub struct Line<'a> {
+	kind: &'static str,
+	number: usize,
+	statement: &'a str,
+	tokens: Peekable<SplitWhitespace<'a>>,
+}
+
+impl<'a> Line<'a> {
+	/// The number of the line in the source, from 1.
+	pub fn number(&self) -> usize {
+		self.number
+	}
+
+	/// The first token of the line, which names the statement.
+	pub fn statement(&self) -> &'a str {
+		self.statement
+	}
+
+	/// The next argument, left to be read.
+	pub fn peek(&mut self) -> Option<&'a str> {
+		self.tokens.peek().cloned()
+	}
+
+	/// Reads the next argument, which must be there. `what` is reported as
+	/// expected otherwise.
+	pub fn expect(&mut self, what: &str) -> Result<&'a str, io::Error> {
+		match self.tokens.next() {
+			Some(token) => Ok(token),
+			None => Err(self.error(&format!("{} expected", what)))
+		}
+	}
+
+	/// Reads the next argument as a value of type `T`.
+	pub fn parse<T: FromStr>(&mut self) -> Result<T, io::Error> {
+		let token = try!(self.expect("value"));
+
+		token.parse().map_err(|_| self.error(&format!("malformed value '{}'", token)))
+	}
+
+	/// Reads the remaining arguments.
+	pub fn rest(&mut self) -> Vec<&'a str> {
+		self.tokens.by_ref().collect()
+	}
+
+	/// Makes sure every argument was read.
+	pub fn end(&mut self) -> Result<(), io::Error> {
+		match self.tokens.next() {
+			Some(token) => Err(self.error(&format!("unexpected '{}'", token))),
+			None => Ok(())
+		}
+	}
+
+	/// An error about this line.
+	pub fn error(&self, message: &str) -> io::Error {
+		error(self.kind, self.number, message)
+	}
+}
+
+impl<'a> Iterator for Line<'a> {
+	type Item = &'a str;
+
+	fn next(&mut self) -> Option<&'a str> {
+		self.tokens.next()
+	}
+}
+
+
+/// An error about the given line of a descriptor of the given `kind`, or
+/// about the whole descriptor if the line number is 0.
+pub fn error(kind: &str, line_number: usize, message: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("Can't read {}! (line {}: {})", kind, line_number, message))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::Lines;
+
+
+	#[test]
+	fn lines_skip_comments_and_blanks() {
+		let mut lines = Lines::new("# header\n\n  frame a 1 2.5 # trailing\nclip", "atlas");
+
+		let mut frame = lines.next().unwrap();
+		assert_eq!((frame.number(), frame.statement()), (3, "frame"));
+		assert_eq!(frame.expect("name").unwrap(), "a");
+		assert_eq!(frame.peek(), Some("1"));
+		assert_eq!(frame.parse::<u32>().unwrap(), 1);
+		assert!(frame.end().is_err());
+		assert!(frame.parse::<f64>().is_err());
+
+		let mut clip = lines.next().unwrap();
+		assert_eq!(clip.expect("name").unwrap_err().to_string(), "Can't read atlas! (line 4: name expected)");
+		assert!(lines.next().is_none());
+	}
+}
@@ -0,0 +1,360 @@
+use phi::data::Rectangle;
+
+use sdl2::pixels::Color;
+use sdl2::rect::{Point as SdlPoint, Rect as SdlRect};
+use sdl2::render::{BlendMode, Renderer};
+
+use std::f64::consts::PI;
+
+
+/// Drawing primitives, in the renderer's current draw color. Shapes cover the
+/// pixels which centers lie inside of them. Angles are in degrees, clockwise
+/// from the x axis.
+pub trait RendererExtensions {
+	fn fill_circle(&mut self, x: f64, y: f64, radius: f64) -> Result<(), String>;
+	/// Draws a ring, which `thickness` grows inwards from the `radius`.
+	fn draw_circle(&mut self, x: f64, y: f64, radius: f64, thickness: f64) -> Result<(), String>;
+
+	fn fill_ellipse(&mut self, x: f64, y: f64, radius_x: f64, radius_y: f64) -> Result<(), String>;
+	fn draw_ellipse(&mut self, x: f64, y: f64, radius_x: f64, radius_y: f64, thickness: f64) -> Result<(), String>;
+
+	/// Draws the part of a circle going from `start` to `end`, clockwise.
+	fn draw_arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64, thickness: f64) -> Result<(), String>;
+
+	/// Fills a polygon, which may be concave or self-intersecting, using the
+	/// even-odd rule.
+	fn fill_polygon(&mut self, points: &[(f64, f64)]) -> Result<(), String>;
+	fn draw_polygon(&mut self, points: &[(f64, f64)], thickness: f64) -> Result<(), String>;
+
+	fn draw_thick_line(&mut self, from: (f64, f64), to: (f64, f64), thickness: f64) -> Result<(), String>;
+	/// Draws a one pixel wide, anti-aliased line, by blending the pixels it
+	/// crosses according to their coverage.
+	fn draw_line_aa(&mut self, from: (f64, f64), to: (f64, f64)) -> Result<(), String>;
+
+	fn fill_rounded_rect(&mut self, rect: Rectangle, radius: f64) -> Result<(), String>;
+	fn draw_rounded_rect(&mut self, rect: Rectangle, radius: f64, thickness: f64) -> Result<(), String>;
+}
+
+impl <'window> RendererExtensions for Renderer<'window> {
+	fn fill_circle(&mut self, x: f64, y: f64, radius: f64) -> Result<(), String> {
+		self.fill_ellipse(x, y, radius, radius)
+	}
+
+	fn draw_circle(&mut self, x: f64, y: f64, radius: f64, thickness: f64) -> Result<(), String> {
+		self.draw_ellipse(x, y, radius, radius, thickness)
+	}
+
+
+	fn fill_ellipse(&mut self, x: f64, y: f64, radius_x: f64, radius_y: f64) -> Result<(), String> {
+		let spans = ring_spans(y - radius_y, y + radius_y,
+			&|row| ellipse_span((x, y), (radius_x, radius_y), row),
+			&|_| None);
+
+		fill_spans(self, &spans)
+	}
+
+	fn draw_ellipse(&mut self, x: f64, y: f64, radius_x: f64, radius_y: f64, thickness: f64) -> Result<(), String> {
+		let spans = ring_spans(y - radius_y, y + radius_y,
+			&|row| ellipse_span((x, y), (radius_x, radius_y), row),
+			&|row| ellipse_span((x, y), (radius_x - thickness, radius_y - thickness), row));
+
+		fill_spans(self, &spans)
+	}
+
+
+	fn draw_arc(&mut self, x: f64, y: f64, radius: f64, start: f64, end: f64, thickness: f64) -> Result<(), String> {
+		let (start, end) = (start.to_radians(), end.to_radians());
+		let end = if end < start { end + 2.0 * PI } else { end };
+		// Roughly one point every other pixel along the arc.
+		let steps = ((end - start) * radius / 2.0).ceil().max(1.0) as usize;
+
+		let points: Vec<(f64, f64)> = (0..steps + 1)
+		.map(|step| start + (end - start) * step as f64 / steps as f64)
+		.map(|angle| (x + radius * angle.cos(), y + radius * angle.sin()))
+		.collect();
+
+		draw_polyline(self, &points, thickness)
+	}
+
+
+	fn fill_polygon(&mut self, points: &[(f64, f64)]) -> Result<(), String> {
+		fill_spans(self, &polygon_spans(points))
+	}
+
+	fn draw_polygon(&mut self, points: &[(f64, f64)], thickness: f64) -> Result<(), String> {
+		if points.is_empty() {
+			return Ok(());
+		}
+		let mut closed = points.to_vec();
+
+		closed.push(points[0]);
+		draw_polyline(self, &closed, thickness)
+	}
+
+
+	fn draw_thick_line(&mut self, from: (f64, f64), to: (f64, f64), thickness: f64) -> Result<(), String> {
+		let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+		let length = (dx * dx + dy * dy).sqrt();
+
+		if length == 0.0 {
+			return self.fill_circle(from.0, from.1, thickness / 2.0);
+		}
+		// The offset from the line to either of its sides.
+		let (nx, ny) = (-dy / length * thickness / 2.0, dx / length * thickness / 2.0);
+
+		self.fill_polygon(&[
+			(from.0 + nx, from.1 + ny),
+			(to.0 + nx, to.1 + ny),
+			(to.0 - nx, to.1 - ny),
+			(from.0 - nx, from.1 - ny),
+		])
+	}
+
+	fn draw_line_aa(&mut self, from: (f64, f64), to: (f64, f64)) -> Result<(), String> {
+		//? This is Xiaolin Wu's algorithm: we walk along the major axis, and
+		//? split every step between the two pixels the line goes through.
+		let color = self.draw_color();
+		let blend_mode = self.blend_mode();
+		let (r, g, b, a) = color.rgba();
+
+		let steep = (to.1 - from.1).abs() > (to.0 - from.0).abs();
+		let (mut from, mut to) = if steep { ((from.1, from.0), (to.1, to.0)) } else { (from, to) };
+
+		if from.0 > to.0 {
+			::std::mem::swap(&mut from, &mut to);
+		}
+		let gradient = if to.0 == from.0 { 1.0 } else { (to.1 - from.1) / (to.0 - from.0) };
+
+		self.set_blend_mode(BlendMode::Blend);
+
+		let mut result = Ok(());
+		let mut x = from.0.round();
+
+		while result.is_ok() && x <= to.0.round() {
+			let y = from.1 + gradient * (x - from.0);
+			let coverage = y - y.floor();
+
+			for &(offset, weight) in &[(0.0, 1.0 - coverage), (1.0, coverage)] {
+				let (px, py) = if steep { (y.floor() + offset, x) } else { (x, y.floor() + offset) };
+
+				self.set_draw_color(Color::RGBA(r, g, b, (a as f64 * weight).round() as u8));
+				result = result.and_then(|_| self.draw_point(SdlPoint::new(px as i32, py as i32)));
+			}
+			x += 1.0;
+		}
+		self.set_draw_color(color);
+		self.set_blend_mode(blend_mode);
+		result
+	}
+
+
+	fn fill_rounded_rect(&mut self, rect: Rectangle, radius: f64) -> Result<(), String> {
+		let spans = ring_spans(rect.y, rect.y + rect.h,
+			&|row| rounded_span(&rect, radius, row),
+			&|_| None);
+
+		fill_spans(self, &spans)
+	}
+
+	fn draw_rounded_rect(&mut self, rect: Rectangle, radius: f64, thickness: f64) -> Result<(), String> {
+		let inner = Rectangle {
+			x: rect.x + thickness,
+			y: rect.y + thickness,
+			w: (rect.w - 2.0 * thickness).max(0.0),
+			h: (rect.h - 2.0 * thickness).max(0.0),
+		};
+		let spans = ring_spans(rect.y, rect.y + rect.h,
+			&|row| rounded_span(&rect, radius, row),
+			&|row| rounded_span(&inner, (radius - thickness).max(0.0), row));
+
+		fill_spans(self, &spans)
+	}
+}
+
+
+fn fill_spans(renderer: &mut Renderer, spans: &[SdlRect]) -> Result<(), String> {
+	if spans.is_empty() {
+		return Ok(());
+	}
+	renderer.fill_rects(spans)
+}
+
+/// Draws the segments joining the points, with round joints.
+fn draw_polyline(renderer: &mut Renderer, points: &[(f64, f64)], thickness: f64) -> Result<(), String> {
+	for segment in points.windows(2) {
+		try!(renderer.draw_thick_line(segment[0], segment[1], thickness));
+
+		if thickness > 2.0 {
+			try!(renderer.fill_circle(segment[1].0, segment[1].1, thickness / 2.0));
+		}
+	}
+	Ok(())
+}
+
+
+/// Returns the one pixel high rectangles covering, row by row between `top`
+/// and `bottom`, the pixels which centers are within the `outer` span of
+/// their row, but not within the `inner` one.
+///
+/// Spans are given as the (left, right) bounds of the shape along the row
+/// which center is passed to them, and `None` where the shape doesn't reach.
+fn ring_spans(top: f64, bottom: f64, outer: &Fn(f64) -> Option<(f64, f64)>, inner: &Fn(f64) -> Option<(f64, f64)>) -> Vec<SdlRect> {
+	let mut spans = vec![];
+
+	for row in top.floor() as i32..bottom.ceil() as i32 {
+		let center = row as f64 + 0.5;
+		let (left, right) = match outer(center) {
+			Some(span) => pixel_range(span),
+			None => continue
+		};
+		match inner(center).map(pixel_range) {
+			Some((inner_left, inner_right)) if inner_left <= inner_right => {
+				push_span(&mut spans, row, left, inner_left.min(right + 1) - 1);
+				push_span(&mut spans, row, (inner_right + 1).max(left), right);
+			},
+			_ => push_span(&mut spans, row, left, right)
+		}
+	}
+	spans
+}
+
+/// Returns the rectangles covering the pixels which centers are inside of the
+/// polygon, according to the even-odd rule.
+fn polygon_spans(points: &[(f64, f64)]) -> Vec<SdlRect> {
+	let mut spans = vec![];
+
+	if points.len() < 3 {
+		return spans;
+	}
+	let top = points.iter().fold(::std::f64::INFINITY, |top, point| top.min(point.1));
+	let bottom = points.iter().fold(::std::f64::NEG_INFINITY, |bottom, point| bottom.max(point.1));
+	let mut crossings = Vec::with_capacity(points.len());
+
+	for row in top.floor() as i32..bottom.ceil() as i32 {
+		let center = row as f64 + 0.5;
+
+		crossings.clear();
+
+		for (i, a) in points.iter().enumerate() {
+			let b = points[(i + 1) % points.len()];
+
+			if (a.1 <= center) != (b.1 <= center) {
+				crossings.push(a.0 + (center - a.1) * (b.0 - a.0) / (b.1 - a.1));
+			}
+		}
+		crossings.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+		for pair in crossings.chunks(2) {
+			if pair.len() == 2 {
+				let (left, right) = pixel_range((pair[0], pair[1]));
+				push_span(&mut spans, row, left, right);
+			}
+		}
+	}
+	spans
+}
+
+/// Returns the first and last columns of the pixels which centers lie within
+/// the given span.
+fn pixel_range(span: (f64, f64)) -> (i32, i32) {
+	((span.0 - 0.5).ceil() as i32, (span.1 - 0.5).floor() as i32)
+}
+
+fn push_span(spans: &mut Vec<SdlRect>, row: i32, left: i32, right: i32) {
+	if left <= right {
+		spans.push(SdlRect::new(left, row, (right - left + 1) as u32, 1));
+	}
+}
+
+fn ellipse_span(center: (f64, f64), radius: (f64, f64), row: f64) -> Option<(f64, f64)> {
+	let dy = (row - center.1) / radius.1;
+
+	if radius.0 <= 0.0 || radius.1 <= 0.0 || dy.abs() > 1.0 {
+		return None;
+	}
+	let half = radius.0 * (1.0 - dy * dy).sqrt();
+
+	Some((center.0 - half, center.0 + half))
+}
+
+fn rounded_span(rect: &Rectangle, radius: f64, row: f64) -> Option<(f64, f64)> {
+	if rect.w <= 0.0 || rect.h <= 0.0 || row < rect.y || row > rect.y + rect.h {
+		return None;
+	}
+	let radius = radius.min(rect.w / 2.0).min(rect.h / 2.0);
+	// The vertical distance from the row to the centers of the nearest
+	// corners, if the row goes through them.
+	let dy = (rect.y + radius - row).max(row - (rect.y + rect.h - radius)).max(0.0);
+	let inset = radius - (radius * radius - dy * dy).max(0.0).sqrt();
+
+	Some((rect.x + inset, rect.x + rect.w - inset))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use phi::data::Rectangle;
+	use sdl2::rect::Rect as SdlRect;
+
+
+	fn area(spans: &[SdlRect]) -> u32 {
+		spans.iter().fold(0, |area, span| area + span.width() * span.height())
+	}
+
+
+	#[test]
+	fn filled_circles_cover_their_area() {
+		let spans = ring_spans(-50.0, 50.0, &|row| ellipse_span((0.0, 0.0), (50.0, 50.0), row), &|_| None);
+		let expected = ::std::f64::consts::PI * 50.0 * 50.0;
+
+		assert_eq!(spans.len(), 100);
+		assert!((area(&spans) as f64 - expected).abs() / expected < 0.01);
+	}
+
+	#[test]
+	fn rings_exclude_their_inside() {
+		let spans = ring_spans(-50.0, 50.0,
+			&|row| ellipse_span((0.0, 0.0), (50.0, 50.0), row),
+			&|row| ellipse_span((0.0, 0.0), (40.0, 40.0), row));
+		let expected = ::std::f64::consts::PI * (50.0 * 50.0 - 40.0 * 40.0);
+
+		assert!((area(&spans) as f64 - expected).abs() / expected < 0.02);
+		assert!(spans.iter()
+			.filter(|span| span.y().abs() < 35)
+			.all(|span| span.x() > 0 || span.x() + span.width() as i32 <= 0));
+	}
+
+	#[test]
+	fn polygons_use_the_even_odd_rule() {
+		let square = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+		assert_eq!(area(&polygon_spans(&square)), 100);
+
+		let triangle = [(0.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+		assert_eq!(area(&polygon_spans(&triangle)), 55);
+
+		let pentagram: Vec<(f64, f64)> = (0..5).map(|i| {
+			let angle = (i as f64 * 144.0 - 90.0).to_radians();
+			(50.0 + 40.0 * angle.cos(), 50.0 + 40.0 * angle.sin())
+		}).collect();
+		let spans = polygon_spans(&pentagram);
+		let covers = |x: i32, y: i32| spans.iter().any(|span| span.contains((x, y)));
+
+		//? The branches cross twice around the pentagon in the middle, which
+		//? is thus left empty.
+		assert!(covers(50, 15));
+		assert!(covers(20, 40));
+		assert!(!covers(50, 50));
+		assert!(!covers(45, 45));
+	}
+
+	#[test]
+	fn rounded_rects_cut_their_corners() {
+		let rect = Rectangle { x: 0.0, y: 0.0, w: 20.0, h: 10.0 };
+		let spans = ring_spans(0.0, 10.0, &|row| rounded_span(&rect, 4.0, row), &|_| None);
+
+		assert!(area(&spans) < 200);
+		assert!(spans[0].width() < 20);
+		assert_eq!(spans[5].width(), 20);
+	}
+}
@@ -0,0 +1,293 @@
+use phi::data::Rectangle;
+use phi::gfx::{Renderable, Sprite};
+use phi::procgen::pixel_surface;
+use phi::target::RenderTarget;
+
+use sdl2::pixels::Color;
+use sdl2::render::{BlendMode, Renderer};
+use sdl2::surface::Surface;
+
+
+/// Full-screen effects applied over whatever the current view renders. The
+/// game loop wraps the rendering of every frame with `begin` and `end`, so
+/// that views only have to switch effects on and off.
+///
+/// Effects which alter the picture itself (pixelation) render the frame into
+/// an offscreen target first; the others are drawn over the frame.
+pub struct PostEffects {
+	/// The color of the current flash, its duration and the time left.
+	flash: Option<(Color, f64, f64)>,
+	/// The color the screen fades to, along with the current and the target
+	/// opacities, and the speed it goes from the former to the latter.
+	fade: (Color, f64, f64, f64),
+
+	vignette: Option<f64>,
+	scanlines: Option<f64>,
+	pixelation: u32,
+
+	scene: Option<RenderTarget>,
+	low_res: Option<RenderTarget>,
+	vignette_overlay: Option<(Sprite, f64)>,
+	scanlines_overlay: Option<(Sprite, f64)>,
+}
+
+impl PostEffects {
+	pub fn new() -> PostEffects {
+		PostEffects {
+			flash: None,
+			fade: (Color::RGB(0, 0, 0), 0.0, 0.0, 0.0),
+
+			vignette: None,
+			scanlines: None,
+			pixelation: 1,
+
+			scene: None,
+			low_res: None,
+			vignette_overlay: None,
+			scanlines_overlay: None,
+		}
+	}
+
+
+	/// Covers the screen with the color, which then fades out over the given
+	/// duration, in seconds.
+	pub fn flash(&mut self, color: Color, duration: f64) {
+		self.flash = Some((color, duration, duration));
+	}
+
+	/// Progressively covers the screen with the color, up to the `opacity` in
+	/// [0, 1], over the given duration, in seconds. Fade to 0 to uncover it.
+	pub fn fade_to(&mut self, color: Color, opacity: f64, duration: f64) {
+		let target = opacity.max(0.0).min(1.0);
+
+		//? Fades without any duration cover the screen right away.
+		self.fade = if duration > 0.0 {
+			(color, self.fade.1, target, (target - self.fade.1).abs() / duration)
+		} else {
+			(color, target, target, 0.0)
+		};
+	}
+
+	/// The current opacity of the fade.
+	pub fn fade_opacity(&self) -> f64 {
+		self.fade.1
+	}
+
+	/// The current opacity of the flash, which goes from 1 down to 0.
+	pub fn flash_opacity(&self) -> f64 {
+		self.flash.map_or(0.0, |(_, duration, left)| left / duration)
+	}
+
+	/// Darkens the edges of the screen, with the given strength in [0, 1].
+	pub fn set_vignette(&mut self, strength: Option<f64>) {
+		self.vignette = strength.map(|strength| strength.max(0.0).min(1.0));
+	}
+
+	/// Darkens every other line of the screen, with the given intensity in
+	/// [0, 1], like old CRT screens did.
+	pub fn set_scanlines(&mut self, intensity: Option<f64>) {
+		self.scanlines = intensity.map(|intensity| intensity.max(0.0).min(1.0));
+	}
+
+	/// Renders the frame with blocks of `factor` by `factor` pixels. A factor
+	/// of 1 turns the effect off.
+	pub fn set_pixelation(&mut self, factor: u32) {
+		self.pixelation = factor.max(1);
+	}
+
+	/// Turns every effect off.
+	pub fn clear(&mut self) {
+		self.flash = None;
+		self.fade = (Color::RGB(0, 0, 0), 0.0, 0.0, 0.0);
+		self.vignette = None;
+		self.scanlines = None;
+		self.pixelation = 1;
+	}
+
+
+	pub fn update(&mut self, elapsed: f64) {
+		self.flash = self.flash.and_then(|(color, duration, left)| {
+			if left > elapsed { Some((color, duration, left - elapsed)) } else { None }
+		});
+
+		let (color, current, target, speed) = self.fade;
+		let step = speed * elapsed;
+		let current = if (target - current).abs() <= step { target }
+			else if target > current { current + step }
+			else { current - step };
+
+		self.fade = (color, current, target, speed);
+	}
+
+
+	/// Prepares the frame to be rendered. Called before the view renders.
+	pub fn begin(&mut self, renderer: &mut Renderer) {
+		if self.pixelation <= 1 {
+			return;
+		}
+		let (w, h) = renderer.output_size().unwrap();
+
+		if let Err(e) = ensure_target(&mut self.scene, renderer, w, h)
+		.and_then(|_| self.scene.as_ref().unwrap().bind(renderer)) {
+			println!("Can't render the scene offscreen: {}", e);
+			self.pixelation = 1;
+		}
+	}
+
+	/// Applies the effects to the rendered frame. Called once the view (and
+	/// the render queue) are done rendering.
+	pub fn end(&mut self, renderer: &mut Renderer) {
+		let (w, h) = renderer.output_size().unwrap();
+		let screen = Rectangle { x: 0.0, y: 0.0, w: w as f64, h: h as f64 };
+
+		if self.pixelation > 1 {
+			self.resolve_pixelated(renderer, screen);
+		}
+		if let Some(strength) = self.vignette {
+			draw_overlay(renderer, &mut self.vignette_overlay, screen, strength, vignette_surface);
+		}
+		if let Some(intensity) = self.scanlines {
+			draw_overlay(renderer, &mut self.scanlines_overlay, screen, intensity, scanlines_surface);
+		}
+		if self.fade.1 > 0.0 {
+			fill_screen(renderer, self.fade.0, self.fade.1);
+		}
+		if let Some((color, _, _)) = self.flash {
+			fill_screen(renderer, color, self.flash_opacity());
+		}
+	}
+
+	/// Copies the scene into a smaller target, then scales it back up to the
+	/// window, so that every pixel of the former spans a block of the latter.
+	fn resolve_pixelated(&mut self, renderer: &mut Renderer, screen: Rectangle) {
+		let _ = RenderTarget::unbind(renderer);
+
+		let scene = match self.scene {
+			Some(ref scene) => scene,
+			None => return
+		};
+		let (w, h) = ((screen.w as u32 / self.pixelation).max(1), (screen.h as u32 / self.pixelation).max(1));
+		let low_res = ensure_target(&mut self.low_res, renderer, w, h)
+		.and_then(|_| {
+			let low_res = self.low_res.as_ref().unwrap();
+
+			low_res.draw_into(renderer, |renderer| {
+				renderer.set_draw_color(Color::RGBA(0, 0, 0, 0));
+				renderer.clear();
+				scene.sprite().render(renderer, Rectangle { x: 0.0, y: 0.0, w: w as f64, h: h as f64 });
+			}).map(|_| low_res)
+		});
+
+		match low_res {
+			Ok(low_res) => low_res.sprite().render(renderer, screen),
+			Err(e) => {
+				println!("Can't pixelate the scene: {}", e);
+				scene.sprite().render(renderer, screen);
+			}
+		}
+	}
+}
+
+
+/// Creates the target, or recreates it if its size doesn't match.
+fn ensure_target(target: &mut Option<RenderTarget>, renderer: &mut Renderer, w: u32, h: u32) -> Result<(), String> {
+	if target.as_ref().map_or(true, |target| target.size() != (w, h)) {
+		*target = Some(try!(RenderTarget::new(renderer, w, h)));
+	}
+	Ok(())
+}
+
+/// Draws an overlay, generated at the screen's size with the given parameter
+/// the first time, and whenever either of them changes.
+fn draw_overlay<F>(renderer: &mut Renderer, overlay: &mut Option<(Sprite, f64)>, screen: Rectangle, param: f64, generate: F)
+where F: Fn(u32, u32, f64) -> Result<Surface<'static>, String> {
+	let outdated = overlay.as_ref().map_or(true, |&(ref sprite, value)| {
+		value != param || sprite.size() != (screen.w, screen.h)
+	});
+
+	if outdated {
+		*overlay = generate(screen.w as u32, screen.h as u32, param).ok()
+		.and_then(|surface| Sprite::from_surface(renderer, &surface))
+		.map(|sprite| (sprite, param));
+	}
+	if let Some((ref sprite, _)) = *overlay {
+		sprite.render(renderer, screen);
+	}
+}
+
+fn fill_screen(renderer: &mut Renderer, color: Color, opacity: f64) {
+	let (r, g, b) = color.rgb();
+	let blend_mode = renderer.blend_mode();
+
+	renderer.set_blend_mode(BlendMode::Blend);
+	renderer.set_draw_color(Color::RGBA(r, g, b, (opacity.max(0.0).min(1.0) * 255.0).round() as u8));
+	renderer.fill_rect(None).unwrap();
+	renderer.set_blend_mode(blend_mode);
+}
+
+
+/// Generates a black image, which opacity is given for every pixel.
+fn shade_surface<F>(w: u32, h: u32, opacity: F) -> Result<Surface<'static>, String> where F: Fn(u32, u32) -> f64 {
+	pixel_surface(w, h, |x, y| Color::RGBA(0, 0, 0, (opacity(x, y).max(0.0).min(1.0) * 255.0).round() as u8))
+}
+
+fn vignette_surface(w: u32, h: u32, strength: f64) -> Result<Surface<'static>, String> {
+	let (half_w, half_h) = (w as f64 / 2.0, h as f64 / 2.0);
+
+	shade_surface(w, h, |x, y| {
+		let (dx, dy) = ((x as f64 + 0.5 - half_w) / half_w, (y as f64 + 0.5 - half_h) / half_h);
+		// Clear in the middle, darkening towards the corners.
+		let distance = ((dx * dx + dy * dy) / 2.0).sqrt();
+
+		strength * ((distance - 0.4) / 0.6).max(0.0).powf(1.5)
+	})
+}
+
+fn scanlines_surface(w: u32, h: u32, intensity: f64) -> Result<Surface<'static>, String> {
+	shade_surface(w, h, |_, y| if y % 2 == 1 { intensity } else { 0.0 })
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::PostEffects;
+	use sdl2::pixels::Color;
+
+
+	#[test]
+	fn flashes_decay_over_their_duration() {
+		let mut effects = PostEffects::new();
+
+		effects.flash(Color::RGB(255, 255, 255), 0.5);
+		assert_eq!(effects.flash_opacity(), 1.0);
+
+		effects.update(0.125);
+		assert_eq!(effects.flash_opacity(), 0.75);
+		effects.update(0.25);
+		assert_eq!(effects.flash_opacity(), 0.25);
+		effects.update(0.125);
+		assert_eq!(effects.flash_opacity(), 0.0);
+	}
+
+	#[test]
+	fn fades_reach_their_target_in_time() {
+		let mut effects = PostEffects::new();
+
+		effects.fade_to(Color::RGB(0, 0, 0), 1.0, 2.0);
+		effects.update(1.0);
+		assert_eq!(effects.fade_opacity(), 0.5);
+		effects.update(1.5);
+		assert_eq!(effects.fade_opacity(), 1.0);
+
+		//? Fading back takes the given duration, from wherever the fade is.
+		effects.fade_to(Color::RGB(0, 0, 0), 0.0, 0.5);
+		effects.update(0.25);
+		assert_eq!(effects.fade_opacity(), 0.5);
+		effects.update(0.25);
+		assert_eq!(effects.fade_opacity(), 0.0);
+
+		effects.fade_to(Color::RGB(0, 0, 0), 0.75, 0.0);
+		effects.update(0.0);
+		assert_eq!(effects.fade_opacity(), 0.75);
+	}
+}
@@ -3,18 +3,22 @@ extern crate sdl2_sys;
 
 
 use phi::data::Rectangle;
+use phi::descr::{self, Line, Lines};
 use phi::Phi;
 
-use sdl2::render::{Renderer, Texture};
+use sdl2::pixels::Color;
+use sdl2::rect::Point as SdlPoint;
+use sdl2::render::{BlendMode, Renderer, Texture};
 use sdl2::surface::Surface;
 use sdl2::image::LoadTexture;
 
 use std::*;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 use std::ops::{Index, Range};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 
@@ -31,7 +35,11 @@ macro_rules! aligned (
 
 #[derive(Clone, Debug)]
 pub struct AlphaChannel {
-	data: Vec<usize>,
+	data: Vec<u64>,
+
+	// The occupied span [first, last) of every row, where `first` and
+	// `last - 1` are opaque. Empty rows have `first == last`.
+	spans: Vec<(u32, u32)>,
 
 	stride: usize,
 	
@@ -41,6 +49,7 @@ pub struct AlphaChannel {
 
 
 const BYTE_ORDER_MARK: u16 = 0xbeef;
+const WORD_BITS: usize = 64;
 
 impl AlphaChannel {
 
@@ -83,10 +92,9 @@ impl AlphaChannel {
 					_ => unreachable!()
 				};
 				let read_pixels = |pixels: &[u8]| {
-					let size_usize = ::std::mem::size_of::<usize>() * 8;
-					let size_packed = aligned!(surface.width() as usize; size_usize);
+					let size_packed = aligned!(surface.width() as usize; WORD_BITS);
 
-					let mut result: Vec<usize> = vec![0; size_packed * surface.height() as usize];
+					let mut result: Vec<u64> = vec![0; size_packed * surface.height() as usize];
 					{
 						let result_mutable = &mut result;
 						let pixels_ptr = &pixels[0] as *const u8;
@@ -98,19 +106,12 @@ impl AlphaChannel {
 
 							for x in 0usize..surface.width() as usize {
 								if read_alpha(row.offset((x * color_depth) as isize)) >= threshold {
-									result_mutable[stride + x / size_usize] |= 1usize << (x % size_usize);
+									result_mutable[stride + x / WORD_BITS] |= 1u64 << (x % WORD_BITS);
 								}
 							}
 						}					
 					}
-					AlphaChannel {
-						data: result,
-
-						stride: size_packed,
-
-						height: surface.height(),
-						width: surface.width(),				
-					}
+					AlphaChannel::with_data(result, surface.width(), surface.height())
 				};
 				Some(match surface.without_lock() {
 					Some(pixels) => read_pixels(pixels),
@@ -123,7 +124,7 @@ impl AlphaChannel {
 
 	pub fn from_file(path: &Path) -> Result<AlphaChannel, io::Error> {
 		let mut file = BufReader::new(try!(File::open(path)));
-		let size_usize = ::std::mem::size_of::<usize>();
+		let size_word = ::std::mem::size_of::<u64>();
 
 		let mut data_vector: Vec<u8> = vec![0; 32];
 		try!(file.read_exact(&mut data_vector));
@@ -146,21 +147,14 @@ impl AlphaChannel {
 					if digest.sum32() != checksum {
 						return Err(io::Error::new(io::ErrorKind::Other, "Can't read alpha channel from this file! (CHECKSUM)"));
 					}
-					let data = Vec::from_raw_parts(
-						data_vector.as_mut_ptr() as *mut usize, 
-						data_vector.len() / size_usize,
-						data_vector.capacity());
-
-					::std::mem::forget(data_vector);
-
-					Ok(AlphaChannel {
-						data: data,
+					if data_vector.len() != aligned!(width as usize; WORD_BITS) * height as usize * size_word {
+						return Err(io::Error::new(io::ErrorKind::Other, "Can't read alpha channel from this file! (SIZE)"));
+					}
+					let mut data = vec![0u64; data_vector.len() / size_word];
 
-						stride: aligned!(width as usize; size_usize * 8),
+					::std::ptr::copy_nonoverlapping(data_vector.as_ptr(), data.as_mut_ptr() as *mut u8, data_vector.len());
 
-						height: height,
-						width: width,	
-					})
+					Ok(AlphaChannel::with_data(data, width, height))
 				},
 				_ => Err(io::Error::new(io::ErrorKind::Other, "Can't read alpha channel from this file! (BYTE ORDER)"))
 			}			
@@ -168,104 +162,157 @@ impl AlphaChannel {
 	}
 
 
-	pub fn intersect(channel_a: &AlphaChannel, x_a: f64, y_a: f64, channel_b: &AlphaChannel, x_b: f64, y_b: f64, roi: Rectangle) -> bool {
-		let (w, h) = (roi.w.round() as usize, roi.h.round() as usize);
+	/// Creates a mask of the given size, which pixels' opacity is told by the
+	/// `opaque` predicate.
+	pub fn from_fn<F>(width: u32, height: u32, opaque: F) -> AlphaChannel where F: Fn(u32, u32) -> bool {
+		let stride = aligned!(width as usize; WORD_BITS);
+		let mut data = vec![0u64; stride * height as usize];
 
-		if w > 0 {
-			let (x_a, x_b) = ((roi.x - x_a).round() as usize, (roi.x - x_b).round() as usize);
-			let (y_a, y_b) = ((roi.y - y_a).round() as usize, (roi.y - y_b).round() as usize);
-			let (xlast_a, xlast_b) = (x_a + w - 1, x_b + w - 1);
+		for y in 0..height {
+			for x in 0..width {
+				if opaque(x, y) {
+					data[y as usize * stride + x as usize / WORD_BITS] |= 1u64 << (x as usize % WORD_BITS);
+				}
+			}
+		}
+		AlphaChannel::with_data(data, width, height)
+	}
 
-			let size_usize = ::std::mem::size_of::<usize>() * 8;
+	fn with_data(data: Vec<u64>, width: u32, height: u32) -> AlphaChannel {
+		let stride = aligned!(width as usize; WORD_BITS);
+		let spans = (0..height as usize).map(|y| {
+			let row = &data[y * stride..(y + 1) * stride];
 
-			let (maskl_a, maskl_b) = (
-				::std::usize::MAX.wrapping_shl((x_a % size_usize) as u32),
-				::std::usize::MAX.wrapping_shl((x_b % size_usize) as u32));			
-			let (maskr_a, maskr_b) = (
-				1usize.wrapping_shl((xlast_a % size_usize) as u32).wrapping_sub(1),
-				1usize.wrapping_shl((xlast_b % size_usize) as u32).wrapping_sub(1));
+			match (row.iter().position(|&word| word != 0), row.iter().rposition(|&word| word != 0)) {
+				(Some(first), Some(last)) => (
+					(first * WORD_BITS + row[first].trailing_zeros() as usize) as u32,
+					(last * WORD_BITS + WORD_BITS - row[last].leading_zeros() as usize) as u32),
+				_ => (0, 0)
+			}
+		})
+		.collect();
 
-			let (x_a, x_b) = (x_a / size_usize, x_b / size_usize);
-			let (w_a, w_b) = (
-				xlast_a / size_usize - x_a, 
-				xlast_b / size_usize - x_b);
+		AlphaChannel {
+			data: data,
+			spans: spans,
 
-			let w = ::std::cmp::min(w_a, w_b) + 1;
-			let get_block = |r: usize, channel: &AlphaChannel, offset: usize, last: usize, maskl: usize, maskr: usize| {
-				let i = offset + r;
-				let mut result = channel[i];
+			stride: stride,
+
+			height: height,
+			width: width,
+		}
+	}
 
-				if r <= 0 {
-					result &= maskl;
-				}
-				if r >= last {
-					result &= maskr;
-				}
-				return result;
-			};
-			for r in 0..h {
-				let (row_a, row_b) = ((y_a + r) * channel_a.stride() + x_a, (y_b + r) * channel_b.stride() + x_b);
 
-				for c in 0..w {
-					let (block_a, block_b) = (
-						get_block(c, channel_a, row_a, w_a, maskl_a, maskr_a),
-						get_block(c, channel_b, row_b, w_b, maskl_b, maskr_b));
+	/// Tells whether opaque pixels of both masks overlap within the `roi`. The
+	/// masks' origins are (`x_a`, `y_a`) and (`x_b`, `y_b`), all in global CS.
+	///
+	/// Masks may be placed at arbitrary offsets relative to each other, thus
+	/// the words of both rows are funnelled to a common bit alignment before
+	/// being compared. Rows, which occupied spans don't overlap, are skipped.
+	pub fn intersect(channel_a: &AlphaChannel, x_a: f64, y_a: f64, channel_b: &AlphaChannel, x_b: f64, y_b: f64, roi: Rectangle) -> bool {
+		let (w, h) = (roi.w.round() as i64, roi.h.round() as i64);
+		let (x_a, x_b) = ((roi.x - x_a).round() as i64, (roi.x - x_b).round() as i64);
+		let (y_a, y_b) = ((roi.y - y_a).round() as i64, (roi.y - y_b).round() as i64);
 
-					if (block_a & block_b) != 0 {
+		for r in 0..h {
+			let (span_a, span_b) = (channel_a.span(y_a + r), channel_b.span(y_b + r));
+			// The ROI-local span, where both rows might have opaque pixels.
+			let lo = cmp::max(cmp::max(span_a.0 - x_a, span_b.0 - x_b), 0);
+			let hi = cmp::min(cmp::min(span_a.1 - x_a, span_b.1 - x_b), w);
+
+			if lo < hi {
+				let (row_a, row_b) = (channel_a.row((y_a + r) as usize), channel_b.row((y_b + r) as usize));
+				let mut c = lo;
+
+				while c < hi {
+					let mut block = fetch_word(row_a, (x_a + c) as usize) & fetch_word(row_b, (x_b + c) as usize);
+
+					if hi - c < WORD_BITS as i64 {
+						block &= (1u64 << (hi - c)) - 1;
+					}
+					if block != 0 {
 						return true;
 					}
+					c += WORD_BITS as i64;
 				}
 			}
 		}
 		false
 	}
 
+	/// Tells whether opaque pixels of the mask, which origin is (`x`, `y`),
+	/// are found within the `roi`, all in global CS.
 	pub fn intersect_box(channel: &AlphaChannel, x: f64, y: f64, roi: Rectangle) -> bool {
-		let (w, h) = (roi.w.round() as usize, roi.h.round() as usize);
+		let (w, h) = (roi.w.round() as i64, roi.h.round() as i64);
+		let (x, y) = ((roi.x - x).round() as i64, (roi.y - y).round() as i64);
 
-		if w > 0 {
-			let (x, y) = ((roi.x - x).round() as usize, (roi.y - y).round() as usize);
-			
-			let xlast = x + w;
+		for r in 0..h {
+			let span = channel.span(y + r);
+			let lo = cmp::max(span.0 - x, 0);
+			let hi = cmp::min(span.1 - x, w);
 
-			let size_usize = ::std::mem::size_of::<usize>() * 8;
-			let maskl = ::std::usize::MAX << (x % size_usize);
-			let maskr = !::std::usize::MAX.wrapping_shl((xlast % size_usize + 1) as u32);
+			if lo < hi {
+				let row = channel.row((y + r) as usize);
+				let mut c = lo;
 
-			let x = x / size_usize;
-			let w = xlast / size_usize - x + 1;
+				while c < hi {
+					let mut block = fetch_word(row, (x + c) as usize);
 
-			if w > 0 {
-				let rlast = w - 1;
+					if hi - c < WORD_BITS as i64 {
+						block &= (1u64 << (hi - c)) - 1;
+					}
+					if block != 0 {
+						return true;
+					}
+					c += WORD_BITS as i64;
+				}
+			}
+		}
+		false
+	}
 
-				for c in 0..h {
-					let row = (c + y) * channel.stride() + x;
 
-					for r in 0..w {
-						let mut block = channel[row + r];
+	/// Computes the contact set of two masks within the `roi` (which is given
+	/// in global CS, as well as the masks' origins). Returns `None` if no
+	/// opaque pixels overlap.
+	pub fn contact(channel_a: &AlphaChannel, x_a: f64, y_a: f64, channel_b: &AlphaChannel, x_b: f64, y_b: f64, roi: Rectangle) -> Option<Contact> {
+		let (x_a, x_b) = ((roi.x - x_a).round() as usize, (roi.x - x_b).round() as usize);
+		let (y_a, y_b) = ((roi.y - y_a).round() as usize, (roi.y - y_b).round() as usize);
 
-						if r == 0 { block &= maskl; }
-						if r == rlast { block &= maskr; }
+		Contact::gather(&roi, |c, r| {
+			(channel_a.get(x_a + c, y_a + r), channel_b.get(x_b + c, y_b + r))
+		})
+	}
 
-						if block != 0 {
-							return true;
-						}
-					}			
-				}			
-			}			
+	/// Computes the contact set of a solid box occupying the whole `roi` (which
+	/// is regarded as the first body) and the mask placed at (`x`, `y`).
+	pub fn contact_box(channel: &AlphaChannel, x: f64, y: f64, roi: Rectangle) -> Option<Contact> {
+		let (x, y) = ((roi.x - x).round() as usize, (roi.y - y).round() as usize);
+
+		Contact::gather(&roi, |c, r| {
+			(true, channel.get(x + c, y + r))
+		})
+	}
+
+	/// Tells whether the pixel at mask-local (`x`, `y`) is opaque. Pixels
+	/// outside of the mask are treated as transparent.
+	pub fn get(&self, x: usize, y: usize) -> bool {
+		if x >= self.width as usize || y >= self.height as usize {
+			return false;
 		}
-		false
+		(self[y * self.stride + x / WORD_BITS] >> (x % WORD_BITS)) & 1 != 0
 	}
 
 
 	pub fn save_to(&self, path: &Path) -> Result<(), io::Error> {
 		let mut file = BufWriter::new(try!(File::create(path)));
-		let size_usize = ::std::mem::size_of::<usize>();
+		let size_word = ::std::mem::size_of::<u64>();
 
 		unsafe {
 			let mut digest = crc32::Digest::new(crc32::IEEE);
 
-			let data_size = self.data.len() * size_usize;
+			let data_size = self.data.len() * size_word;
 			let data_slice = ::std::slice::from_raw_parts(
 				self.data.as_ptr() as *const u8, data_size);
 
@@ -286,6 +333,22 @@ impl AlphaChannel {
 		self.stride
 	}
 
+	/// Returns the words of the `y`-th row.
+	pub fn row(&self, y: usize) -> &[u64] {
+		&self.data[y * self.stride..(y + 1) * self.stride]
+	}
+
+	/// Returns the occupied span of the `y`-th row. Rows out of the mask are
+	/// regarded as empty ones.
+	fn span(&self, y: i64) -> (i64, i64) {
+		if y < 0 || y >= self.height as i64 {
+			return (0, 0);
+		}
+		let (first, last) = self.spans[y as usize];
+
+		(first as i64, last as i64)
+	}
+
 	pub fn width(&self) -> u32 {
 		self.width
 	}
@@ -300,26 +363,153 @@ impl AlphaChannel {
 }
 
 impl Index<usize> for AlphaChannel {
-	type Output = usize;
+	type Output = u64;
 
-	fn index<'a>(&'a self, _index: usize) -> &'a usize {
+	fn index<'a>(&'a self, _index: usize) -> &'a u64 {
 		&self.data[_index]
 	}
 }
 
 impl Index<Range<usize>> for AlphaChannel {
-	type Output = [usize];
+	type Output = [u64];
 
-	fn index<'a>(&'a self, _index: Range<usize>) -> &'a [usize] {
+	fn index<'a>(&'a self, _index: Range<usize>) -> &'a [u64] {
 		&self.data[_index]
 	}
 }
 
 
+/// Fetches `WORD_BITS` bits of the `row` starting at the `bit` position, by
+/// funnelling two adjacent words. Bits past the row's end read as zeros.
+fn fetch_word(row: &[u64], bit: usize) -> u64 {
+	let (i, shift) = (bit / WORD_BITS, bit % WORD_BITS);
+	let lo = if i < row.len() { row[i] >> shift } else { 0 };
+
+	if shift == 0 {
+		return lo;
+	}
+	let hi = if i + 1 < row.len() { row[i + 1] << (WORD_BITS - shift) } else { 0 };
+
+	lo | hi
+}
+
+
+/// The contact set of two colliding bodies, as produced by the collision
+/// queries of `AlphaChannel`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+	/// The overlapping opaque pixels count.
+	pub pixels: usize,
+
+	/// The center of mass of the overlapping opaque pixels, in global CS.
+	pub centroid: (f64, f64),
+
+	/// The unit vector pointing from the first body towards the second one.
+	pub normal: (f64, f64),
+
+	/// How deep the bodies interpenetrate along the `normal`, in pixels.
+	pub depth: f64,
+}
+
+impl Contact {
+
+	/// Walks over every pixel of the `roi` and accumulates those reported as
+	/// opaque by `sample` (for the first and the second body respectively).
+	fn gather<F>(roi: &Rectangle, sample: F) -> Option<Contact> where F: Fn(usize, usize) -> (bool, bool) {
+		let (w, h) = (roi.w.round() as usize, roi.h.round() as usize);
+
+		let mut pixels = 0usize;
+		let (mut sum_x, mut sum_y) = (0.0, 0.0);
+		let (mut count_a, mut sum_a) = (0usize, (0.0, 0.0));
+		let (mut count_b, mut sum_b) = (0usize, (0.0, 0.0));
+
+		for r in 0..h {
+			for c in 0..w {
+				let (opaque_a, opaque_b) = sample(c, r);
+				let (x, y) = (c as f64 + 0.5, r as f64 + 0.5);
+
+				if opaque_a {
+					count_a += 1;
+					sum_a = (sum_a.0 + x, sum_a.1 + y);
+				}
+				if opaque_b {
+					count_b += 1;
+					sum_b = (sum_b.0 + x, sum_b.1 + y);
+				}
+				if opaque_a && opaque_b {
+					pixels += 1;
+					sum_x += x;
+					sum_y += y;
+				}
+			}
+		}
+		if pixels == 0 {
+			return None;
+		}
+		// Each body occupies its own side of the overlap, so the direction from
+		// one center of mass to another approximates the contact normal well.
+		let normal = {
+			let (dx, dy) = (
+				sum_b.0 / count_b as f64 - sum_a.0 / count_a as f64,
+				sum_b.1 / count_b as f64 - sum_a.1 / count_a as f64);
+			let length = (dx * dx + dy * dy).sqrt();
+
+			if length > 1e-6 {
+				(dx / length, dy / length)
+			}
+			// The bodies are indistinguishable within the overlap, hence we
+			// assume they collide across the narrowest side of it.
+			else if w < h { (1.0, 0.0) }
+			else { (0.0, 1.0) }
+		};
+		let (mut lo, mut hi) = (::std::f64::MAX, ::std::f64::MIN);
+
+		for r in 0..h {
+			for c in 0..w {
+				if let (true, true) = sample(c, r) {
+					let projection = (c as f64 + 0.5) * normal.0 + (r as f64 + 0.5) * normal.1;
+
+					lo = lo.min(projection);
+					hi = hi.max(projection);
+				}
+			}
+		}
+		Some(Contact {
+			pixels: pixels,
+			centroid: (roi.x + sum_x / pixels as f64, roi.y + sum_y / pixels as f64),
+			normal: normal,
+			depth: hi - lo + 1.0,
+		})
+	}
+
+
+	/// Returns the contact with its `normal` flipped, if necessary, so that it
+	/// points from the location `from` towards the location `to`.
+	pub fn oriented(self, from: (f64, f64), to: (f64, f64)) -> Contact {
+		if (to.0 - from.0) * self.normal.0 + (to.1 - from.1) * self.normal.1 < 0.0 {
+			return Contact {
+				normal: (-self.normal.0, -self.normal.1),
+				..self
+			};
+		}
+		self
+	}
+
+	/// The displacement which, being applied to the second body, pushes it
+	/// out of the first one.
+	pub fn separation(&self) -> (f64, f64) {
+		(self.normal.0 * self.depth, self.normal.1 * self.depth)
+	}
+}
+
+
 #[derive(Clone)]
 pub struct Sprite {
 	tex: Rc<RefCell<Texture>>,
 	src: Rectangle,
+	/// The opacity of this sprite only, in [0, 1]. The texture is shared, so
+	/// the alpha is applied when the sprite is drawn rather than kept in it.
+	alpha: f64,
 }
 
 
@@ -335,7 +525,8 @@ impl Sprite {
 				h: tex_query.height as f64,
 				x: 0.0,
 				y: 0.0,
-			}
+			},
+			alpha: 1.0,
 		}
 	}
 
@@ -356,12 +547,19 @@ impl Sprite {
 	}
 
 
+	/// Swaps the texture shared by this sprite, its clones and its regions for
+	/// the given one.
+	pub fn replace_texture(&self, texture: Texture) {
+		*self.tex.borrow_mut() = texture;
+	}
+
+
 	pub fn get_alpha(&self) -> f64 {
-		self.tex.borrow().alpha_mod() as f64 / 255.0
+		self.alpha
 	}
 
 	pub fn set_alpha(&mut self, alpha: f64) {
-		self.tex.borrow_mut().set_alpha_mod((alpha.max(0.0).min(1.0) * 255.0).round() as u8);
+		self.alpha = alpha.max(0.0).min(1.0);
 	}
 
 	/// Returns a new `Sprite` representing a sub-region of the current one.
@@ -380,6 +578,7 @@ impl Sprite {
 			return Some(Sprite {
 				tex: self.tex.clone(),
 				src: new_src,
+				alpha: self.alpha,
 			})
 		}
 		None
@@ -393,27 +592,351 @@ impl Sprite {
 	pub fn frame(&self) -> &Rectangle {
 		&self.src
 	}
+
+	/// Identifies the texture shared by this sprite, its clones and its
+	/// regions, so that draws may be grouped by texture.
+	pub fn texture_id(&self) -> usize {
+		&*self.tex as *const RefCell<Texture> as usize
+	}
 }
 
 impl Renderable for Sprite {
 	fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
-		renderer.copy(&mut self.tex.borrow_mut(), self.src.to_sdl(), dest.to_sdl()).unwrap();
+		let mut tex = self.tex.borrow_mut();
+
+		if self.alpha < 1.0 {
+			let alpha_mod = tex.alpha_mod();
+
+			tex.set_alpha_mod(to_alpha_mod(self.alpha));
+			renderer.copy(&mut tex, self.src.to_sdl(), dest.to_sdl()).unwrap();
+			tex.set_alpha_mod(alpha_mod);
+		}
+		else {
+			renderer.copy(&mut tex, self.src.to_sdl(), dest.to_sdl()).unwrap();
+		}
+	}
+
+	fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+		let mut tex = self.tex.borrow_mut();
+		//? Modulation belongs to the texture, which is shared by every region
+		//? and clone of the sprite, so we restore it once the copy is done.
+		let (color_mod, alpha_mod, blend_mode) = (tex.color_mod(), tex.alpha_mod(), tex.blend_mode());
+		let (r, g, b) = params.color.rgb();
+
+		tex.set_color_mod(r, g, b);
+		tex.set_alpha_mod(to_alpha_mod(self.alpha * params.alpha.max(0.0).min(1.0)));
+		tex.set_blend_mode(params.blend.to_sdl());
+
+		let pivot = params.pivot.unwrap_or((dest.w / 2.0, dest.h / 2.0));
+
+		renderer.copy_ex(&tex, self.src.to_sdl(), dest.to_sdl(), 
+			params.angle, Some(SdlPoint::new(pivot.0 as i32, pivot.1 as i32)),
+			params.flip_h, params.flip_v).unwrap();
+
+		tex.set_color_mod(color_mod.0, color_mod.1, color_mod.2);
+		tex.set_alpha_mod(alpha_mod);
+		tex.set_blend_mode(blend_mode);
+	}
+}
+
+
+/// Converts an opacity, in [0, 1], to the alpha modulation of a texture.
+fn to_alpha_mod(alpha: f64) -> u8 {
+	(alpha * 255.0).round() as u8
+}
+
+
+/// The way an animation goes through its frames.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayMode {
+	/// Plays the frames once, then stays at the last one.
+	Once,
+	/// Plays the frames over and over.
+	Loop,
+	/// Plays the frames forth, then back, and so on.
+	PingPong,
+	/// Plays the frames up to `end` (exclusive), then loops over the range
+	/// [`start`, `end`) only.
+	LoopRange(usize, usize),
+}
+
+
+/// Where an animation is at, and how it goes through its frames. It knows
+/// nothing of the frames but their durations, so that it may be driven (and
+/// tested) without a renderer.
+#[derive(Clone, Debug)]
+pub struct Playback {
+	/// The time every frame is shown for, in seconds.
+	durations: Vec<f64>,
+
+	mode: PlayMode,
+
+	/// The multiplier applied to the elapsed time. If the value is negative,
+	/// then we "rewind" the animation.
+	speed: f64,
+	paused: bool,
+
+	current_frame: usize,
+	/// The time the current frame has been shown for.
+	frame_time: f64,
+	/// Either 1 or -1, the latter while a `PingPong` animation goes back.
+	direction: isize,
+	finished: bool,
+
+	/// The frames entered during the last call to `add_time`, in order.
+	entered: Vec<usize>,
+}
+
+impl Playback {
+	/// Creates a looping playback at time 0, which frames are shown for their
+	/// own `durations` (in seconds).
+	pub fn new(durations: Vec<f64>) -> Playback {
+		if durations.is_empty() {
+			panic!("Passed no frames to Playback");
+		}
+		if durations.iter().any(|&duration| duration <= 0.0) {
+			panic!("Passed a non-positive frame duration to Playback");
+		}
+		Playback {
+			durations: durations,
+
+			mode: PlayMode::Loop,
+
+			speed: 1.0,
+			paused: false,
+
+			current_frame: 0,
+			frame_time: 0.0,
+			direction: 1,
+			finished: false,
+
+			entered: vec![],
+		}
+	}
+
+
+	pub fn frames_count(&self) -> usize {
+		self.durations.len()
+	}
+
+	pub fn current_frame_index(&self) -> usize {
+		self.current_frame
+	}
+
+	/// The time it takes to go through every frame once, in seconds, at the
+	/// normal speed.
+	pub fn duration(&self) -> f64 {
+		self.durations.iter().fold(0.0, |total, duration| total + duration)
+	}
+
+
+	/// Set the time it takes to get from one frame to the next, in seconds,
+	/// for every frame. If the value is negative, then we "rewind" the
+	/// animation.
+	pub fn set_frame_delay(&mut self, frame_delay: f64) {
+		if frame_delay == 0.0 {
+			panic!("Passed 0 to Playback::set_frame_delay");
+		}
+		for duration in &mut self.durations {
+			*duration = frame_delay.abs();
+		}
+		self.frame_time = self.frame_time.min(frame_delay.abs());
+		self.speed = self.speed.abs() * frame_delay.signum();
+	}
+
+	/// Set the number of frames the animation goes through every second.
+	/// If the value is negative, then we "rewind" the animation.
+	pub fn set_fps(&mut self, fps: f64) {
+		if fps == 0.0 {
+			panic!("Passed 0 to Playback::set_fps");
+		}
+		self.set_frame_delay(1.0 / fps);
+	}
+
+	/// Set the time the `index`-th frame is shown for, in seconds.
+	pub fn set_frame_duration(&mut self, index: usize, duration: f64) {
+		if duration <= 0.0 {
+			panic!("Passed a non-positive duration to Playback::set_frame_duration");
+		}
+		self.durations[index] = duration;
+	}
+
+	/// Set the multiplier applied to the elapsed time. If the value is
+	/// negative, then we "rewind" the animation.
+	pub fn set_speed(&mut self, speed: f64) {
+		self.speed = speed;
+	}
+
+	/// Ranges of a `LoopRange` are clamped to the frames there are, and hold
+	/// one frame at least.
+	pub fn set_mode(&mut self, mode: PlayMode) {
+		self.mode = match mode {
+			PlayMode::LoopRange(start, end) => {
+				let end = end.max(1).min(self.frames_count());
+				PlayMode::LoopRange(start.min(end - 1), end)
+			},
+			mode => mode,
+		};
+		self.finished = false;
+	}
+
+	pub fn mode(&self) -> PlayMode {
+		self.mode
+	}
+
+
+	pub fn pause(&mut self) {
+		self.paused = true;
+	}
+
+	pub fn resume(&mut self) {
+		self.paused = false;
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+
+	/// Whether a `Once` animation has got through its last frame.
+	pub fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	/// Whether the `index`-th frame was entered during the last call to
+	/// `add_time`, even if the animation went past it within that call.
+	pub fn entered_frame(&self, index: usize) -> bool {
+		self.entered.contains(&index)
+	}
+
+	/// The frames entered during the last call to `add_time`, in order.
+	pub fn frames_entered(&self) -> &[usize] {
+		&self.entered
+	}
+
+
+	/// Rewinds the animation to its first frame.
+	pub fn restart(&mut self) {
+		self.current_frame = 0;
+		self.frame_time = 0.0;
+		self.direction = 1;
+		self.finished = false;
+		self.entered.clear();
+	}
+
+	/// Moves the animation to the given `index`-th frame, or to the last one
+	/// if there aren't that many.
+	pub fn seek_frame(&mut self, index: usize) {
+		self.current_frame = index.min(self.frames_count() - 1);
+		self.frame_time = 0.0;
+		self.finished = false;
+		self.entered.clear();
+	}
+
+	/// Moves the animation to the state it would reach after playing for the
+	/// given time, in seconds, from its start at the normal speed.
+	pub fn seek(&mut self, time: f64) {
+		let (speed, paused) = (self.speed, self.paused);
+
+		self.restart();
+		self.speed = 1.0;
+		self.paused = false;
+		self.add_time(time.max(0.0));
+		self.speed = speed;
+		self.paused = paused;
+		self.entered.clear();
+	}
+
+	/// Adds a certain amount of time, in seconds, to the time the current
+	/// frame is shown for, so that it knows when it must go to the next frame.
+	pub fn add_time(&mut self, dt: f64) {
+		self.entered.clear();
+
+		if self.paused {
+			return;
+		}
+		let time = dt * self.speed;
+		let mut remaining = time.abs();
+
+		//? The direction is settled once, so that landing right between two
+		//? frames doesn't send us back where we came from.
+		while !self.finished {
+			if time >= 0.0 {
+				let left = self.durations[self.current_frame] - self.frame_time;
+
+				if remaining < left {
+					self.frame_time += remaining;
+					break;
+				}
+				remaining -= left;
+				self.step(1);
+			} else {
+				if remaining < self.frame_time {
+					self.frame_time -= remaining;
+					break;
+				}
+				remaining -= self.frame_time;
+				self.step(-1);
+			}
+		}
+	}
+
+	/// Enters the frame next to the current one, in the direction of time
+	/// given by `step`, according to the play mode.
+	fn step(&mut self, step: isize) {
+		let count = self.frames_count() as isize;
+		let current = self.current_frame as isize;
+
+		let next = match self.mode {
+			PlayMode::Once => {
+				if current + step < 0 || current + step >= count {
+					self.finished = true;
+					self.frame_time = if step > 0 { self.durations[self.current_frame] } else { 0.0 };
+
+					return;
+				}
+				current + step
+			},
+			PlayMode::Loop => {
+				(current + step + count) % count
+			},
+			PlayMode::PingPong => {
+				if count == 1 {
+					current
+				} else {
+					if current + step * self.direction < 0 || current + step * self.direction >= count {
+						self.direction = -self.direction;
+					}
+					current + step * self.direction
+				}
+			},
+			PlayMode::LoopRange(start, end) => {
+				let (start, end) = (start as isize, end as isize);
+
+				if step > 0 && current + 1 >= end {
+					start
+				} else if step < 0 && current == start {
+					end - 1
+				} else {
+					(current + step).max(0)
+				}
+			},
+		};
+		self.current_frame = next as usize;
+		self.frame_time = if step > 0 { 0.0 } else { self.durations[self.current_frame] };
+		self.entered.push(self.current_frame);
 	}
 }
 
 
+/// Frames played back over time.
 #[derive(Clone)]
 pub struct AnimatedSprite {
 	/// The frames that will be rendered, in order.
 	sprites: Vec<Sprite>,
-
-	/// The time it takes to get from one frame to the next, in seconds.
-	frame_delay: f64,
-
-	/// The total time that the sprite has been alive, from which the current
-	/// frame is derived.
-	current_time: f64,
-	current_frame: usize,
+	/// The frame-local points the frames are rotated around, if they aren't
+	/// their centers.
+	pivots: Vec<(f64, f64)>,
+	playback: Playback,
 }
 
 pub struct AnimatedSpriteDescr {
@@ -425,21 +948,41 @@ pub struct AnimatedSpriteDescr {
 }
 
 
+const DEFAULT_FRAME_DURATION: f64 = 0.1;
+
 impl AnimatedSprite {	
-	/// Creates a new animated sprite initialized at time 0.
+	/// Creates a new looping animated sprite initialized at time 0.
 	pub fn new(sprites: Vec<Sprite>, fps: f64) -> AnimatedSprite {
+		if fps == 0.0 {
+			panic!("Passed 0 to AnimatedSprite::new");
+		}
+		let mut result = AnimatedSprite::with_durations(sprites, vec![]);
+
+		result.set_fps(fps);
+		result
+	}
+
+	/// Creates a new looping animated sprite initialized at time 0, which
+	/// frames are shown for their own `durations` (in seconds).
+	pub fn with_durations(sprites: Vec<Sprite>, durations: Vec<f64>) -> AnimatedSprite {
+		if sprites.is_empty() {
+			panic!("Passed no frames to AnimatedSprite");
+		}
+		let mut durations = durations;
+
+		durations.resize(sprites.len(), DEFAULT_FRAME_DURATION);
+
 		AnimatedSprite {
 			sprites: sprites,
-			frame_delay: 1f64 / fps,
-			current_time: 0.0,
-			current_frame: 0,
+			pivots: vec![],
+			playback: Playback::new(durations),
 		}
 	}
 
 	pub fn load(path: &str, phi: &mut Phi, descr: AnimatedSpriteDescr) -> AnimatedSprite {
 		let spritesheet = Sprite::load(&mut phi.renderer, path).unwrap();
 
-		AnimatedSprite::new(Self::load_frames(&spritesheet, descr), 0.0)
+		AnimatedSprite::with_durations(Self::load_frames(&spritesheet, descr), vec![])
 	}
 
 	pub fn load_with_fps(path: &str, phi: &mut Phi, descr: AnimatedSpriteDescr, fps: f64) -> AnimatedSprite {
@@ -480,40 +1023,117 @@ impl AnimatedSprite {
 	}
 
 	pub fn current_frame_index(&self) -> usize {
-		self.current_frame
+		self.playback.current_frame_index()
 	}
 
 	pub fn get_frame_at(&self, index: usize) -> &Sprite {
 		&self.sprites[index]
 	}
 
+	pub fn playback(&self) -> &Playback {
+		&self.playback
+	}
 
-	/// Set the time it takes to get from one frame to the next, in seconds.
-	/// If the value is negative, then we "rewind" the animation.
+	pub fn playback_mut(&mut self) -> &mut Playback {
+		&mut self.playback
+	}
+
+	/// The time it takes to go through every frame once, in seconds, at the
+	/// normal speed.
+	pub fn duration(&self) -> f64 {
+		self.playback.duration()
+	}
+
+
+	/// Set the time it takes to get from one frame to the next, in seconds,
+	/// for every frame. If the value is negative, then we "rewind" the
+	/// animation.
 	pub fn set_frame_delay(&mut self, frame_delay: f64) {
-		self.frame_delay = frame_delay;
+		self.playback.set_frame_delay(frame_delay);
 	}
 
 	/// Set the number of frames the animation goes through every second.
 	/// If the value is negative, then we "rewind" the animation.
 	pub fn set_fps(&mut self, fps: f64) {
-		if fps == 0.0 {
-			panic!("Passed 0 to AnimatedSprite::set_fps");
-		}
-		self.set_frame_delay(1.0 / fps);
+		self.playback.set_fps(fps);
 	}
 
-	/// Adds a certain amount of time, in seconds, to the `current_time` of the
-	/// animated sprite, so that it knows when it must go to the next frame.
-	pub fn add_time(&mut self, dt: f64) {
-		self.current_time += dt;
+	/// Set the time the `index`-th frame is shown for, in seconds.
+	pub fn set_frame_duration(&mut self, index: usize, duration: f64) {
+		self.playback.set_frame_duration(index, duration);
+	}
 
-		// If we decide to go "back in time", this allows us to select the
-		// last frame whenever we reach a negative one.
-		if self.current_time < 0.0 {
-			self.current_time = (self.frames_count() - 1) as f64 * self.frame_delay;
-		}
-		self.current_frame = (self.current_time / self.frame_delay) as usize % self.frames_count();
+	/// Set the frame-local points the frames are rotated around, in order,
+	/// unless the draw parameters give their own.
+	pub fn set_pivots(&mut self, pivots: Vec<(f64, f64)>) {
+		self.pivots = pivots;
+	}
+
+	/// Set the multiplier applied to the elapsed time. If the value is
+	/// negative, then we "rewind" the animation.
+	pub fn set_speed(&mut self, speed: f64) {
+		self.playback.set_speed(speed);
+	}
+
+	pub fn set_mode(&mut self, mode: PlayMode) {
+		self.playback.set_mode(mode);
+	}
+
+	pub fn mode(&self) -> PlayMode {
+		self.playback.mode()
+	}
+
+
+	pub fn pause(&mut self) {
+		self.playback.pause();
+	}
+
+	pub fn resume(&mut self) {
+		self.playback.resume();
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.playback.is_paused()
+	}
+
+	/// Whether a `Once` animation has got through its last frame.
+	pub fn is_finished(&self) -> bool {
+		self.playback.is_finished()
+	}
+
+	/// Whether the `index`-th frame was entered during the last call to
+	/// `add_time`, even if the animation went past it within that call.
+	pub fn entered_frame(&self, index: usize) -> bool {
+		self.playback.entered_frame(index)
+	}
+
+	/// The frames entered during the last call to `add_time`, in order.
+	pub fn frames_entered(&self) -> &[usize] {
+		self.playback.frames_entered()
+	}
+
+
+	/// Rewinds the animation to its first frame.
+	pub fn restart(&mut self) {
+		self.playback.restart();
+	}
+
+	/// Moves the animation to the given `index`-th frame, or to the last one
+	/// if there aren't that many.
+	pub fn seek_frame(&mut self, index: usize) {
+		self.playback.seek_frame(index);
+	}
+
+	/// Moves the animation to the state it would reach after playing for the
+	/// given time, in seconds, from its start at the normal speed.
+	pub fn seek(&mut self, time: f64) {
+		self.playback.seek(time);
+	}
+
+	/// Adds a certain amount of time, in seconds, to the time the current
+	/// frame is shown for, so that it knows when it must go to the next frame.
+	pub fn add_time(&mut self, dt: f64) {
+		self.playback.add_time(dt);
 	}
 }
 
@@ -522,6 +1142,363 @@ impl Renderable for AnimatedSprite {
 	fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
 		self.sprites[self.current_frame_index()].render(renderer, dest);
 	}
+
+	fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, params: &DrawParams) {
+		let index = self.current_frame_index();
+		let sprite = &self.sprites[index];
+
+		match (params.pivot, self.pivots.get(index)) {
+			(None, Some(&(x, y))) => {
+				//? The pivot is given in the frame's pixels, which are
+				//? stretched over the destination.
+				let (w, h) = sprite.size();
+				let pivot = (x * dest.w / w, y * dest.h / h);
+
+				sprite.render_ex(renderer, dest, &DrawParams { pivot: Some(pivot), ..*params });
+			},
+			_ => sprite.render_ex(renderer, dest, params),
+		}
+	}
+}
+
+
+/// Describes a single frame of an atlas, as it is written in the descriptor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasFrameDescr {
+	pub name: String,
+	/// The region of the frame on the atlas image.
+	pub rect: Rectangle,
+	/// The frame-local point the frame is anchored at. Defaults to the
+	/// frame's center.
+	pub pivot: (f64, f64),
+	/// How long the frame is shown within clips, in seconds.
+	pub duration: f64,
+}
+
+/// Describes a named sequence of frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasClipDescr {
+	pub name: String,
+	pub frames: Vec<String>,
+	/// How the clip is played. Defaults to `PlayMode::Loop`.
+	pub mode: PlayMode,
+}
+
+/// The contents of an atlas descriptor file. Those are plain text files, with
+/// one statement per line (`#` starts a comment):
+///
+///     image <path relative to the descriptor>
+///     frame <name> <x> <y> <w> <h> [pivot <x> <y>] [duration <seconds>]
+///     grid <prefix> <x> <y> <w> <h> <columns> <count> [pivot <x> <y>] [duration <seconds>]
+///     clip <name> [once|loop|pingpong|loop_range <start> <end>] <frame or prefix*>...
+///
+/// `grid` is a shorthand which declares `count` frames named `<prefix>_<i>`,
+/// laid out row by row, `columns` frames per row, starting at (`x`, `y`).
+/// Clips refer to frames declared above them; a name ending with `*` stands
+/// for every such frame which name starts with the given prefix. The optional
+/// play mode of a clip follows its name; clips loop by default.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasDescr {
+	pub image: PathBuf,
+	pub frames: Vec<AtlasFrameDescr>,
+	pub clips: Vec<AtlasClipDescr>,
+}
+
+impl AtlasDescr {
+
+	pub fn load(path: &Path) -> Result<AtlasDescr, io::Error> {
+		let mut source = String::new();
+
+		try!(try!(File::open(path)).read_to_string(&mut source));
+
+		AtlasDescr::parse(&source, path.parent().unwrap_or(Path::new("")))
+	}
+
+	/// Parses the descriptor `source`. The image path is resolved against the
+	/// `base` directory.
+	pub fn parse(source: &str, base: &Path) -> Result<AtlasDescr, io::Error> {
+		let mut image = None;
+		let mut frames: Vec<AtlasFrameDescr> = vec![];
+		let mut clips = vec![];
+
+		for mut line in Lines::new(source, ATLAS_DESCR) {
+			match line.statement() {
+				"image" => {
+					image = Some(base.join(try!(line.expect("image path"))));
+				},
+				"frame" => {
+					let name = try!(line.expect("frame name"));
+					let rect = Rectangle {
+						x: try!(line.parse()),
+						y: try!(line.parse()),
+						w: try!(line.parse()),
+						h: try!(line.parse()),
+					};
+					let (pivot, duration) = try!(parse_frame_options(&mut line, &rect));
+
+					frames.push(AtlasFrameDescr {
+						name: name.to_string(),
+						rect: rect,
+						pivot: pivot,
+						duration: duration,
+					});
+				},
+				"grid" => {
+					let prefix = try!(line.expect("grid prefix"));
+					let origin: (f64, f64) = (try!(line.parse()), try!(line.parse()));
+					let size: (f64, f64) = (try!(line.parse()), try!(line.parse()));
+					let columns: usize = try!(line.parse());
+					let count: usize = try!(line.parse());
+
+					if columns == 0 {
+						return Err(line.error("grid must have at least one column"));
+					}
+					let (pivot, duration) = try!(parse_frame_options(&mut line, &Rectangle::with_size(size.0, size.1)));
+
+					for index in 0..count {
+						frames.push(AtlasFrameDescr {
+							name: format!("{}_{}", prefix, index),
+							rect: Rectangle {
+								x: origin.0 + size.0 * (index % columns) as f64,
+								y: origin.1 + size.1 * (index / columns) as f64,
+								w: size.0,
+								h: size.1,
+							},
+							pivot: pivot,
+							duration: duration,
+						});
+					}
+				},
+				"clip" => {
+					let name = try!(line.expect("clip name"));
+					let mut clip_frames = vec![];
+
+					let mode = match line.peek() {
+						Some("once") => { line.next(); PlayMode::Once },
+						Some("loop") => { line.next(); PlayMode::Loop },
+						Some("pingpong") => { line.next(); PlayMode::PingPong },
+						Some("loop_range") => {
+							line.next();
+							PlayMode::LoopRange(try!(line.parse()), try!(line.parse()))
+						},
+						_ => PlayMode::Loop,
+					};
+
+					while let Some(pattern) = line.next() {
+						let matches: Vec<String> = frames.iter()
+						.filter(|frame| if pattern.ends_with('*') {
+							frame.name.starts_with(&pattern[..pattern.len() - 1])
+						} else {
+							frame.name == pattern
+						})
+						.map(|frame| frame.name.clone())
+						.collect();
+
+						if matches.is_empty() {
+							return Err(line.error(&format!("no frames match '{}'", pattern)));
+						}
+						clip_frames.extend(matches);
+					}
+					if clip_frames.is_empty() {
+						return Err(line.error("clip must have at least one frame"));
+					}
+					if let PlayMode::LoopRange(start, end) = mode {
+						if start >= end || end > clip_frames.len() {
+							return Err(line.error("invalid loop range"));
+						}
+					}
+					clips.push(AtlasClipDescr {
+						name: name.to_string(),
+						frames: clip_frames,
+						mode: mode,
+					});
+				},
+				statement => {
+					return Err(line.error(&format!("unknown statement '{}'", statement)));
+				},
+			}
+		}
+		Ok(AtlasDescr {
+			image: try!(image.ok_or(descr::error(ATLAS_DESCR, 0, "image path is missing"))),
+			frames: frames,
+			clips: clips,
+		})
+	}
+}
+
+const ATLAS_DESCR: &'static str = "atlas descriptor";
+
+fn parse_frame_options(line: &mut Line, rect: &Rectangle) -> Result<((f64, f64), f64), io::Error> {
+	let mut pivot = (rect.w / 2.0, rect.h / 2.0);
+	let mut duration = DEFAULT_FRAME_DURATION;
+
+	while let Some(option) = line.next() {
+		match option {
+			"pivot" => {
+				pivot = (try!(line.parse()), try!(line.parse()));
+			},
+			"duration" => {
+				duration = try!(line.parse());
+
+				if duration <= 0.0 {
+					return Err(line.error("duration must be positive"));
+				}
+			},
+			_ => return Err(line.error(&format!("unknown option '{}'", option)))
+		}
+	}
+	Ok((pivot, duration))
+}
+
+
+/// A frame of an atlas, ready to be rendered.
+#[derive(Clone)]
+pub struct AtlasFrame {
+	pub sprite: Sprite,
+	pub pivot: (f64, f64),
+	pub duration: f64,
+}
+
+/// A set of named frames and clips, which share the same image. Built from an
+/// `AtlasDescr`, so that spritesheets may be repacked without code changes.
+#[derive(Clone)]
+pub struct Atlas {
+	image: PathBuf,
+	frames: HashMap<String, AtlasFrame>,
+	clips: HashMap<String, (Vec<String>, PlayMode)>,
+}
+
+impl Atlas {
+
+	/// Slices the `sheet` according to the `descr`. Returns `None` if any of
+	/// the frames doesn't fit into the sheet.
+	pub fn new(sheet: &Sprite, descr: &AtlasDescr) -> Option<Atlas> {
+		let mut frames = HashMap::with_capacity(descr.frames.len());
+
+		for frame in &descr.frames {
+			frames.insert(frame.name.clone(), AtlasFrame {
+				sprite: tryo!(sheet.region(frame.rect)),
+				pivot: frame.pivot,
+				duration: frame.duration,
+			});
+		}
+		Some(Atlas {
+			image: descr.image.clone(),
+			frames: frames,
+			clips: descr.clips.iter()
+			.map(|clip| (clip.name.clone(), (clip.frames.clone(), clip.mode)))
+			.collect(),
+		})
+	}
+
+	/// Loads the atlas descriptor located at the given path, along with the
+	/// image it refers to.
+	pub fn load(renderer: &Renderer, path: &str) -> Result<Atlas, io::Error> {
+		let descr = try!(AtlasDescr::load(Path::new(path)));
+		let sheet = try!(descr.image.to_str()
+			.and_then(|image_path| Sprite::load(renderer, image_path))
+			.ok_or(io::Error::new(io::ErrorKind::NotFound, "Can't load atlas image!")));
+
+		Atlas::new(&sheet, &descr)
+		.ok_or(io::Error::new(io::ErrorKind::InvalidData, "Atlas frames don't fit the image!"))
+	}
+
+
+	/// The path of the image the frames are sliced from.
+	pub fn image(&self) -> &Path {
+		&self.image
+	}
+
+	pub fn frame(&self, name: &str) -> Option<&Sprite> {
+		self.frames.get(name).map(|frame| &frame.sprite)
+	}
+
+	/// Returns the frames of the named clip, in order.
+	pub fn clip_frames(&self, name: &str) -> Option<Vec<&AtlasFrame>> {
+		self.clips.get(name).map(|&(ref names, _)| {
+			names.iter().map(|name| &self.frames[name]).collect()
+		})
+	}
+
+	/// The time it takes to play the named clip through, in seconds.
+	pub fn clip_duration(&self, name: &str) -> Option<f64> {
+		self.clip_frames(name).map(|frames| {
+			frames.iter().fold(0.0, |duration, frame| duration + frame.duration)
+		})
+	}
+
+	/// Creates a new animated sprite, which plays the named clip, every frame
+	/// being shown for its own duration and rotated around its own pivot.
+	pub fn clip(&self, name: &str) -> Option<AnimatedSprite> {
+		let frames = tryo!(self.clip_frames(name));
+		let mut sprite = AnimatedSprite::with_durations(
+			frames.iter().map(|frame| frame.sprite.clone()).collect(),
+			frames.iter().map(|frame| frame.duration).collect());
+
+		sprite.set_mode(self.clips[name].1);
+		sprite.set_pivots(frames.iter().map(|frame| frame.pivot).collect());
+		Some(sprite)
+	}
+}
+
+
+/// How the rendered pixels are combined with the ones they're drawn over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blend {
+	Alpha,
+	Additive,
+	Modulate,
+}
+
+impl Blend {
+	pub fn to_sdl(&self) -> BlendMode {
+		match *self {
+			Blend::Alpha => BlendMode::Blend,
+			Blend::Additive => BlendMode::Add,
+			Blend::Modulate => BlendMode::Mod,
+		}
+	}
+}
+
+
+/// Transformations applied to a graphical component while it's rendered.
+/// Fields are meant to be overridden over the defaults, as in:
+///
+///     DrawParams { angle: 90.0, blend: Blend::Additive, ..DrawParams::default() }
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawParams {
+	/// Clockwise rotation, in degrees.
+	pub angle: f64,
+	/// The point to rotate around, relative to the destination's top-left
+	/// corner. `None` stands for the destination's center.
+	pub pivot: Option<(f64, f64)>,
+
+	pub flip_h: bool,
+	pub flip_v: bool,
+
+	/// The color the component is tinted with; white keeps it intact.
+	pub color: Color,
+	/// Opacity in [0, 1], applied over the component's own.
+	pub alpha: f64,
+
+	pub blend: Blend,
+}
+
+impl Default for DrawParams {
+	fn default() -> DrawParams {
+		DrawParams {
+			angle: 0.0,
+			pivot: None,
+
+			flip_h: false,
+			flip_v: false,
+
+			color: Color::RGB(255, 255, 255),
+			alpha: 1.0,
+
+			blend: Blend::Alpha,
+		}
+	}
 }
 
 
@@ -529,14 +1506,270 @@ impl Renderable for AnimatedSprite {
 /// of the window.
 pub trait Renderable {
 	fn render(&self, renderer: &mut Renderer, dest: Rectangle);
+
+	/// Renders the component, transformed according to the `params`. Those
+	/// components, which don't support transformations, ignore them.
+	fn render_ex(&self, renderer: &mut Renderer, dest: Rectangle, _params: &DrawParams) {
+		self.render(renderer, dest);
+	}
 }
 
 pub trait CopySprite<T> {
 	fn copy_sprite(&mut self, sprite: &T, dest: Rectangle);
+
+	fn copy_sprite_ex(&mut self, sprite: &T, dest: Rectangle, params: &DrawParams);
 }
 
 impl<'window, T: Renderable> CopySprite<T> for Renderer<'window> {
 	fn copy_sprite(&mut self, renderable: &T, dest: Rectangle) {
 		renderable.render(self, dest);
 	}
-}
\ No newline at end of file
+
+	fn copy_sprite_ex(&mut self, renderable: &T, dest: Rectangle, params: &DrawParams) {
+		renderable.render_ex(self, dest, params);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate rand;
+	extern crate time;
+
+	use super::*;
+	use phi::data::Rectangle;
+	use self::rand::{Rng, SeedableRng, XorShiftRng};
+	use self::time::{ PreciseTime, Duration };
+
+
+	/// Brute-force per-pixel reference of `AlphaChannel::intersect`.
+	fn intersect_reference(channel_a: &AlphaChannel, x_a: f64, y_a: f64, channel_b: &AlphaChannel, x_b: f64, y_b: f64, roi: Rectangle) -> bool {
+		let (w, h) = (roi.w.round() as i64, roi.h.round() as i64);
+		let (x_a, x_b) = ((roi.x - x_a).round() as i64, (roi.x - x_b).round() as i64);
+		let (y_a, y_b) = ((roi.y - y_a).round() as i64, (roi.y - y_b).round() as i64);
+
+		for r in 0..h {
+			for c in 0..w {
+				if opaque(channel_a, x_a + c, y_a + r) && opaque(channel_b, x_b + c, y_b + r) {
+					return true;
+				}
+			}
+		}
+		false
+	}
+
+	fn opaque(channel: &AlphaChannel, x: i64, y: i64) -> bool {
+		x >= 0 && y >= 0 && channel.get(x as usize, y as usize)
+	}
+
+	/// Generates a blob (an ellipse with some holes punched), surrounded by
+	/// transparent margins, so that masks have empty rows and ragged spans.
+	fn random_mask(rng: &mut XorShiftRng) -> AlphaChannel {
+		let (w, h) = (rng.gen_range(1, 200u32), rng.gen_range(1, 200u32));
+		let (rx, ry) = (w as f64 * rng.gen_range(0.1, 0.5), h as f64 * rng.gen_range(0.1, 0.5));
+		let holes: Vec<bool> = (0..w * h).map(|_| rng.gen::<f64>() < 0.2).collect();
+
+		AlphaChannel::from_fn(w, h, |x, y| {
+			let (dx, dy) = ((x as f64 + 0.5 - w as f64 / 2.0) / rx, (y as f64 + 0.5 - h as f64 / 2.0) / ry);
+
+			dx * dx + dy * dy <= 1.0 && !holes[(y * w + x) as usize]
+		})
+	}
+
+	/// Places both masks at random, so that their frames overlap, and returns
+	/// their origins along with the ROI.
+	fn random_placement(rng: &mut XorShiftRng, a: &AlphaChannel, b: &AlphaChannel) -> ((f64, f64), (f64, f64), Rectangle) {
+		loop {
+			let origin_a = (rng.gen_range(0, 100) as f64, rng.gen_range(0, 100) as f64);
+			let origin_b = (rng.gen_range(-100, 200) as f64, rng.gen_range(-100, 200) as f64);
+			let frame = |origin: (f64, f64), channel: &AlphaChannel| Rectangle {
+				x: origin.0,
+				y: origin.1,
+				w: channel.width() as f64,
+				h: channel.height() as f64,
+			};
+			if let Some(roi) = Rectangle::intersection(&frame(origin_a, a), &frame(origin_b, b)) {
+				return (origin_a, origin_b, roi);
+			}
+		}
+	}
+
+
+	const RANDOM_CASES_COUNT: usize = 4000;
+
+	#[test]
+	fn intersect_matches_reference() {
+		let mut rng = XorShiftRng::from_seed([0x1234, 0x5678, 0x9abc, 0xdef0]);
+
+		for _ in 0..RANDOM_CASES_COUNT {
+			let (a, b) = (random_mask(&mut rng), random_mask(&mut rng));
+			let (origin_a, origin_b, roi) = random_placement(&mut rng, &a, &b);
+
+			assert_eq!(
+				AlphaChannel::intersect(&a, origin_a.0, origin_a.1, &b, origin_b.0, origin_b.1, roi),
+				intersect_reference(&a, origin_a.0, origin_a.1, &b, origin_b.0, origin_b.1, roi));
+		}
+	}
+
+	#[test]
+	fn intersect_box_matches_reference() {
+		let mut rng = XorShiftRng::from_seed([0x0fed, 0xcba9, 0x8765, 0x4321]);
+
+		for _ in 0..RANDOM_CASES_COUNT {
+			let a = random_mask(&mut rng);
+			let b = AlphaChannel::from_fn(rng.gen_range(1, 100u32), rng.gen_range(1, 100u32), |_, _| true);
+			let (origin_a, origin_b, roi) = random_placement(&mut rng, &a, &b);
+
+			assert_eq!(
+				AlphaChannel::intersect_box(&a, origin_a.0, origin_a.1, roi),
+				intersect_reference(&a, origin_a.0, origin_a.1, &b, origin_b.0, origin_b.1, roi));
+		}
+	}
+
+	#[test]
+	fn contact_of_overlapping_squares() {
+		let square = AlphaChannel::from_fn(10, 10, |_, _| true);
+		let roi = Rectangle { x: 6.0, y: 0.0, w: 4.0, h: 10.0 };
+		let contact = AlphaChannel::contact(&square, 0.0, 0.0, &square, 6.0, 0.0, roi).unwrap();
+
+		assert_eq!(contact.pixels, 40);
+		assert_eq!(contact.centroid, (8.0, 5.0));
+		assert_eq!(contact.normal, (1.0, 0.0));
+		assert_eq!(contact.depth, 4.0);
+	}
+
+
+	#[test]
+	fn atlas_descr_parses_frames_grids_and_clips() {
+		let descr = AtlasDescr::parse("
+			# Frames are declared before clips using them.
+			image sheet.png
+
+			frame logo 0 0 32 16 pivot 0 8
+			grid boom 0 16 8 8 2 3 duration 0.25
+
+			clip boom once boom_*
+			clip intro logo boom_2
+			clip idle loop_range 1 3 boom_*
+			", Path::new("assets")).unwrap();
+
+		assert_eq!(descr.image, Path::new("assets").join("sheet.png"));
+		assert_eq!(descr.frames.len(), 4);
+		assert_eq!(descr.frames[0].pivot, (0.0, 8.0));
+		assert_eq!(descr.frames[1].pivot, (4.0, 4.0));
+		assert_eq!(descr.frames[3].name, "boom_2");
+		assert_eq!(descr.frames[3].rect, Rectangle { x: 0.0, y: 24.0, w: 8.0, h: 8.0 });
+		assert_eq!(descr.frames[3].duration, 0.25);
+		assert_eq!(descr.clips[0].frames, vec!["boom_0", "boom_1", "boom_2"]);
+		assert_eq!(descr.clips[1].frames, vec!["logo", "boom_2"]);
+		assert_eq!(descr.clips[0].mode, PlayMode::Once);
+		assert_eq!(descr.clips[1].mode, PlayMode::Loop);
+		assert_eq!(descr.clips[2].mode, PlayMode::LoopRange(1, 3));
+
+		assert!(AtlasDescr::parse("image sheet.png\nclip boom boom_*", Path::new("")).is_err());
+		assert!(AtlasDescr::parse("frame logo 0 0 32", Path::new("")).is_err());
+		assert!(AtlasDescr::parse("image sheet.png\nframe a 0 0 1 1\nclip a loop_range 0 2 a", Path::new("")).is_err());
+	}
+
+
+	fn measure<F>(mut action: F)  -> Duration where F: FnMut() -> () {
+		let start = PreciseTime::now();
+		{
+			action();	
+		}
+		start.to(PreciseTime::now())
+	}
+
+
+	const INTERSECT_REPEAT_COUNT: usize = 200;
+
+	#[test]
+	fn playback_modes_go_through_frames() {
+		let mut playback = Playback::new(vec![0.25; 3]);
+
+		playback.add_time(0.25);
+		assert_eq!(playback.current_frame_index(), 1);
+		playback.add_time(0.5);
+		assert_eq!(playback.current_frame_index(), 0);
+		assert_eq!(playback.frames_entered(), &[2, 0]);
+
+		playback.restart();
+		playback.set_mode(PlayMode::Once);
+		playback.add_time(1.0);
+		assert_eq!((playback.current_frame_index(), playback.is_finished()), (2, true));
+		assert_eq!(playback.frames_entered(), &[1, 2]);
+		playback.add_time(1.0);
+		assert_eq!(playback.current_frame_index(), 2);
+		assert!(playback.frames_entered().is_empty());
+
+		playback.restart();
+		playback.set_mode(PlayMode::PingPong);
+		playback.add_time(1.0);
+		assert_eq!(playback.frames_entered(), &[1, 2, 1, 0]);
+
+		playback.restart();
+		playback.set_mode(PlayMode::LoopRange(1, 3));
+		playback.add_time(1.25);
+		assert_eq!(playback.frames_entered(), &[1, 2, 1, 2, 1]);
+
+		playback.set_mode(PlayMode::LoopRange(5, 10));
+		assert_eq!(playback.mode(), PlayMode::LoopRange(2, 3));
+	}
+
+	#[test]
+	fn playback_speed_seek_and_pause() {
+		let mut playback = Playback::new(vec![0.25; 3]);
+
+		playback.set_speed(-1.0);
+		playback.add_time(0.125);
+		assert_eq!(playback.frames_entered(), &[2]);
+		playback.add_time(0.25);
+		assert_eq!(playback.frames_entered(), &[1]);
+
+		playback.set_speed(2.0);
+		playback.add_time(0.125);
+		assert_eq!(playback.current_frame_index(), 2);
+
+		playback.pause();
+		playback.add_time(1.0);
+		assert_eq!(playback.current_frame_index(), 2);
+		assert!(!playback.entered_frame(2));
+		playback.resume();
+
+		playback.seek(0.625);
+		assert_eq!(playback.current_frame_index(), 2);
+		assert!(playback.frames_entered().is_empty());
+
+		playback.seek_frame(10);
+		assert_eq!(playback.current_frame_index(), 2);
+		playback.add_time(0.125);
+		assert!(playback.entered_frame(0));
+	}
+
+	#[test]
+	fn bench_intersect() {
+		let mut rng = XorShiftRng::from_seed([0x2468, 0x1357, 0xaaaa, 0x5555]);
+		let cases: Vec<_> = (0..RANDOM_CASES_COUNT / 4).map(|_| {
+			let (a, b) = (random_mask(&mut rng), random_mask(&mut rng));
+			let (origin_a, origin_b, roi) = random_placement(&mut rng, &a, &b);
+
+			(a, origin_a, b, origin_b, roi)
+		})
+		.collect();
+
+		let run = |intersect: fn(&AlphaChannel, f64, f64, &AlphaChannel, f64, f64, Rectangle) -> bool| {
+			measure(|| {
+				for _ in 0..INTERSECT_REPEAT_COUNT {
+					for &(ref a, origin_a, ref b, origin_b, roi) in &cases {
+						intersect(a, origin_a.0, origin_a.1, b, origin_b.0, origin_b.1, roi);
+					}
+				}
+			})
+			.num_microseconds().unwrap()
+		};
+		let (fast, reference) = (run(AlphaChannel::intersect), run(intersect_reference));
+		let queries_count = (INTERSECT_REPEAT_COUNT * cases.len()) as f64;
+
+		println!("AlphaChannel::intersect takes {:.3}us in average, reference takes {:.3}us.", 
+			fast as f64 / queries_count, reference as f64 / queries_count);
+	}
+}
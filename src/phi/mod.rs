@@ -2,19 +2,43 @@
 #[macro_use]
 mod events;
 
+pub mod assets;
+pub mod audio;
+pub mod backend;
+pub mod color;
 pub mod data;
+pub mod descr;
+pub mod draw;
+pub mod effects;
 pub mod gfx;
+pub mod music;
+pub mod particles;
+pub mod procgen;
+pub mod render;
+pub mod stems;
+pub mod synth;
+pub mod target;
+pub mod text;
+pub mod tween;
+pub mod variants;
 
 
 use sdl2::pixels::Color;
 use sdl2::render::Renderer;
-use sdl2::ttf::{Sdl2TtfContext/*, Font*/};
+use sdl2::ttf::Sdl2TtfContext;
 
-// use std::collections::HashMap;
-use std::path::Path;
 
+pub use self::draw::RendererExtensions;
 
+use self::assets::{Assets, Handle};
+use self::audio::Audio;
+use self::backend::{AudioBackend, NullAudio, SdlMixer, Sound};
+use self::data::Rectangle;
+use self::effects::PostEffects;
 use self::gfx::Sprite;
+use self::music::MusicManager;
+use self::render::RenderQueue;
+use self::text::{TextLayout, TextStyle};
 
 
 struct_events!{
@@ -30,7 +54,9 @@ struct_events!{
 
 		key_1: Num1,
 		key_2: Num2,
-		key_3: Num3
+		key_3: Num3,
+
+		key_m: M
 	},
 	other: {
 		quit: Quit { .. }
@@ -52,31 +78,30 @@ pub enum ViewAction {
 pub struct Phi<'window> {
 	pub events: Events,
 	pub renderer: Renderer<'window>,
-
-	ttf_context: Sdl2TtfContext,
-	// cached_fonts: HashMap<(&'static str, u16), Font<'window, 'static>>,
-
-	allocated_channels: i32,
+	pub assets: Assets<'window>,
+	/// Draw commands submitted while rendering the current view. Flushed by the
+	/// game loop, after `View::render`.
+	pub queue: RenderQueue,
+	/// Full-screen effects, applied over every frame.
+	pub effects: PostEffects,
+	pub audio: Audio,
+	/// The music played in the background, which views switch between.
+	pub music: MusicManager,
 }
 
 impl <'window> Phi<'window> {
-	fn new(events: Events, ttf_context: Sdl2TtfContext, renderer: Renderer<'window>) -> Phi<'window> {
-		let result = Phi {
+	fn new(events: Events, ttf_context: &'window Sdl2TtfContext, renderer: Renderer<'window>, audio: Box<AudioBackend>) -> Phi<'window> {
+		Phi {
 			events: events,
 			renderer: renderer,
-
-			ttf_context: ttf_context,
-			// cached_fonts: HashMap::new(),
-
-			allocated_channels: 32
-		};
-		//? This function asks us how many channels we wish to allocate for our game.
-		//? That is, how many sounds do we wish to be able to play at the same time?
-		//? While testing, 16 channels seemed to be sufficient. Which means that we
-		//? should probably request 32 of 'em just in case. :-°
-		::sdl2::mixer::allocate_channels(result.allocated_channels);
-
-		result
+			assets: Assets::new(ttf_context),
+			queue: RenderQueue::new(),
+			effects: PostEffects::new(),
+			//? The audio starts with 32 channels, that is, 32 sounds playing at
+			//? the same time, and allocates more (up to a limit) when needed.
+			audio: Audio::new(audio),
+			music: MusicManager::new(),
+		}
 	}
 
 	pub fn output_size(&self) -> (f64, f64) {
@@ -84,83 +109,37 @@ impl <'window> Phi<'window> {
 		(w as f64, h as f64)
 	}
 
-	pub fn ttf_str_sprite(&mut self, text: &str, font_path: &'static str, size: u16, color: Color) -> Option<Sprite> {
-		// //? First, we verify whether the font is already cached. If this is the
-		// //? case, we use it to render the text.
-		// if let Some(font) = self.cached_fonts.get(&(font_path, size)) {
-		// 	return font.render(text).blended(color).ok()
-		// 		.and_then(|surface| self.renderer.create_texture_from_surface(&surface).ok())
-		// 		.map(Sprite::from_texture)
-		// }
-		// //? Otherwise, we start by trying to load the requested font.
-		// self.ttf_context.load_font(Path::new(font_path), size).ok()
-		// 	.and_then(|font| {
-		// 		//? If this works, we cache the font we acquired.
-		// 		self.cached_fonts.insert((font_path, size), font);
-		// 		//? Then, we call the method recursively. Because we know that
-		// 		//? the font has been cached, the `if` block will be executed
-		// 		//? and the sprite will be appropriately rendered.
-		// 		self.ttf_str_sprite(text, font_path, size, color)
-		// 	})
-
-		self.ttf_context.load_font(Path::new(font_path), size).ok()
-			.and_then(|font| font.render(text).blended(color).ok())
-			.and_then(|surface| self.renderer.create_texture_from_surface(&surface).ok())
+	pub fn ttf_str_sprite(&mut self, text: &str, font_path: &str, size: u16, color: Color) -> Option<Sprite> {
+		//? The font is loaded once, then served by the assets cache.
+		let font = tryo!(self.assets.font(font_path, size).ok());
+		let surface = tryo!(font.borrow().render(text).blended(color).ok());
+
+		self.renderer.create_texture_from_surface(&surface).ok()
 			.map(Sprite::from_texture)
 	}
 
-	/// Play a sound once, and allocate new channels if this is necessary.
-	pub fn play_sound(&mut self, sound: &::sdl2::mixer::Chunk) {
-		// Attempt to play the sound once.
-		match ::sdl2::mixer::Channel::all().play(sound, 0) {
-			Err(_) => {
-				// If there weren't enough channels allocated, then we double
-				// that number and try again.
-				self.allocated_channels *= 2;
-				::sdl2::mixer::allocate_channels(self.allocated_channels);
-
-				self.play_sound(sound);
-			},
-			_ => { /* Everything's Alright! */ }
-		}
+	/// Returns the dimensions of the text, once laid out with the given style.
+	pub fn measure_text(&mut self, text: &str, style: &TextStyle) -> Option<(f64, f64)> {
+		let atlas = tryo!(self.assets.glyphs(&self.renderer, &style.font, style.size, text).ok());
+		let mut layout = TextLayout::new();
+
+		layout.compute(&atlas.borrow(), text, style);
+		Some(layout.size())
 	}
-}
 
-pub trait RendererExtensions {
-	fn fill_circle(self: &mut Self, x: f64, y:f64, radius: f64) -> Result<(), String>;
-}
+	/// Plays a sound effect once, through the audio buses and within the
+	/// limits of the sound.
+	pub fn play_sound(&mut self, sound: &Handle<Sound>) {
+		self.audio.play(sound);
+	}
 
-impl <'window> RendererExtensions for Renderer<'window> {
-	fn fill_circle(self: &mut Self, x: f64, y:f64, radius: f64) -> Result<(), String> {
-		use sdl2::rect::Point as SdlPoint;
-
-		let (mut e, mut a, mut b) = (0.0, radius, 0.0);
-
-		while a >= b {
-			let points = [
-				SdlPoint::new((x + a) as i32, (y + b) as i32),
-				SdlPoint::new((x + b) as i32, (y + a) as i32),
-				SdlPoint::new((x - b) as i32, (y + a) as i32),
-				SdlPoint::new((x - a) as i32, (y + b) as i32),
-				SdlPoint::new((x - a) as i32, (y - b) as i32),
-				SdlPoint::new((x - b) as i32, (y - a) as i32),
-				SdlPoint::new((x + b) as i32, (y - a) as i32),
-				SdlPoint::new((x + a) as i32, (y - b) as i32),
-			];
-			try!(self.draw_points(&points));
-
-			b += 1.0;
-			e += 1.0 + 2.0 * b;
-			if 2.0 * (e - a) + 1.0 > 0.0 {
-				a -= 1.0;
-				e += 1.0 - 2.0 * a;
-			}
-		}
-		Ok(())
+	/// Plays a sound effect once, panned towards where it happens and fading
+	/// out when it happens off-screen.
+	pub fn play_sound_at(&mut self, sound: &Handle<Sound>, position: (f64, f64)) {
+		self.audio.play_at(sound, position);
 	}
 }
 
-
 pub trait View {
 	/// Called on every frame to take care of the logic of the program. From
 	/// user inputs and the instance's internal state, determine whether to
@@ -176,6 +155,10 @@ pub trait View {
 }
 
 
+/// Where the audio settings are saved, relative to the working directory.
+const AUDIO_SETTINGS: &'static str = "settings/audio.cfg";
+
+
 /// Create a window with name `title`, initialize the underlying libraries and
 /// start the game with the `View` returned by `init()`.
 pub fn spawn<F>(title: &str, size: (u32, u32), init: F) where F: Fn(&mut Phi) -> Box<View> {
@@ -184,12 +167,12 @@ pub fn spawn<F>(title: &str, size: (u32, u32), init: F) where F: Fn(&mut Phi) ->
 	let video = sdl_context.video().unwrap();
 	let mut timer = sdl_context.timer().unwrap();
 	let _image_context = ::sdl2::image::init(::sdl2::image::INIT_PNG).unwrap();
-	let _ttf_context = ::sdl2::ttf::init().unwrap();
+	let ttf_context = ::sdl2::ttf::init().unwrap();
 
 	// Initialize audio plugin
 	//? We will stick to the Ogg format throughout this article. However, you
 	//? can easily require other ones.
-	let _mixer_context = ::sdl2::mixer::init(::sdl2::mixer::INIT_OGG).unwrap();
+	let _mixer_context = ::sdl2::mixer::init(::sdl2::mixer::INIT_OGG);
 	//? We configure our audio context so that:
 	//?   * The frequency is 44100;
 	//?   * Use signed 16 bits samples, in little-endian byte order;
@@ -197,7 +180,17 @@ pub fn spawn<F>(title: &str, size: (u32, u32), init: F) where F: Fn(&mut Phi) ->
 	//?   * Samples are 1024 bytes in size.
 	//? You don't really need to understand what all of this means. I myself just
 	//? copy-pasted this from andelf's demo. ;-)
-	::sdl2::mixer::open_audio(44100, ::sdl2::mixer::AUDIO_S16LSB, 2, 1024).unwrap();
+	let opened = _mixer_context.as_ref().map_err(|e| e.clone())
+		.and_then(|_| ::sdl2::mixer::open_audio(44100, ::sdl2::mixer::AUDIO_S16LSB, 2, 1024));
+
+	//? Machines without an audio device still run the game, silently.
+	let audio: Box<AudioBackend> = match opened {
+		Ok(()) => Box::new(SdlMixer),
+		Err(e) => {
+			println!("Can't open audio, the game will be silent: {}", e);
+			Box::new(NullAudio::new())
+		}
+	};
 
 	// Create the window
 	let window = video.window(title, size.0, size.1)
@@ -209,11 +202,18 @@ pub fn spawn<F>(title: &str, size: (u32, u32), init: F) where F: Fn(&mut Phi) ->
 	// Create the context
 	let mut context = Phi::new(
 		Events::new(sdl_context.event_pump().unwrap()), 
-		_ttf_context,
+		&ttf_context,
 		window.renderer()
 		.accelerated()
-		.build().unwrap());
+		.build().unwrap(),
+		audio);
+
+	context.audio.persist_to(AUDIO_SETTINGS);
 
+	// Watch the assets, so that they can be tweaked while the game is running.
+	if ::DEBUG {
+		context.assets.watch("assets", 1.0);
+	}
 	// Create the default view
 	let mut current_view = init(&mut context);
 
@@ -249,11 +249,25 @@ pub fn spawn<F>(title: &str, size: (u32, u32), init: F) where F: Fn(&mut Phi) ->
 		}
 		// Logic & rendering
 		context.events.pump(&mut context.renderer);
+		context.assets.reload_changed(&context.renderer, elapsed);
+		context.effects.update(elapsed);
+		context.audio.update(elapsed);
+		context.music.update(&mut context.assets, elapsed);
+		//? There's no camera: what's heard is what's in the window.
+		let (w, h) = context.output_size();
+		context.audio.set_listener(Rectangle { x: 0.0, y: 0.0, w: w, h: h });
+
+		if context.events.now.key_m == Some(true) {
+			context.audio.toggle_mute();
+		}
 
 		match current_view.update(&mut context, elapsed) {
 			ViewAction::Render(view) => {
 				current_view = view;
+				context.effects.begin(&mut context.renderer);
 				current_view.render(&mut context);
+				context.queue.flush(&mut context.renderer);
+				context.effects.end(&mut context.renderer);
 				context.renderer.present();
 			},
 			ViewAction::Quit => break,
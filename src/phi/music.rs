@@ -0,0 +1,371 @@
+extern crate rand;
+
+
+use phi::assets::{Assets, Handle};
+use phi::backend::Track;
+
+use sdl2::mixer::Music;
+
+use std::collections::HashMap;
+
+
+use self::rand::{Rng, XorShiftRng};
+
+
+/// The fade used when a view asks for other music, in seconds.
+pub const DEFAULT_FADE: f64 = 1.5;
+
+
+/// Tracks played one after the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Playlist {
+	tracks: Vec<String>,
+	shuffle: bool,
+	looping: bool,
+	/// Whether tracks resume where they were left, when played again.
+	resume: bool,
+}
+
+impl Playlist {
+	/// Creates a playlist of the tracks located at the given paths, played in
+	/// order, once.
+	pub fn new(tracks: &[&str]) -> Playlist {
+		Playlist {
+			tracks: tracks.iter().map(|track| track.to_string()).collect(),
+			shuffle: false,
+			looping: false,
+			resume: false,
+		}
+	}
+
+	/// Creates a playlist which loops over a single track, resuming it where
+	/// it was left.
+	pub fn single(track: &str) -> Playlist {
+		Playlist::new(&[track]).looping(true).resume(true)
+	}
+
+	/// Whether the tracks are played in a random order, which changes every
+	/// time the playlist starts over.
+	pub fn shuffle(mut self, shuffle: bool) -> Playlist {
+		self.shuffle = shuffle;
+		self
+	}
+
+	/// Whether the playlist starts over once done.
+	pub fn looping(mut self, looping: bool) -> Playlist {
+		self.looping = looping;
+		self
+	}
+
+	pub fn resume(mut self, resume: bool) -> Playlist {
+		self.resume = resume;
+		self
+	}
+
+	pub fn tracks(&self) -> &[String] {
+		&self.tracks
+	}
+}
+
+
+enum State {
+	Idle,
+	/// Playing the track at `index` in `order`, started at the given time,
+	/// from the given position in the track.
+	Playing { index: usize, started: f64, from: f64 },
+	/// Waiting for the current track to fade out, until the given time.
+	FadingOut { until: f64 },
+}
+
+
+/// Plays the music views ask for. Switching to other music fades the current
+/// track out, then fades the new one in.
+///
+/// SDL_mixer streams a single piece of music at a time, thus tracks can't
+/// overlap: "crossfading" fades out, then in.
+pub struct MusicManager {
+	playlist: Option<Playlist>,
+	order: Vec<usize>,
+	state: State,
+	/// What to play once the current track faded out, along with the fade.
+	pending: Option<(Option<Playlist>, f64)>,
+
+	track: Option<Handle<Track>>,
+	/// Where every track was left, in seconds.
+	positions: HashMap<String, f64>,
+
+	time: f64,
+	rng: XorShiftRng,
+}
+
+impl MusicManager {
+	pub fn new() -> MusicManager {
+		MusicManager {
+			playlist: None,
+			order: vec![],
+			state: State::Idle,
+			pending: None,
+
+			track: None,
+			positions: HashMap::new(),
+
+			time: 0.0,
+			rng: rand::weak_rng(),
+		}
+	}
+
+
+	/// Plays the playlist, fading the current music out then this one in over
+	/// `fade` seconds each. Does nothing if it's already playing.
+	pub fn play(&mut self, assets: &mut Assets, playlist: Playlist, fade: f64) {
+		if self.target() == Some(&playlist) {
+			return;
+		}
+		self.switch(assets, Some(playlist), fade);
+	}
+
+	/// Loops over a single track. Shorthand for `play(Playlist::single(..))`.
+	pub fn play_track(&mut self, assets: &mut Assets, path: &str, fade: f64) {
+		self.play(assets, Playlist::single(path), fade);
+	}
+
+	/// Fades the music out over `fade` seconds.
+	pub fn stop(&mut self, assets: &mut Assets, fade: f64) {
+		if self.target().is_none() {
+			return;
+		}
+		self.switch(assets, None, fade);
+	}
+
+	/// The path of the track being played, if any.
+	pub fn current_track(&self) -> Option<&str> {
+		match self.state {
+			State::Playing { index, .. } => self.playlist.as_ref().map(|playlist| &*playlist.tracks[self.order[index]]),
+			_ => None
+		}
+	}
+
+
+	/// Moves on to the next track once the current one is done, and to the
+	/// pending music once the current one faded out.
+	pub fn update(&mut self, assets: &mut Assets, elapsed: f64) {
+		self.time += elapsed;
+
+		match self.state {
+			State::FadingOut { until } => {
+				//? Silent tracks never stop on their own: they wait for the
+				//? whole fade instead.
+				if self.time >= until || (!self.is_silent() && !Music::is_playing()) {
+					self.state = State::Idle;
+
+					if let Some((playlist, fade)) = self.pending.take() {
+						self.start(assets, playlist, fade);
+					}
+				}
+			},
+			State::Playing { index, .. } => {
+				//? Looping single tracks are played by SDL_mixer forever, but
+				//? every other track is played once. Silent tracks, loaded
+				//? without a mixer, never end.
+				if !Music::is_playing() && !self.is_silent() {
+					self.next(assets, index + 1);
+				}
+			},
+			State::Idle => {}
+		}
+	}
+
+
+	fn switch(&mut self, assets: &mut Assets, playlist: Option<Playlist>, fade: f64) {
+		match self.state {
+			State::Playing { .. } => {
+				self.remember_position();
+				Music::fade_out(to_ms(fade)).unwrap_or(());
+
+				self.state = State::FadingOut { until: self.time + fade };
+				self.pending = Some((playlist, fade));
+			},
+			State::FadingOut { .. } => {
+				self.pending = Some((playlist, fade));
+			},
+			State::Idle => self.start(assets, playlist, fade),
+		}
+	}
+
+	fn is_silent(&self) -> bool {
+		self.track.as_ref().map_or(false, |track| track.borrow().is_silent())
+	}
+
+	/// The music which is, or will be once the current one faded out, played.
+	fn target(&self) -> Option<&Playlist> {
+		match self.pending {
+			Some((ref playlist, _)) => playlist.as_ref(),
+			None => self.playlist.as_ref(),
+		}
+	}
+
+	fn start(&mut self, assets: &mut Assets, playlist: Option<Playlist>, fade: f64) {
+		self.playlist = playlist;
+		self.track = None;
+
+		let count = self.playlist.as_ref().map_or(0, |playlist| playlist.tracks.len());
+
+		self.order = (0..count).collect();
+		self.shuffle();
+		self.play_index(assets, 0, fade);
+	}
+
+	fn next(&mut self, assets: &mut Assets, index: usize) {
+		let looping = self.playlist.as_ref().map_or(false, |playlist| playlist.looping);
+
+		if index < self.order.len() {
+			self.play_index(assets, index, 0.0);
+		} else if looping && !self.order.is_empty() {
+			self.shuffle();
+			self.play_index(assets, 0, 0.0);
+		} else {
+			self.state = State::Idle;
+		}
+	}
+
+	fn shuffle(&mut self) {
+		if self.playlist.as_ref().map_or(false, |playlist| playlist.shuffle) {
+			self.rng.shuffle(&mut self.order);
+		}
+	}
+
+	/// Starts the track at `index` in the play order, fading it in. Tracks
+	/// which can't be loaded are skipped, until one of them plays or every
+	/// one of them failed.
+	fn play_index(&mut self, assets: &mut Assets, index: usize, fade: f64) {
+		let count = self.order.len();
+		let looping = self.playlist.as_ref().map_or(false, |playlist| playlist.looping);
+
+		for skipped in 0..count {
+			if index + skipped >= count && !looping {
+				break;
+			}
+			if self.try_play(assets, (index + skipped) % count, fade) {
+				return;
+			}
+		}
+		self.state = State::Idle;
+	}
+
+	/// Starts the track at `index` in the play order, unless it can't be
+	/// loaded.
+	fn try_play(&mut self, assets: &mut Assets, index: usize, fade: f64) -> bool {
+		let (path, loops, resume) = match self.playlist {
+			Some(ref playlist) => {
+				let single = playlist.looping && playlist.tracks.len() == 1;
+				(playlist.tracks[self.order[index]].clone(), if single { -1 } else { 0 }, playlist.resume)
+			},
+			None => return false
+		};
+		let music = match assets.music(&path) {
+			Ok(music) => music,
+			Err(e) => {
+				println!("Can't load music {}: {}", path, e);
+				return false;
+			}
+		};
+		let from = if resume { self.positions.get(&path).cloned().unwrap_or(0.0) } else { 0.0 };
+		let result = if from > 0.0 {
+			music.borrow().fade_in_from_pos(loops, to_ms(fade), from)
+			.or_else(|_| music.borrow().fade_in(loops, to_ms(fade)))
+		} else {
+			music.borrow().fade_in(loops, to_ms(fade))
+		};
+		if let Err(e) = result {
+			println!("Can't play music {}: {}", path, e);
+		}
+		self.track = Some(music);
+		self.state = State::Playing { index: index, started: self.time, from: from };
+		true
+	}
+
+	fn remember_position(&mut self) {
+		if let State::Playing { index, started, from } = self.state {
+			if let Some(ref playlist) = self.playlist {
+				let path = playlist.tracks[self.order[index]].clone();
+				self.positions.insert(path, from + self.time - started);
+			}
+		}
+	}
+}
+
+fn to_ms(seconds: f64) -> i32 {
+	(seconds.max(0.0) * 1000.0).round() as i32
+}
+
+
+#[cfg(test)]
+mod tests {
+	use phi::assets::Assets;
+	use super::{MusicManager, Playlist, State};
+
+
+	const FIRST: &'static str = "assets/sounds/mdk_phoenix_orchestral.ogg";
+	const SECOND: &'static str = "assets/sounds/explosion.ogg";
+
+	//? Without a mixer, tracks load silent, and wait for their whole fade.
+
+	#[test]
+	fn switching_waits_for_the_fade() {
+		let ttf_context = ::sdl2::ttf::init().unwrap();
+		let mut assets = Assets::new(&ttf_context);
+		let mut music = MusicManager::new();
+
+		music.play_track(&mut assets, FIRST, 1.0);
+		assert_eq!(music.current_track(), Some(FIRST));
+
+		music.play_track(&mut assets, SECOND, 1.0);
+		assert_eq!(music.current_track(), None);
+		assert_eq!(music.target(), Some(&Playlist::single(SECOND)));
+
+		//? Asking for the pending music again doesn't restart the fade.
+		music.update(&mut assets, 0.6);
+		music.play_track(&mut assets, SECOND, 1.0);
+		music.update(&mut assets, 0.6);
+		assert_eq!(music.current_track(), Some(SECOND));
+
+		music.stop(&mut assets, 1.0);
+		assert_eq!(music.target(), None);
+		music.update(&mut assets, 1.0);
+		assert_eq!(music.current_track(), None);
+	}
+
+	#[test]
+	fn tracks_resume_where_they_were_left() {
+		let ttf_context = ::sdl2::ttf::init().unwrap();
+		let mut assets = Assets::new(&ttf_context);
+		let mut music = MusicManager::new();
+
+		music.play_track(&mut assets, FIRST, 0.0);
+		music.update(&mut assets, 2.0);
+		music.play_track(&mut assets, SECOND, 0.5);
+		music.update(&mut assets, 0.5);
+		music.play_track(&mut assets, FIRST, 0.5);
+		music.update(&mut assets, 0.5);
+
+		match music.state {
+			State::Playing { from, .. } => assert_eq!(from, 2.0),
+			_ => panic!("expected the first track to play")
+		}
+		assert_eq!(music.current_track(), Some(FIRST));
+	}
+
+	#[test]
+	fn missing_tracks_are_skipped() {
+		let ttf_context = ::sdl2::ttf::init().unwrap();
+		let mut assets = Assets::new(&ttf_context);
+		let mut music = MusicManager::new();
+
+		music.play(&mut assets, Playlist::new(&["assets/sounds/missing.ogg", SECOND]), 0.0);
+		assert_eq!(music.current_track(), Some(SECOND));
+
+		music.play(&mut assets, Playlist::new(&["assets/sounds/missing.ogg"]).looping(true), 0.0);
+		music.update(&mut assets, 0.0);
+		assert_eq!(music.current_track(), None);
+	}
+}
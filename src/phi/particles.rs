@@ -0,0 +1,366 @@
+extern crate rand;
+
+
+use phi::Phi;
+use phi::data::Rectangle;
+use phi::gfx::{Blend, DrawParams, Sprite};
+use phi::tween::Lerp;
+
+use sdl2::pixels::Color;
+
+use std::f64::consts::PI;
+
+
+use self::rand::{Rng, XorShiftRng};
+
+
+/// A value which changes over the life of a particle. It goes through its
+/// keys, given as `(t, value)` pairs where `t` is the fraction of the life
+/// in [0, 1], and is interpolated linearly in between.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T> {
+	keys: Vec<(f64, T)>,
+}
+
+impl <T: Lerp + Clone> Curve<T> {
+	/// Creates a curve going through the given keys. Panics if there's none.
+	pub fn new(keys: Vec<(f64, T)>) -> Curve<T> {
+		if keys.is_empty() {
+			panic!("Passed no keys to Curve::new");
+		}
+		let mut keys = keys;
+
+		keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+		Curve { keys: keys }
+	}
+
+	pub fn constant(value: T) -> Curve<T> {
+		Curve::new(vec![(0.0, value)])
+	}
+
+	/// Creates a curve going from `start` at birth to `end` at death.
+	pub fn between(start: T, end: T) -> Curve<T> {
+		Curve::new(vec![(0.0, start), (1.0, end)])
+	}
+
+	pub fn sample(&self, t: f64) -> T {
+		let first = &self.keys[0];
+		let last = &self.keys[self.keys.len() - 1];
+
+		if t <= first.0 {
+			return first.1.clone();
+		}
+		if t >= last.0 {
+			return last.1.clone();
+		}
+		let next = self.keys.iter().position(|key| key.0 > t).unwrap();
+		let (from, to) = (&self.keys[next - 1], &self.keys[next]);
+
+		from.1.lerp(&to.1, (t - from.0) / (to.0 - from.0))
+	}
+}
+
+
+/// How particles are drawn.
+#[derive(Clone)]
+pub enum ParticleShape {
+	/// A filled square, which side is the particle's size.
+	Point,
+	/// The sprite, scaled so that its largest side is the particle's size,
+	/// and tinted with the particle's color.
+	Sprite(Sprite),
+}
+
+
+/// Describes the particles an emitter spawns, and how they evolve.
+///
+///     EmitterConfig { rate: 60.0, spread: 30.0, ..EmitterConfig::default() }
+#[derive(Clone)]
+pub struct EmitterConfig {
+	/// The number of particles spawned every second while the emitter is
+	/// active. Bursts are triggered separately.
+	pub rate: f64,
+	/// The most particles the emitter holds at once. New particles are
+	/// dropped while the pool is full.
+	pub max_particles: usize,
+
+	/// The range the life of a particle is picked from, in seconds.
+	pub lifetime: (f64, f64),
+	/// The range the initial speed of a particle is picked from, in pixels
+	/// per second.
+	pub speed: (f64, f64),
+	/// The direction particles are shot in, in degrees, clockwise from the
+	/// x axis.
+	pub direction: f64,
+	/// The angle of the cone particles are shot within, in degrees, centered
+	/// on the `direction`.
+	pub spread: f64,
+
+	/// The acceleration applied to the particles, in pixels per second².
+	pub gravity: (f64, f64),
+	/// The fraction of their velocity particles lose every second.
+	pub drag: f64,
+
+	pub size: Curve<f64>,
+	pub color: Curve<Color>,
+	pub alpha: Curve<f64>,
+
+	pub shape: ParticleShape,
+	pub blend: Blend,
+	/// The render queue layer particles are submitted to.
+	pub layer: i32,
+}
+
+impl Default for EmitterConfig {
+	fn default() -> EmitterConfig {
+		EmitterConfig {
+			rate: 0.0,
+			max_particles: 256,
+
+			lifetime: (1.0, 1.0),
+			speed: (50.0, 50.0),
+			direction: 0.0,
+			spread: 360.0,
+
+			gravity: (0.0, 0.0),
+			drag: 0.0,
+
+			size: Curve::constant(2.0),
+			color: Curve::constant(Color::RGB(255, 255, 255)),
+			alpha: Curve::between(1.0, 0.0),
+
+			shape: ParticleShape::Point,
+			blend: Blend::Alpha,
+			layer: 0,
+		}
+	}
+}
+
+
+#[derive(Clone, Copy, Debug)]
+struct Particle {
+	position: (f64, f64),
+	velocity: (f64, f64),
+	age: f64,
+	lifetime: f64,
+}
+
+
+/// Spawns and simulates particles. Particles live in a pool allocated upfront,
+/// and move on their own once spawned, so that moving the emitter around only
+/// affects the next ones.
+pub struct Emitter {
+	config: EmitterConfig,
+	position: (f64, f64),
+	active: bool,
+
+	/// Fractional particles left over from the previous updates, so that low
+	/// rates still spawn particles at high frame rates.
+	pending: f64,
+	particles: Vec<Particle>,
+	rng: XorShiftRng,
+}
+
+impl Emitter {
+	/// Creates an active emitter at the origin.
+	pub fn new(config: EmitterConfig) -> Emitter {
+		Emitter {
+			particles: Vec::with_capacity(config.max_particles),
+			config: config,
+			position: (0.0, 0.0),
+			active: true,
+
+			pending: 0.0,
+			rng: rand::weak_rng(),
+		}
+	}
+
+
+	pub fn config(&self) -> &EmitterConfig {
+		&self.config
+	}
+
+	pub fn config_mut(&mut self) -> &mut EmitterConfig {
+		&mut self.config
+	}
+
+	pub fn position(&self) -> (f64, f64) {
+		self.position
+	}
+
+	pub fn set_position(&mut self, position: (f64, f64)) {
+		self.position = position;
+	}
+
+	pub fn is_active(&self) -> bool {
+		self.active
+	}
+
+	/// Whether the emitter spawns particles at its `rate`. Living particles
+	/// keep going either way.
+	pub fn set_active(&mut self, active: bool) {
+		if self.active != active {
+			self.active = active;
+			self.pending = 0.0;
+		}
+	}
+
+	/// The number of living particles.
+	pub fn len(&self) -> usize {
+		self.particles.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.particles.is_empty()
+	}
+
+
+	/// Spawns `count` particles at once, at the emitter's position.
+	pub fn burst(&mut self, count: usize) {
+		let position = self.position;
+
+		self.burst_at(position, count);
+	}
+
+	/// Spawns `count` particles at once, at the given position.
+	pub fn burst_at(&mut self, position: (f64, f64), count: usize) {
+		for _ in 0..count {
+			self.spawn(position);
+		}
+	}
+
+	fn spawn(&mut self, position: (f64, f64)) {
+		if self.particles.len() >= self.config.max_particles {
+			return;
+		}
+		let angle = (self.config.direction + self.config.spread * (self.rng.next_f64() - 0.5)) * PI / 180.0;
+		let speed = pick(&mut self.rng, self.config.speed);
+
+		self.particles.push(Particle {
+			position: position,
+			velocity: (angle.cos() * speed, angle.sin() * speed),
+			age: 0.0,
+			lifetime: pick(&mut self.rng, self.config.lifetime).max(::std::f64::EPSILON),
+		});
+	}
+
+
+	pub fn update(&mut self, dt: f64) {
+		let damping = (1.0 - self.config.drag * dt).max(0.0);
+		let gravity = self.config.gravity;
+		let mut index = 0;
+
+		//? Dead particles are swapped with the last one, so that the pool
+		//? stays packed without moving the others around.
+		while index < self.particles.len() {
+			let alive = {
+				let particle = &mut self.particles[index];
+
+				particle.age += dt;
+				particle.velocity.0 = (particle.velocity.0 + gravity.0 * dt) * damping;
+				particle.velocity.1 = (particle.velocity.1 + gravity.1 * dt) * damping;
+				particle.position.0 += particle.velocity.0 * dt;
+				particle.position.1 += particle.velocity.1 * dt;
+
+				particle.age < particle.lifetime
+			};
+
+			if alive {
+				index += 1;
+			} else {
+				self.particles.swap_remove(index);
+			}
+		}
+		if self.active {
+			self.pending += self.config.rate * dt;
+
+			let position = self.position;
+
+			while self.pending >= 1.0 {
+				self.spawn(position);
+				self.pending -= 1.0;
+			}
+		}
+	}
+
+	/// Submits the living particles to the render queue.
+	pub fn render(&self, phi: &mut Phi) {
+		let config = &self.config;
+
+		for particle in &self.particles {
+			let t = particle.age / particle.lifetime;
+			let size = config.size.sample(t);
+			let color = config.color.sample(t);
+			let alpha = config.alpha.sample(t).max(0.0).min(1.0);
+
+			match config.shape {
+				ParticleShape::Point => {
+					let (r, g, b) = color.rgb();
+					let rect = Rectangle::with_size(size, size).center_at(particle.position);
+
+					//? Points are drawn as plain rectangles, which don't blend
+					//? with the background, so we fade them out by darkening
+					//? their color instead.
+					phi.queue.fill_rect(config.layer, 0.0, rect, Color::RGB(
+						(r as f64 * alpha) as u8, (g as f64 * alpha) as u8, (b as f64 * alpha) as u8));
+				},
+				ParticleShape::Sprite(ref sprite) => {
+					let (w, h) = sprite.size();
+					let scale = size / w.max(h);
+					let rect = Rectangle::with_size(w * scale, h * scale).center_at(particle.position);
+
+					phi.queue.sprite_ex(config.layer, 0.0, sprite, rect, &DrawParams {
+						color: color,
+						alpha: alpha,
+						blend: config.blend,
+						..DrawParams::default()
+					});
+				},
+			}
+		}
+	}
+}
+
+/// Picks a value in the given range, at random.
+fn pick(rng: &mut XorShiftRng, range: (f64, f64)) -> f64 {
+	range.0 + (range.1 - range.0) * rng.next_f64()
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+
+	#[test]
+	fn curves_interpolate_between_keys() {
+		let curve = Curve::new(vec![(1.0, 0.0), (0.0, 10.0), (0.5, 20.0)]);
+
+		assert_eq!(curve.sample(-1.0), 10.0);
+		assert_eq!(curve.sample(0.25), 15.0);
+		assert_eq!(curve.sample(0.75), 10.0);
+		assert_eq!(curve.sample(2.0), 0.0);
+	}
+
+	#[test]
+	fn emitters_spawn_at_their_rate_and_recycle_dead_particles() {
+		let mut emitter = Emitter::new(EmitterConfig {
+			rate: 10.0,
+			max_particles: 8,
+			lifetime: (0.55, 0.55),
+			..EmitterConfig::default()
+		});
+
+		for _ in 0..5 {
+			emitter.update(0.1);
+		}
+		assert_eq!(emitter.len(), 5);
+
+		emitter.burst(10);
+		assert_eq!(emitter.len(), 8);
+
+		emitter.set_active(false);
+		emitter.update(1.0);
+		assert!(emitter.is_empty());
+	}
+}
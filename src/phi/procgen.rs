@@ -0,0 +1,273 @@
+extern crate rand;
+
+
+use phi::color::Gradient;
+use phi::gfx::Sprite;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::Renderer;
+use sdl2::surface::Surface;
+
+
+use self::rand::{Rng, SeedableRng, XorShiftRng};
+
+
+/// Creates a random number generator which always yields the same sequence
+/// for the same seed.
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+	//? XorShift can't start from a state made of zeros only, hence the
+	//? constants mixed into the seed.
+	XorShiftRng::from_seed([
+		(seed as u32) ^ 0x193a6754,
+		((seed >> 32) as u32) ^ 0xa8a7d469,
+		0x97830e05,
+		0x113ba7bb])
+}
+
+/// Generates an image, which color is given for every pixel.
+pub fn pixel_surface<F>(w: u32, h: u32, pixel: F) -> Result<Surface<'static>, String> where F: Fn(u32, u32) -> Color {
+	let mut surface = try!(Surface::new(w, h, PixelFormatEnum::ARGB8888));
+	let pitch = surface.pitch() as usize;
+
+	surface.with_lock_mut(|pixels| {
+		for y in 0..h {
+			for x in 0..w {
+				let (r, g, b, a) = pixel(x, y).rgba();
+				let offset = y as usize * pitch + x as usize * 4;
+
+				//? ARGB8888 pixels are native-endian 32-bit words.
+				let word = (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32;
+				pixels[offset..offset + 4].copy_from_slice(&word.to_ne_bytes());
+			}
+		}
+	});
+	Ok(surface)
+}
+
+
+/// Describes a layer of stars, scattered over a transparent image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StarLayerConfig {
+	/// The average number of stars per 10 000 pixels.
+	pub density: f64,
+	/// The colors of the stars, picked at random along the gradient.
+	pub colors: Gradient,
+	/// The range of the opacity of the stars.
+	pub brightness: (f64, f64),
+	/// The fraction of the stars which are large, glowing ones.
+	pub large: f64,
+}
+
+impl Default for StarLayerConfig {
+	fn default() -> StarLayerConfig {
+		StarLayerConfig {
+			density: 8.0,
+			colors: Gradient::new(vec![
+				(0.0, Color::RGB(170, 190, 255)),
+				(0.6, Color::RGB(255, 255, 255)),
+				(1.0, Color::RGB(255, 220, 170))]),
+			brightness: (0.3, 1.0),
+			large: 0.05,
+		}
+	}
+}
+
+/// Generates a transparent image of the given size, scattered with stars. The
+/// image tiles seamlessly: stars crossing an edge show up on the other side.
+pub fn star_layer(seed: u64, w: u32, h: u32, config: &StarLayerConfig) -> Result<Surface<'static>, String> {
+	let mut rng = seeded_rng(seed);
+	let count = (w as f64 * h as f64 * config.density / 10000.0).round() as usize;
+	let mut alpha = vec![0.0; (w * h) as usize];
+	let mut tint = vec![Color::RGBA(0, 0, 0, 0); (w * h) as usize];
+
+	for _ in 0..count {
+		let (x, y) = (rng.gen_range(0, w) as i64, rng.gen_range(0, h) as i64);
+		let color = config.colors.sample(rng.gen::<f64>());
+		let brightness = random_in(&mut rng, config.brightness);
+		//? Large stars are a bright pixel surrounded by a faint cross.
+		let radius: i64 = if rng.gen::<f64>() < config.large { 1 } else { 0 };
+
+		for dy in -radius..radius + 1 {
+			for dx in -radius..radius + 1 {
+				let falloff = match dx.abs() + dy.abs() { 0 => 1.0, 1 => 0.4, _ => 0.1 };
+				let index = (wrap(y + dy, h) * w + wrap(x + dx, w)) as usize;
+				let value = brightness * falloff;
+
+				if value > alpha[index] {
+					alpha[index] = value;
+					tint[index] = color;
+				}
+			}
+		}
+	}
+	pixel_surface(w, h, |x, y| {
+		let index = (y * w + x) as usize;
+		let (r, g, b) = tint[index].rgb();
+
+		Color::RGBA(r, g, b, (alpha[index] * 255.0).round() as u8)
+	})
+}
+
+
+/// Describes clouds of colored gas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NebulaConfig {
+	/// The number of noise cells along the width of the image, that is, how
+	/// many clouds it fits.
+	pub scale: u32,
+	/// The number of layers of finer noise added over the first one.
+	pub octaves: u32,
+	/// How much the finer layers contribute, relative to the previous one.
+	pub persistence: f64,
+	/// The noise value below which the image is transparent, in [0, 1].
+	pub threshold: f64,
+	/// The colors of the clouds, from their edges to their cores.
+	pub colors: Gradient,
+	/// The opacity of the cores.
+	pub opacity: f64,
+}
+
+impl Default for NebulaConfig {
+	fn default() -> NebulaConfig {
+		NebulaConfig {
+			scale: 4,
+			octaves: 5,
+			persistence: 0.5,
+			threshold: 0.45,
+			colors: Gradient::new(vec![
+				(0.0, Color::RGB(40, 10, 80)),
+				(0.5, Color::RGB(150, 40, 120)),
+				(1.0, Color::RGB(255, 150, 170))]),
+			opacity: 0.6,
+		}
+	}
+}
+
+/// Generates a transparent image of the given size, filled with clouds of
+/// fractal noise. The image tiles seamlessly.
+pub fn nebula(seed: u64, w: u32, h: u32, config: &NebulaConfig) -> Result<Surface<'static>, String> {
+	let seed = seeded_rng(seed).next_u32();
+	//? Cells are square, hence fewer of them along the height, as long as
+	//? there's a whole number of them, so that the noise tiles.
+	let cells = (config.scale.max(1), ((config.scale.max(1) as f64 * h as f64 / w as f64).round() as u32).max(1));
+	let norm = (0..config.octaves.max(1)).fold(0.0, |sum, octave| sum + config.persistence.powi(octave as i32));
+
+	pixel_surface(w, h, |x, y| {
+		let (u, v) = (x as f64 / w as f64, y as f64 / h as f64);
+		let mut value = 0.0;
+		let mut amplitude = 1.0;
+
+		for octave in 0..config.octaves.max(1) {
+			let frequency = 1 << octave;
+			let (cells_x, cells_y) = (cells.0 * frequency, cells.1 * frequency);
+
+			value += amplitude * value_noise(seed.wrapping_add(octave), u * cells_x as f64, v * cells_y as f64, cells_x, cells_y);
+			amplitude *= config.persistence;
+		}
+		let t = ((value / norm - config.threshold) / (1.0 - config.threshold)).max(0.0).min(1.0);
+		let (r, g, b) = config.colors.sample(t).rgb();
+
+		Color::RGBA(r, g, b, (t * config.opacity * 255.0).round() as u8)
+	})
+}
+
+
+/// Generates a texture from the surface.
+pub fn to_sprite(renderer: &Renderer, surface: Result<Surface<'static>, String>) -> Result<Sprite, String> {
+	surface.and_then(|surface| Sprite::from_surface(renderer, &surface).ok_or("Can't create texture!".to_string()))
+}
+
+
+/// Smoothly interpolated random values on a lattice, which wraps around every
+/// `period_x` cells horizontally and `period_y` cells vertically.
+fn value_noise(seed: u32, x: f64, y: f64, period_x: u32, period_y: u32) -> f64 {
+	let (cell_x, cell_y) = (x.floor(), y.floor());
+	let (fx, fy) = (smooth(x - cell_x), smooth(y - cell_y));
+	let (x0, y0) = (cell_x as i64, cell_y as i64);
+
+	let corner = |dx: i64, dy: i64| lattice(seed, wrap(x0 + dx, period_x), wrap(y0 + dy, period_y));
+	let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * fx;
+	let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * fx;
+
+	top + (bottom - top) * fy
+}
+
+/// A pseudo-random value in [0, 1] for every lattice point.
+fn lattice(seed: u32, x: u32, y: u32) -> f64 {
+	let mut hash = seed ^ x.wrapping_mul(0x27d4eb2d) ^ y.wrapping_mul(0x165667b1);
+
+	hash = (hash ^ (hash >> 15)).wrapping_mul(0x85ebca6b);
+	hash = (hash ^ (hash >> 13)).wrapping_mul(0xc2b2ae35);
+	hash ^= hash >> 16;
+
+	hash as f64 / ::std::u32::MAX as f64
+}
+
+fn smooth(t: f64) -> f64 {
+	t * t * (3.0 - 2.0 * t)
+}
+
+fn wrap(value: i64, period: u32) -> u32 {
+	let period = period as i64;
+	(((value % period) + period) % period) as u32
+}
+
+fn random_in(rng: &mut XorShiftRng, range: (f64, f64)) -> f64 {
+	if range.1 > range.0 { rng.gen_range(range.0, range.1) } else { range.0 }
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{seeded_rng, star_layer, value_noise, StarLayerConfig};
+	use super::rand::Rng;
+
+
+	#[test]
+	fn generation_is_seeded() {
+		assert_eq!(seeded_rng(42).next_u32(), seeded_rng(42).next_u32());
+		assert!(seeded_rng(42).next_u32() != seeded_rng(43).next_u32());
+	}
+
+	#[test]
+	fn noise_tiles() {
+		for &(x, y) in &[(0.0, 0.0), (0.25, 2.75), (3.5, 1.125)] {
+			let value = value_noise(7, x, y, 4, 3);
+
+			assert!(value >= 0.0 && value <= 1.0);
+			assert_eq!(value, value_noise(7, x + 4.0, y, 4, 3));
+			assert_eq!(value, value_noise(7, x, y + 3.0, 4, 3));
+		}
+	}
+
+	#[test]
+	fn star_layer_tiles() {
+		//? A single large star in a 3x3 image: wherever it lands, its cross
+		//? and faint corners cover every pixel only if they wrap around.
+		let config = StarLayerConfig {
+			density: 10000.0 / 9.0,
+			brightness: (1.0, 1.0),
+			large: 1.0,
+			..StarLayerConfig::default()
+		};
+		for seed in 0..16 {
+			let surface = star_layer(seed, 3, 3, &config).unwrap();
+			let pitch = surface.pitch() as usize;
+			let mut alphas = vec![];
+
+			surface.with_lock(|pixels| {
+				for y in 0..3 {
+					for x in 0..3 {
+						//? The alpha is the high byte of a native-endian word.
+						let offset = y * pitch + x * 4;
+						let word = u32::from_ne_bytes([pixels[offset], pixels[offset + 1], pixels[offset + 2], pixels[offset + 3]]);
+
+						alphas.push((word >> 24) as u8);
+					}
+				}
+			});
+			alphas.sort();
+			assert_eq!(alphas, vec![26, 26, 26, 26, 102, 102, 102, 102, 255]);
+		}
+	}
+}
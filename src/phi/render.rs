@@ -0,0 +1,164 @@
+use phi::data::Rectangle;
+use phi::gfx::{DrawParams, Renderable, Sprite};
+
+use sdl2::pixels::Color;
+use sdl2::render::Renderer;
+
+use std::cmp::Ordering;
+
+
+/// Something to be drawn once the frame's commands are flushed.
+pub enum DrawCommand {
+	/// Copies the sprite to the destination, with the given parameters or as
+	/// the sprite currently is.
+	Sprite(Sprite, Rectangle, Option<DrawParams>),
+	FillRect(Rectangle, Color),
+	/// Draws anything the other commands can't describe.
+	Custom(Box<Fn(&mut Renderer)>),
+}
+
+impl DrawCommand {
+	fn texture_id(&self) -> usize {
+		match *self {
+			DrawCommand::Sprite(ref sprite, _, _) => sprite.texture_id(),
+			_ => 0,
+		}
+	}
+
+	fn draw(&self, renderer: &mut Renderer) {
+		match *self {
+			DrawCommand::Sprite(ref sprite, dest, Some(ref params)) => sprite.render_ex(renderer, dest, params),
+			DrawCommand::Sprite(ref sprite, dest, None) => sprite.render(renderer, dest),
+			DrawCommand::FillRect(rect, color) => {
+				if let Some(rect) = rect.to_sdl() {
+					renderer.set_draw_color(color);
+					renderer.fill_rect(rect).unwrap();
+				}
+			},
+			DrawCommand::Custom(ref draw) => draw(renderer),
+		}
+	}
+}
+
+
+struct QueuedCommand {
+	layer: i32,
+	depth: f64,
+	texture: usize,
+	sequence: usize,
+	command: DrawCommand,
+}
+
+
+/// Collects the draw commands submitted during a frame, so that they're drawn
+/// in order of `layer`, then `depth` (lowest first), regardless of the order
+/// objects were visited in.
+///
+/// Commands sharing both a layer and a depth are grouped by texture, to save
+/// on state changes; their order is only kept within a texture. The queue is
+/// flushed by the game loop once the current view is done rendering, that is,
+/// on top of whatever the view drew directly through the renderer.
+pub struct RenderQueue {
+	commands: Vec<QueuedCommand>,
+	sequence: usize,
+}
+
+impl RenderQueue {
+	pub fn new() -> RenderQueue {
+		RenderQueue {
+			commands: vec![],
+			sequence: 0,
+		}
+	}
+
+
+	pub fn submit(&mut self, layer: i32, depth: f64, command: DrawCommand) {
+		self.commands.push(QueuedCommand {
+			layer: layer,
+			depth: depth,
+			texture: command.texture_id(),
+			sequence: self.sequence,
+			command: command,
+		});
+		self.sequence += 1;
+	}
+
+	pub fn sprite(&mut self, layer: i32, depth: f64, sprite: &Sprite, dest: Rectangle) {
+		self.submit(layer, depth, DrawCommand::Sprite(sprite.clone(), dest, None));
+	}
+
+	pub fn sprite_ex(&mut self, layer: i32, depth: f64, sprite: &Sprite, dest: Rectangle, params: &DrawParams) {
+		self.submit(layer, depth, DrawCommand::Sprite(sprite.clone(), dest, Some(*params)));
+	}
+
+	pub fn fill_rect(&mut self, layer: i32, depth: f64, rect: Rectangle, color: Color) {
+		self.submit(layer, depth, DrawCommand::FillRect(rect, color));
+	}
+
+	pub fn custom<F>(&mut self, layer: i32, depth: f64, draw: F) where F: Fn(&mut Renderer) + 'static {
+		self.submit(layer, depth, DrawCommand::Custom(Box::new(draw)));
+	}
+
+
+	pub fn len(&self) -> usize {
+		self.commands.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.commands.is_empty()
+	}
+
+	/// Drops the pending commands without drawing them.
+	pub fn clear(&mut self) {
+		self.commands.clear();
+		self.sequence = 0;
+	}
+
+	/// Draws every pending command in order, then empties the queue.
+	pub fn flush(&mut self, renderer: &mut Renderer) {
+		self.sort();
+
+		for queued in &self.commands {
+			queued.command.draw(renderer);
+		}
+		self.clear();
+	}
+
+
+	fn sort(&mut self) {
+		self.commands.sort_by(|a, b| {
+			a.layer.cmp(&b.layer)
+			.then(a.depth.partial_cmp(&b.depth).unwrap_or(Ordering::Equal))
+			.then(a.texture.cmp(&b.texture))
+			.then(a.sequence.cmp(&b.sequence))
+		});
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use phi::data::Rectangle;
+	use sdl2::pixels::Color;
+
+
+	#[test]
+	fn commands_are_sorted_by_layer_then_depth_then_submission() {
+		let mut queue = RenderQueue::new();
+		let rect = Rectangle { x: 0.0, y: 0.0, w: 1.0, h: 1.0 };
+
+		queue.fill_rect(2, 0.0, rect, Color::RGB(0, 0, 0));
+		queue.fill_rect(1, 5.0, rect, Color::RGB(0, 0, 1));
+		queue.fill_rect(1, -1.0, rect, Color::RGB(0, 0, 2));
+		queue.fill_rect(1, 5.0, rect, Color::RGB(0, 0, 3));
+		queue.sort();
+
+		let order: Vec<usize> = queue.commands.iter().map(|queued| queued.sequence).collect();
+
+		assert_eq!(order, vec![2, 1, 3, 0]);
+
+		queue.clear();
+		assert!(queue.is_empty());
+	}
+}
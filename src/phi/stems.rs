@@ -0,0 +1,209 @@
+use phi::assets::{Assets, Handle};
+use phi::audio::{Audio, Bus};
+use phi::backend::Sound;
+use phi::descr::Lines;
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+
+/// How fast the stems fade in and out by default, in volume per second.
+const DEFAULT_FADE_SPEED: f64 = 0.5;
+
+
+/// A stem, which fades in as the intensity goes from `from` to `to`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StemDescr {
+	pub path: String,
+	pub from: f64,
+	pub to: f64,
+}
+
+/// Describes a piece of music made of stems, one per line:
+///
+/// ```text
+/// # path                            from  to
+/// stem assets/sounds/game/drone.sfx 0     0
+/// stem assets/sounds/game/lead.sfx  0.6   0.9
+/// ```
+///
+/// A stem with `to` at zero is always heard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayeredMusicDescr {
+	pub stems: Vec<StemDescr>,
+}
+
+impl LayeredMusicDescr {
+	pub fn load(path: &Path) -> Result<LayeredMusicDescr, io::Error> {
+		let mut source = String::new();
+
+		try!(try!(File::open(path)).read_to_string(&mut source));
+
+		LayeredMusicDescr::parse(&source)
+	}
+
+	pub fn parse(source: &str) -> Result<LayeredMusicDescr, io::Error> {
+		let mut stems = vec![];
+
+		for mut line in Lines::new(source, "stems") {
+			match line.statement() {
+				"stem" => {
+					let path = try!(line.expect("path"));
+					let from: f64 = try!(line.parse());
+					let to: f64 = try!(line.parse());
+
+					try!(line.end());
+
+					if to < from {
+						return Err(line.error("the stem must fade in before it's fully heard"));
+					}
+					stems.push(StemDescr { path: path.to_string(), from: from, to: to });
+				},
+				statement => return Err(line.error(&format!("unknown statement '{}'", statement)))
+			}
+		}
+		Ok(LayeredMusicDescr { stems: stems })
+	}
+}
+
+
+/// The volume of a stem, in [0, 1], at the given intensity.
+fn stem_volume(stem: &StemDescr, intensity: f64) -> f64 {
+	if intensity >= stem.to {
+		1.0
+	} else if intensity <= stem.from {
+		0.0
+	} else {
+		(intensity - stem.from) / (stem.to - stem.from)
+	}
+}
+
+
+struct Stem {
+	descr: StemDescr,
+	sound: Handle<Sound>,
+	/// The current volume of the stem, which follows the intensity.
+	volume: f64,
+}
+
+/// Music made of stems of the same length, looped together on channels of
+/// their own, which are heard or not depending on how intense the game is.
+///
+/// The stems are played through the music bus.
+pub struct LayeredMusic {
+	stems: Vec<Stem>,
+	channels: Vec<usize>,
+
+	intensity: f64,
+	fade_speed: f64,
+}
+
+impl LayeredMusic {
+	/// Loads the stems listed by the descriptor located at the given path.
+	pub fn load(assets: &mut Assets, path: &str) -> Result<LayeredMusic, String> {
+		let descr = try!(LayeredMusicDescr::load(Path::new(path)).map_err(|e| e.to_string()));
+		let mut stems = Vec::with_capacity(descr.stems.len());
+
+		for stem in descr.stems {
+			stems.push(Stem {
+				sound: try!(assets.sound(&stem.path)),
+				descr: stem,
+				volume: 0.0,
+			});
+		}
+		Ok(LayeredMusic {
+			stems: stems,
+			channels: vec![],
+
+			intensity: 0.0,
+			fade_speed: DEFAULT_FADE_SPEED,
+		})
+	}
+
+	/// Sets how fast the stems fade in and out, in volume per second.
+	pub fn set_fade_speed(&mut self, fade_speed: f64) {
+		self.fade_speed = fade_speed.max(0.0);
+	}
+
+	pub fn intensity(&self) -> f64 {
+		self.intensity
+	}
+
+	/// Sets how intense the game is, in [0, 1]. The stems fade towards their
+	/// volume at this intensity.
+	pub fn set_intensity(&mut self, intensity: f64) {
+		self.intensity = intensity.max(0.0).min(1.0);
+	}
+
+	pub fn is_playing(&self) -> bool {
+		!self.channels.is_empty()
+	}
+
+
+	/// Starts looping every stem, those which aren't heard at the current
+	/// intensity included, so that they stay in sync.
+	pub fn start(&mut self, audio: &mut Audio) {
+		if self.is_playing() {
+			return;
+		}
+		self.channels = audio.reserve_channels(self.stems.len());
+
+		for stem in &mut self.stems {
+			stem.volume = stem_volume(&stem.descr, self.intensity);
+		}
+		let music_volume = audio.settings().effective_volume(Bus::Music);
+		let sounds: Vec<_> = self.stems.iter().zip(&self.channels)
+			.map(|(stem, &channel)| (channel, stem.sound.clone(), stem.volume * music_volume))
+			.collect();
+
+		audio.loop_reserved(&sounds);
+	}
+
+	/// Stops the stems, and gives their channels back to sound effects.
+	pub fn stop(&mut self, audio: &mut Audio) {
+		if self.is_playing() {
+			audio.release_channels();
+			self.channels.clear();
+		}
+	}
+
+	/// Fades the stems towards their volume at the current intensity.
+	pub fn update(&mut self, audio: &mut Audio, elapsed: f64) {
+		let music_volume = audio.settings().effective_volume(Bus::Music);
+		let step = self.fade_speed * elapsed;
+
+		for (stem, channel) in self.stems.iter_mut().zip(&self.channels) {
+			let target = stem_volume(&stem.descr, self.intensity);
+
+			stem.volume = if stem.volume < target {
+				(stem.volume + step).min(target)
+			} else {
+				(stem.volume - step).max(target)
+			};
+			audio.set_reserved_volume(*channel, stem.volume * music_volume);
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{stem_volume, LayeredMusicDescr, StemDescr};
+
+
+	#[test]
+	fn stems_fade_in_with_intensity() {
+		let descr = LayeredMusicDescr::parse("
+			# The base is always heard.
+			stem drone.sfx 0 0
+			stem lead.sfx 0.5 1
+		").unwrap();
+
+		assert_eq!(descr.stems[1], StemDescr { path: "lead.sfx".to_string(), from: 0.5, to: 1.0 });
+		assert_eq!(stem_volume(&descr.stems[0], 0.0), 1.0);
+		assert_eq!(stem_volume(&descr.stems[1], 0.25), 0.0);
+		assert_eq!(stem_volume(&descr.stems[1], 0.75), 0.5);
+		assert!(LayeredMusicDescr::parse("stem lead.sfx 1 0.5").is_err());
+	}
+}
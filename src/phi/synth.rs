@@ -0,0 +1,557 @@
+extern crate byteorder;
+extern crate rand;
+
+
+use phi::backend::Sound;
+use phi::descr::Lines;
+
+use std::f64::consts::PI;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+
+use self::byteorder::{LittleEndian, WriteBytesExt};
+use self::rand::Rng;
+
+
+/// The rate at which sounds are synthesized, in samples per second.
+pub const SAMPLE_RATE: u32 = 44100;
+/// The longest sound synthesized, in seconds, whatever its envelope.
+const MAX_DURATION: f64 = 5.0;
+/// The number of random values making up a period of noise.
+const NOISE_PERIOD: usize = 32;
+
+
+/// The shape of the wave which makes up the sound.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Waveform {
+	Square,
+	Sawtooth,
+	Triangle,
+	Sine,
+	Noise,
+}
+
+impl Waveform {
+	pub fn name(&self) -> &'static str {
+		match *self {
+			Waveform::Square => "square",
+			Waveform::Sawtooth => "sawtooth",
+			Waveform::Triangle => "triangle",
+			Waveform::Sine => "sine",
+			Waveform::Noise => "noise",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Waveform> {
+		match name {
+			"square" => Some(Waveform::Square),
+			"sawtooth" => Some(Waveform::Sawtooth),
+			"triangle" => Some(Waveform::Triangle),
+			"sine" => Some(Waveform::Sine),
+			"noise" => Some(Waveform::Noise),
+			_ => None
+		}
+	}
+}
+
+
+/// The kinds of sounds which may be generated at random.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Preset {
+	Pickup,
+	Laser,
+	Explosion,
+	PowerUp,
+	Hit,
+	Jump,
+	Blip,
+}
+
+
+//? Every parameter besides the waveform is a number, given along with the
+//? range it's kept in and its default value. The macro spares us from
+//? listing them again for parsing, saving and mutating the parameters.
+macro_rules! synth_params {
+	( $( $(#[$doc:meta])* $name:ident: ($min:expr, $max:expr) = $default:expr, )* ) => {
+		/// Describes a sound effect, which is synthesized rather than recorded.
+		#[derive(Clone, Debug, PartialEq)]
+		pub struct SynthParams {
+			pub wave: Waveform,
+			$( $(#[$doc])* pub $name: f64, )*
+		}
+
+		impl Default for SynthParams {
+			fn default() -> SynthParams {
+				SynthParams {
+					wave: Waveform::Square,
+					$( $name: $default, )*
+				}
+			}
+		}
+
+		impl SynthParams {
+			/// The name of every number, along with its range.
+			fn ranges() -> Vec<(&'static str, f64, f64)> {
+				vec![ $( (stringify!($name), $min, $max), )* ]
+			}
+
+			fn values(&self) -> Vec<(&'static str, f64)> {
+				vec![ $( (stringify!($name), self.$name), )* ]
+			}
+
+			fn value_mut(&mut self, name: &str) -> Option<&mut f64> {
+				match name {
+					$( stringify!($name) => Some(&mut self.$name), )*
+					_ => None
+				}
+			}
+		}
+	}
+}
+
+synth_params! {
+	/// The time the sound takes to reach its full volume, in seconds.
+	attack: (0.0, 2.0) = 0.0,
+	/// The time the sound is held at full volume, in seconds.
+	sustain: (0.0, 2.0) = 0.1,
+	/// How much louder the sound starts its sustain, fading back to its full
+	/// volume over it.
+	punch: (0.0, 1.0) = 0.0,
+	/// The time the sound takes to fade out, in seconds.
+	decay: (0.0, 2.0) = 0.2,
+
+	/// The starting pitch, in Hz.
+	frequency: (20.0, 4000.0) = 440.0,
+	/// The pitch below which the sound stops, in Hz. Zero never stops it.
+	min_frequency: (0.0, 4000.0) = 0.0,
+	/// How fast the pitch rises (or falls, when negative), in octaves per
+	/// second.
+	slide: (-20.0, 20.0) = 0.0,
+	/// How fast the slide changes, in octaves per second squared.
+	delta_slide: (-100.0, 100.0) = 0.0,
+
+	/// How much the pitch wobbles, as a fraction of itself.
+	vibrato_depth: (0.0, 1.0) = 0.0,
+	/// How fast the pitch wobbles, in Hz.
+	vibrato_speed: (0.0, 50.0) = 0.0,
+
+	/// The factor the pitch jumps by, once, after `arpeggio_time` seconds.
+	arpeggio: (0.25, 4.0) = 1.0,
+	/// Zero never makes the pitch jump.
+	arpeggio_time: (0.0, 2.0) = 0.0,
+	/// The period, in seconds, over which the pitch starts over from its
+	/// initial value, slide and arpeggio included. Zero never does.
+	repeat: (0.0, 2.0) = 0.0,
+
+	/// The fraction of the period during which square waves are high.
+	duty: (0.0, 1.0) = 0.5,
+	/// How fast the duty changes, per second.
+	duty_sweep: (-2.0, 2.0) = 0.0,
+
+	/// The cutoff of the low-pass filter, in [0, 1]. One lets every pitch
+	/// through.
+	lowpass: (0.0, 1.0) = 1.0,
+	/// How fast the low-pass cutoff changes, per second.
+	lowpass_sweep: (-2.0, 2.0) = 0.0,
+	/// How much the pitches near the low-pass cutoff are boosted.
+	resonance: (0.0, 1.0) = 0.0,
+	/// The cutoff of the high-pass filter, in [0, 1]. Zero lets every pitch
+	/// through.
+	highpass: (0.0, 1.0) = 0.0,
+	/// How fast the high-pass cutoff changes, per second.
+	highpass_sweep: (-2.0, 2.0) = 0.0,
+
+	volume: (0.0, 1.0) = 0.5,
+}
+
+impl SynthParams {
+	/// Generates the parameters of a sound of the given kind, which varies
+	/// with the random number generator.
+	pub fn preset<R: Rng>(preset: Preset, rng: &mut R) -> SynthParams {
+		let mut params = SynthParams::default();
+
+		match preset {
+			Preset::Pickup => {
+				params.frequency = between(rng, 800.0, 1600.0);
+				params.sustain = between(rng, 0.0, 0.1);
+				params.decay = between(rng, 0.1, 0.3);
+				params.punch = between(rng, 0.3, 0.6);
+
+				if rng.gen() {
+					params.arpeggio = between(rng, 1.25, 1.6);
+					params.arpeggio_time = between(rng, 0.03, 0.08);
+				}
+			},
+			Preset::Laser => {
+				params.wave = *rng.choose(&[Waveform::Square, Waveform::Sawtooth, Waveform::Sine]).unwrap();
+				params.frequency = between(rng, 600.0, 1800.0);
+				params.min_frequency = between(rng, 50.0, params.frequency * 0.3);
+				params.slide = between(rng, -12.0, -4.0);
+				params.duty = between(rng, 0.1, 0.5);
+				params.duty_sweep = between(rng, 0.0, 1.0);
+				params.sustain = between(rng, 0.02, 0.08);
+				params.decay = between(rng, 0.05, 0.2);
+				params.punch = between(rng, 0.0, 0.3);
+
+				if rng.gen_weighted_bool(3) {
+					params.highpass = between(rng, 0.0, 0.3);
+				}
+			},
+			Preset::Explosion => {
+				params.wave = Waveform::Noise;
+				params.frequency = between(rng, 40.0, 400.0);
+				params.slide = between(rng, -3.0, 0.0);
+				params.sustain = between(rng, 0.05, 0.3);
+				params.decay = between(rng, 0.2, 0.6);
+				params.punch = between(rng, 0.2, 0.8);
+
+				if rng.gen() {
+					params.vibrato_depth = between(rng, 0.0, 0.3);
+					params.vibrato_speed = between(rng, 5.0, 20.0);
+				}
+			},
+			Preset::PowerUp => {
+				params.wave = *rng.choose(&[Waveform::Square, Waveform::Sawtooth]).unwrap();
+				params.frequency = between(rng, 200.0, 600.0);
+				params.slide = between(rng, 1.0, 4.0);
+				params.duty = between(rng, 0.2, 0.5);
+				params.sustain = between(rng, 0.1, 0.3);
+				params.decay = between(rng, 0.1, 0.4);
+
+				if rng.gen() {
+					params.repeat = between(rng, 0.05, 0.15);
+				} else {
+					params.vibrato_depth = between(rng, 0.0, 0.2);
+					params.vibrato_speed = between(rng, 10.0, 30.0);
+				}
+			},
+			Preset::Hit => {
+				params.wave = *rng.choose(&[Waveform::Square, Waveform::Sawtooth, Waveform::Noise]).unwrap();
+				params.frequency = between(rng, 100.0, 800.0);
+				params.slide = between(rng, -8.0, -2.0);
+				params.sustain = between(rng, 0.0, 0.05);
+				params.decay = between(rng, 0.05, 0.2);
+				params.highpass = between(rng, 0.0, 0.3);
+			},
+			Preset::Jump => {
+				params.frequency = between(rng, 300.0, 700.0);
+				params.slide = between(rng, 2.0, 5.0);
+				params.duty = between(rng, 0.2, 0.5);
+				params.sustain = between(rng, 0.05, 0.15);
+				params.decay = between(rng, 0.05, 0.2);
+			},
+			Preset::Blip => {
+				params.wave = *rng.choose(&[Waveform::Square, Waveform::Sawtooth]).unwrap();
+				params.frequency = between(rng, 400.0, 1200.0);
+				params.duty = between(rng, 0.2, 0.5);
+				params.sustain = between(rng, 0.02, 0.05);
+				params.decay = between(rng, 0.01, 0.05);
+			},
+		}
+		params
+	}
+
+	/// Nudges about half of the numbers at random, by up to `amount` times
+	/// their range, so that variations of a sound may be tried out.
+	pub fn mutate<R: Rng>(&mut self, rng: &mut R, amount: f64) {
+		for (name, min, max) in SynthParams::ranges() {
+			if rng.gen() {
+				let offset = rng.gen_range(-1.0, 1.0) * amount * (max - min);
+				let value = self.value_mut(name).unwrap();
+
+				*value = (*value + offset).max(min).min(max);
+			}
+		}
+	}
+
+
+	/// Reads parameters written by `save`. Missing entries keep their
+	/// default.
+	pub fn load(path: &Path) -> Result<SynthParams, io::Error> {
+		let mut source = String::new();
+
+		try!(try!(File::open(path)).read_to_string(&mut source));
+
+		SynthParams::parse(&source)
+	}
+
+	/// Parses the parameters, given as one `<name> <value>` pair per line.
+	pub fn parse(source: &str) -> Result<SynthParams, io::Error> {
+		let mut params = SynthParams::default();
+
+		for mut line in Lines::new(source, "sound parameters") {
+			match line.statement() {
+				"wave" => {
+					let name = try!(line.expect("waveform"));
+					params.wave = try!(Waveform::from_name(name).ok_or(line.error(&format!("unknown waveform '{}'", name))));
+				},
+				name => {
+					let value = try!(line.parse());
+					*try!(params.value_mut(name).ok_or(line.error(&format!("unknown parameter '{}'", name)))) = value;
+				},
+			}
+		}
+		Ok(params)
+	}
+
+	pub fn to_string(&self) -> String {
+		let mut result = format!("wave {}\n", self.wave.name());
+
+		for (name, value) in self.values() {
+			result.push_str(&format!("{} {}\n", name, value));
+		}
+		result
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), io::Error> {
+		if let Some(parent) = path.parent() {
+			try!(fs::create_dir_all(parent));
+		}
+		try!(File::create(path)).write_all(self.to_string().as_bytes())
+	}
+
+
+	/// The length of the sound, in seconds.
+	pub fn duration(&self) -> f64 {
+		(self.attack + self.sustain + self.decay).min(MAX_DURATION)
+	}
+
+	/// Generates the sound as mono, signed 16-bit samples. The same parameters
+	/// always give the same samples.
+	pub fn synthesize(&self, sample_rate: u32) -> Vec<i16> {
+		let dt = 1.0 / sample_rate as f64;
+		let length = (self.duration() * sample_rate as f64) as usize;
+		let nyquist = sample_rate as f64 / 2.0;
+
+		//? The noise is seeded, so that the sound doesn't change whenever
+		//? it's synthesized again.
+		let mut rng = ::phi::procgen::seeded_rng(0x5f5);
+		let mut noise: Vec<f64> = (0..NOISE_PERIOD).map(|_| rng.gen_range(-1.0, 1.0)).collect();
+
+		let mut samples = Vec::with_capacity(length);
+		let mut pitch = Pitch::new(self);
+		let mut filter = Filter::new(self);
+		let mut phase = 0.0;
+		let mut duty = self.duty;
+
+		for i in 0..length {
+			let t = i as f64 * dt;
+
+			if self.repeat > 0.0 && pitch.elapsed >= self.repeat {
+				pitch = Pitch::new(self);
+			}
+			let frequency = match pitch.advance(self, dt) {
+				Some(frequency) => frequency,
+				//? The pitch slid below its minimum: the sound is cut short.
+				None => break
+			};
+			let vibrato = 1.0 + self.vibrato_depth * (2.0 * PI * self.vibrato_speed * t).sin();
+
+			phase += (frequency * vibrato).min(nyquist) * dt;
+
+			if phase >= 1.0 {
+				phase -= phase.floor();
+
+				if self.wave == Waveform::Noise {
+					for value in &mut noise {
+						*value = rng.gen_range(-1.0, 1.0);
+					}
+				}
+			}
+			duty = (duty + self.duty_sweep * dt).max(0.0).min(1.0);
+
+			let sample = match self.wave {
+				Waveform::Square => if phase < duty { 0.5 } else { -0.5 },
+				Waveform::Sawtooth => 1.0 - 2.0 * phase,
+				Waveform::Triangle => 4.0 * (phase - 0.5).abs() - 1.0,
+				Waveform::Sine => (2.0 * PI * phase).sin(),
+				Waveform::Noise => noise[(phase * NOISE_PERIOD as f64) as usize % NOISE_PERIOD],
+			};
+			let sample = filter.apply(self, sample, dt) * self.envelope(t) * self.volume;
+
+			samples.push((sample.max(-1.0).min(1.0) * ::std::i16::MAX as f64) as i16);
+		}
+		samples
+	}
+
+	/// Synthesizes the sound, ready to be played, under the given name.
+	pub fn to_sound(&self, name: &str) -> Result<Sound, String> {
+		Sound::from_wav(name, &wav(&self.synthesize(SAMPLE_RATE), 1, SAMPLE_RATE))
+	}
+
+
+	/// The volume of the sound at the time `t`, in seconds.
+	fn envelope(&self, t: f64) -> f64 {
+		if t < self.attack {
+			t / self.attack
+		} else if t < self.attack + self.sustain {
+			1.0 + (1.0 - (t - self.attack) / self.sustain) * 2.0 * self.punch
+		} else if self.decay > 0.0 {
+			(1.0 - (t - self.attack - self.sustain) / self.decay).max(0.0)
+		} else {
+			0.0
+		}
+	}
+}
+
+
+/// The pitch of a sound, as it slides.
+struct Pitch {
+	frequency: f64,
+	slide: f64,
+	/// The time since the pitch started, in seconds.
+	elapsed: f64,
+	arpeggiated: bool,
+}
+
+impl Pitch {
+	fn new(params: &SynthParams) -> Pitch {
+		Pitch {
+			frequency: params.frequency,
+			slide: params.slide,
+			elapsed: 0.0,
+			arpeggiated: false,
+		}
+	}
+
+	/// Returns the pitch, in Hz, after `dt` more seconds, unless it fell below
+	/// the minimum.
+	fn advance(&mut self, params: &SynthParams, dt: f64) -> Option<f64> {
+		self.elapsed += dt;
+		self.slide += params.delta_slide * dt;
+		self.frequency = (self.frequency * (self.slide * dt).exp2()).min(SAMPLE_RATE as f64);
+
+		if !self.arpeggiated && params.arpeggio_time > 0.0 && self.elapsed >= params.arpeggio_time {
+			self.frequency *= params.arpeggio;
+			self.arpeggiated = true;
+		}
+		if self.frequency < params.min_frequency { None } else { Some(self.frequency) }
+	}
+}
+
+
+/// A resonant low-pass filter, followed by a high-pass one.
+struct Filter {
+	lowpass: f64,
+	highpass: f64,
+	position: f64,
+	velocity: f64,
+	output: f64,
+}
+
+impl Filter {
+	fn new(params: &SynthParams) -> Filter {
+		Filter {
+			lowpass: params.lowpass,
+			highpass: params.highpass,
+			position: 0.0,
+			velocity: 0.0,
+			output: 0.0,
+		}
+	}
+
+	fn apply(&mut self, params: &SynthParams, sample: f64, dt: f64) -> f64 {
+		self.lowpass = (self.lowpass + params.lowpass_sweep * dt).max(0.0).min(1.0);
+		self.highpass = (self.highpass + params.highpass_sweep * dt).max(0.0).min(1.0);
+
+		let previous = self.position;
+
+		//? The low-pass filter is a damped spring pulled towards the sample:
+		//? the lower the cutoff, the looser the spring, and the less damped,
+		//? the more it resonates.
+		if self.lowpass < 1.0 {
+			let stiffness = self.lowpass.powi(3) * 0.1;
+			let damping = (5.0 / (1.0 + params.resonance.powi(2) * 20.0) * (0.01 + stiffness)).min(0.8);
+
+			self.velocity += (sample - self.position) * stiffness;
+			self.velocity -= self.velocity * damping;
+		} else {
+			self.position = sample;
+			self.velocity = 0.0;
+		}
+		self.position += self.velocity;
+
+		//? The high-pass filter lets the changes through, and leaks whatever
+		//? holds steady.
+		self.output += self.position - previous;
+		self.output -= self.output * self.highpass.powi(2) * 0.1;
+		self.output
+	}
+}
+
+
+/// Encodes signed 16-bit samples, interleaved over the channels, as a WAV
+/// file.
+pub fn wav(samples: &[i16], channels: u16, sample_rate: u32) -> Vec<u8> {
+	let data_len = samples.len() as u32 * 2;
+	let mut result = Vec::with_capacity(44 + data_len as usize);
+
+	//? Writing into a vector never fails.
+	result.extend_from_slice(b"RIFF");
+	result.write_u32::<LittleEndian>(36 + data_len).unwrap();
+	result.extend_from_slice(b"WAVEfmt ");
+	result.write_u32::<LittleEndian>(16).unwrap();
+	// PCM
+	result.write_u16::<LittleEndian>(1).unwrap();
+	result.write_u16::<LittleEndian>(channels).unwrap();
+	result.write_u32::<LittleEndian>(sample_rate).unwrap();
+	// Bytes per second, bytes per frame and bits per sample
+	result.write_u32::<LittleEndian>(sample_rate * 2 * channels as u32).unwrap();
+	result.write_u16::<LittleEndian>(2 * channels).unwrap();
+	result.write_u16::<LittleEndian>(16).unwrap();
+	result.extend_from_slice(b"data");
+	result.write_u32::<LittleEndian>(data_len).unwrap();
+
+	for &sample in samples {
+		result.write_i16::<LittleEndian>(sample).unwrap();
+	}
+	result
+}
+
+fn between<R: Rng>(rng: &mut R, low: f64, high: f64) -> f64 {
+	if high > low { rng.gen_range(low, high) } else { low }
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{Preset, SynthParams, Waveform, wav};
+	use phi::procgen::seeded_rng;
+
+
+	#[test]
+	fn parameters_round_trip() {
+		let mut params = SynthParams::preset(Preset::Laser, &mut seeded_rng(3));
+		params.mutate(&mut seeded_rng(4), 0.1);
+
+		assert_eq!(SynthParams::parse(&params.to_string()).unwrap(), params);
+		assert_eq!(SynthParams::parse("wave noise\ndecay 0.5 # Longer\n").unwrap().wave, Waveform::Noise);
+		assert!(SynthParams::parse("wobble 3").is_err());
+	}
+
+	#[test]
+	fn sounds_are_reproducible() {
+		for &preset in &[Preset::Pickup, Preset::Explosion, Preset::PowerUp, Preset::Hit] {
+			let params = SynthParams::preset(preset, &mut seeded_rng(9));
+			let samples = params.synthesize(8000);
+
+			assert_eq!(params, SynthParams::preset(preset, &mut seeded_rng(9)));
+			assert_eq!(samples, params.synthesize(8000));
+			assert!(samples.len() <= (params.duration() * 8000.0) as usize);
+			assert!(samples.iter().any(|&sample| sample != 0));
+		}
+	}
+
+	#[test]
+	fn wav_header() {
+		let bytes = wav(&[0, 1, -1], 1, 22050);
+
+		assert_eq!(bytes.len(), 44 + 6);
+		assert_eq!(&bytes[0..4], b"RIFF");
+		assert_eq!(&bytes[4..8], &[42, 0, 0, 0]);
+		assert_eq!(&bytes[24..28], &[0x22, 0x56, 0, 0]);
+		assert_eq!(&bytes[44..], &[0, 0, 1, 0, 0xff, 0xff]);
+	}
+}
@@ -0,0 +1,83 @@
+extern crate sdl2_sys;
+
+
+use phi::gfx::Sprite;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{BlendMode, Renderer};
+
+use std::ptr;
+
+
+use self::sdl2_sys::render::{SDL_SetRenderTarget, SDL_Texture};
+
+
+/// An offscreen image, which may be drawn into in place of the window, then
+/// drawn like any other sprite.
+///
+/// Only one target is bound at a time. Targets don't nest: unbinding one
+/// always gets back to drawing into the window.
+pub struct RenderTarget {
+	sprite: Sprite,
+	raw: *mut SDL_Texture,
+	size: (u32, u32),
+}
+
+impl RenderTarget {
+	/// Creates a transparent target of the given size, in pixels.
+	pub fn new(renderer: &mut Renderer, width: u32, height: u32) -> Result<RenderTarget, String> {
+		let mut texture = try!(renderer.create_texture_target(PixelFormatEnum::ARGB8888, width, height)
+			.map_err(|e| format!("Can't create render target: {:?}", e)));
+
+		texture.set_blend_mode(BlendMode::Blend);
+
+		//? The pointer stays valid for as long as the texture is alive, that
+		//? is, as long as the sprite (which we don't share mutably) is.
+		let raw = unsafe { texture.raw() };
+		let result = RenderTarget {
+			sprite: Sprite::from_texture(texture),
+			raw: raw,
+			size: (width, height),
+		};
+		try!(result.draw_into(renderer, |renderer| {
+			renderer.set_draw_color(Color::RGBA(0, 0, 0, 0));
+			renderer.clear();
+		}));
+		Ok(result)
+	}
+
+
+	/// The contents of the target, as a sprite.
+	pub fn sprite(&self) -> &Sprite {
+		&self.sprite
+	}
+
+	pub fn size(&self) -> (u32, u32) {
+		self.size
+	}
+
+
+	/// Redirects every subsequent drawing operation to the target.
+	pub fn bind(&self, renderer: &mut Renderer) -> Result<(), String> {
+		set_target(renderer, self.raw)
+	}
+
+	/// Gets back to drawing into the window.
+	pub fn unbind(renderer: &mut Renderer) -> Result<(), String> {
+		set_target(renderer, ptr::null_mut())
+	}
+
+	/// Binds the target, calls `draw`, then gets back to the window.
+	pub fn draw_into<F>(&self, renderer: &mut Renderer, draw: F) -> Result<(), String> where F: FnOnce(&mut Renderer) {
+		try!(self.bind(renderer));
+		draw(renderer);
+		RenderTarget::unbind(renderer)
+	}
+}
+
+fn set_target(renderer: &mut Renderer, texture: *mut SDL_Texture) -> Result<(), String> {
+	if unsafe { SDL_SetRenderTarget(renderer.raw(), texture) } != 0 {
+		return Err(::sdl2::get_error());
+	}
+	Ok(())
+}
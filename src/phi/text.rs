@@ -0,0 +1,452 @@
+use phi::Phi;
+use phi::assets::Handle;
+use phi::data::Rectangle;
+use phi::gfx::{DrawParams, Renderable, Sprite};
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect as SdlRect;
+use sdl2::render::{BlendMode, Renderer};
+use sdl2::surface::Surface;
+use sdl2::ttf::Font;
+
+use std::collections::HashMap;
+
+
+/// The width of glyph atlas images, in pixels. They grow downwards.
+const GLYPH_ATLAS_WIDTH: u32 = 512;
+
+
+/// How the lines of a text are aligned to each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+	Left,
+	Center,
+	Right,
+}
+
+/// Describes how a text looks and is laid out.
+///
+///     TextStyle { align: Align::Center, ..TextStyle::new(FONT, 16, Color::RGB(255, 255, 255)) }
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextStyle {
+	pub font: String,
+	pub size: u16,
+	pub color: Color,
+
+	pub align: Align,
+	/// The width lines are wrapped at, in pixels, if any. Lines only break
+	/// between words.
+	pub wrap: Option<f64>,
+	/// The multiplier applied to the font's line spacing.
+	pub line_spacing: f64,
+
+	/// The offset and the color of the shadow drawn below the text.
+	pub shadow: Option<((f64, f64), Color)>,
+	/// The thickness and the color of the outline drawn around the text.
+	pub outline: Option<(f64, Color)>,
+}
+
+impl TextStyle {
+	pub fn new(font: &str, size: u16, color: Color) -> TextStyle {
+		TextStyle {
+			font: font.to_string(),
+			size: size,
+			color: color,
+
+			align: Align::Left,
+			wrap: None,
+			line_spacing: 1.0,
+
+			shadow: None,
+			outline: None,
+		}
+	}
+}
+
+
+#[derive(Clone)]
+struct Glyph {
+	/// `None` for glyphs which leave no ink, such as spaces.
+	sprite: Option<Sprite>,
+	advance: f64,
+}
+
+/// The glyphs of a font at a given size, rasterized once into a shared image.
+/// Glyphs are rendered in white, and tinted when drawn.
+///
+/// Atlases are built and cached by `Assets::glyphs`, which rebuilds them in
+/// place whenever a text needs glyphs they don't hold yet.
+pub struct GlyphAtlas {
+	glyphs: HashMap<char, Glyph>,
+	line_height: f64,
+}
+
+impl GlyphAtlas {
+	/// Rasterizes the given characters. Characters missing from the font are
+	/// skipped, and later drawn as nothing.
+	pub fn build(renderer: &Renderer, font: &Font, chars: &[char]) -> Result<GlyphAtlas, String> {
+		let mut rendered = Vec::with_capacity(chars.len());
+
+		for &ch in chars {
+			let advance = match font.find_glyph_metrics(ch) {
+				Some(metrics) => metrics.advance as f64,
+				None => continue
+			};
+			let surface = font.render_char(ch).blended(Color::RGB(255, 255, 255)).ok()
+			.and_then(|surface| if surface.width() > 0 && surface.height() > 0 { Some(surface) } else { None });
+
+			rendered.push((ch, advance, surface));
+		}
+		// Lay the glyphs out, row by row.
+		let (mut x, mut y, mut row_height) = (0, 0, 0);
+		let mut places = Vec::with_capacity(rendered.len());
+
+		for &(_, _, ref surface) in &rendered {
+			places.push(surface.as_ref().map(|surface| {
+				if x + surface.width() > GLYPH_ATLAS_WIDTH {
+					x = 0;
+					y += row_height;
+					row_height = 0;
+				}
+				let place = SdlRect::new(x as i32, y as i32, surface.width(), surface.height());
+
+				x += surface.width();
+				row_height = row_height.max(surface.height());
+				place
+			}));
+		}
+		let mut sheet = try!(Surface::new(GLYPH_ATLAS_WIDTH, (y + row_height).max(1), PixelFormatEnum::ARGB8888));
+
+		for (&mut (_, _, ref mut surface), place) in rendered.iter_mut().zip(&places) {
+			if let (Some(surface), &Some(place)) = (surface.as_mut(), place) {
+				//? Copy the glyphs as they are, alpha included, rather than
+				//? blending them over the (transparent) sheet.
+				try!(surface.set_blend_mode(BlendMode::None));
+				try!(surface.blit(None, &mut sheet, Some(place)));
+			}
+		}
+		let sheet = try!(Sprite::from_surface(renderer, &sheet).ok_or("Can't create the glyph atlas texture!".to_string()));
+		let mut glyphs = HashMap::with_capacity(rendered.len());
+
+		for (&(ch, advance, _), place) in rendered.iter().zip(places) {
+			glyphs.insert(ch, Glyph {
+				sprite: place.and_then(|place| sheet.region(Rectangle {
+					x: place.x() as f64,
+					y: place.y() as f64,
+					w: place.width() as f64,
+					h: place.height() as f64,
+				})),
+				advance: advance,
+			});
+		}
+		Ok(GlyphAtlas {
+			glyphs: glyphs,
+			line_height: font.recommended_line_spacing() as f64,
+		})
+	}
+
+
+	/// Whether every (non-control) character of the text has been rasterized.
+	pub fn contains_all(&self, text: &str) -> bool {
+		text.chars().all(|ch| ch.is_control() || self.glyphs.contains_key(&ch))
+	}
+
+	pub fn chars(&self) -> Vec<char> {
+		self.glyphs.keys().cloned().collect()
+	}
+
+	pub fn line_height(&self) -> f64 {
+		self.line_height
+	}
+
+	fn advance(&self, ch: char) -> f64 {
+		self.glyphs.get(&ch).map_or(0.0, |glyph| glyph.advance)
+	}
+}
+
+
+/// The position of every glyph of a text, relative to its top-left corner.
+/// Laying a text out again reuses the previous allocations.
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+	glyphs: Vec<(char, f64, f64)>,
+	/// The (start, end) indices of each line in the text, and its width.
+	lines: Vec<(usize, usize, f64)>,
+	size: (f64, f64),
+}
+
+impl TextLayout {
+	pub fn new() -> TextLayout {
+		TextLayout::default()
+	}
+
+	/// The dimensions of the laid out text.
+	pub fn size(&self) -> (f64, f64) {
+		self.size
+	}
+
+	pub fn compute(&mut self, atlas: &GlyphAtlas, text: &str, style: &TextStyle) {
+		let line_height = atlas.line_height() * style.line_spacing;
+		let lines = &mut self.lines;
+
+		lines.clear();
+		self.glyphs.clear();
+
+		for (offset, paragraph) in paragraphs(text) {
+			let (mut start, mut width) = (offset, 0.0);
+			let mut word_start = offset;
+
+			for (index, ch) in paragraph.char_indices().map(|(i, ch)| (offset + i, ch))
+			.chain(Some((offset + paragraph.len(), ' '))) {
+				if ch != ' ' {
+					continue;
+				}
+				// Reached the end of a word: break the line before it if it
+				// doesn't fit, unless it's alone on the line.
+				let word_width = measure(atlas, &text[word_start..index]);
+
+				if let Some(wrap) = style.wrap {
+					if word_start > start && width + word_width > wrap {
+						let trimmed = &text[start..word_start];
+						lines.push((start, word_start, measure(atlas, trimmed.trim_end())));
+
+						start = word_start;
+						width = 0.0;
+					}
+				}
+				width += word_width + atlas.advance(' ');
+				word_start = index + 1;
+			}
+			let end = offset + paragraph.len();
+			lines.push((start, end, measure(atlas, &text[start..end])));
+		}
+		let width = style.wrap.unwrap_or(lines.iter().fold(0.0, |w, line| line.2.max(w)));
+
+		for (i, &(start, end, line_width)) in lines.iter().enumerate() {
+			let mut x = match style.align {
+				Align::Left => 0.0,
+				Align::Center => ((width - line_width) / 2.0).floor(),
+				Align::Right => width - line_width,
+			};
+			let y = i as f64 * line_height;
+
+			for ch in text[start..end].chars() {
+				self.glyphs.push((ch, x, y));
+				x += atlas.advance(ch);
+			}
+		}
+		self.size = (width, lines.len() as f64 * line_height);
+	}
+}
+
+/// Splits the text into lines, along with the offset each one starts at.
+fn paragraphs(text: &str) -> Vec<(usize, &str)> {
+	let mut offset = 0;
+
+	text.split('\n').map(|paragraph| {
+		let result = (offset, paragraph);
+		offset += paragraph.len() + 1;
+		result
+	}).collect()
+}
+
+/// Returns the width of a single line of text.
+fn measure(atlas: &GlyphAtlas, line: &str) -> f64 {
+	line.chars().fold(0.0, |width, ch| width + atlas.advance(ch))
+}
+
+
+/// A piece of text, drawn straight from the glyph atlas of its font. Changing
+/// the string only lays it out again, so that texts may change every frame.
+#[derive(Clone)]
+pub struct Text {
+	atlas: Handle<GlyphAtlas>,
+	style: TextStyle,
+	text: String,
+	layout: TextLayout,
+	alpha: f64,
+}
+
+impl Text {
+	pub fn new(phi: &mut Phi, text: &str, style: TextStyle) -> Result<Text, String> {
+		let atlas = try!(phi.assets.glyphs(&phi.renderer, &style.font, style.size, text));
+		let mut layout = TextLayout::new();
+
+		layout.compute(&atlas.borrow(), text, &style);
+
+		Ok(Text {
+			atlas: atlas,
+			style: style,
+			text: text.to_string(),
+			layout: layout,
+			alpha: 1.0,
+		})
+	}
+
+	/// Creates a text which fits into the given `bounds`, by lowering the
+	/// font size of the `style` as much as necessary.
+	pub fn fit(phi: &mut Phi, text: &str, style: TextStyle, bounds: (f64, f64)) -> Result<Text, String> {
+		let mut result = try!(Text::new(phi, text, style));
+
+		while result.style.size > 1 && (result.size().0 > bounds.0 || result.size().1 > bounds.1) {
+			let style = TextStyle { size: result.style.size - 1, ..result.style.clone() };
+			result = try!(Text::new(phi, text, style));
+		}
+		Ok(result)
+	}
+
+
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	/// Changes the displayed string. Does nothing if it's the same.
+	pub fn set_text(&mut self, phi: &mut Phi, text: &str) -> Result<(), String> {
+		if self.text == text {
+			return Ok(());
+		}
+		if !self.atlas.borrow().contains_all(text) {
+			self.atlas = try!(phi.assets.glyphs(&phi.renderer, &self.style.font, self.style.size, text));
+		}
+		self.text.clear();
+		self.text.push_str(text);
+		self.layout.compute(&self.atlas.borrow(), &self.text, &self.style);
+
+		Ok(())
+	}
+
+	pub fn style(&self) -> &TextStyle {
+		&self.style
+	}
+
+	/// Changes the colors of the text, which doesn't require laying it out.
+	pub fn set_color(&mut self, color: Color) {
+		self.style.color = color;
+	}
+
+	pub fn get_alpha(&self) -> f64 {
+		self.alpha
+	}
+
+	pub fn set_alpha(&mut self, alpha: f64) {
+		self.alpha = alpha.max(0.0).min(1.0);
+	}
+
+	/// The dimensions of the text's bounding box.
+	pub fn size(&self) -> (f64, f64) {
+		self.layout.size()
+	}
+
+
+	/// Calls `draw` for every glyph to copy, in order, for the shadow and
+	/// outline first, then for the text itself.
+	fn each_glyph<F>(&self, location: (f64, f64), mut draw: F) where F: FnMut(&Sprite, Rectangle, DrawParams) {
+		let atlas = self.atlas.borrow();
+		let mut passes = vec![];
+
+		if let Some((offset, color)) = self.style.shadow {
+			passes.push((offset, color));
+		}
+		if let Some((thickness, color)) = self.style.outline {
+			for &(dx, dy) in &[(-1.0, -1.0), (0.0, -1.0), (1.0, -1.0), (-1.0, 0.0), (1.0, 0.0), (-1.0, 1.0), (0.0, 1.0), (1.0, 1.0)] {
+				passes.push(((dx * thickness, dy * thickness), color));
+			}
+		}
+		passes.push(((0.0, 0.0), self.style.color));
+
+		for &((dx, dy), color) in &passes {
+			let params = DrawParams {
+				color: color,
+				alpha: self.alpha * color.rgba().3 as f64 / 255.0,
+				..DrawParams::default()
+			};
+
+			for &(ch, x, y) in &self.layout.glyphs {
+				if let Some(&Glyph { sprite: Some(ref sprite), .. }) = atlas.glyphs.get(&ch) {
+					let (w, h) = sprite.size();
+
+					draw(sprite, Rectangle {
+						x: (location.0 + x + dx).round(),
+						y: (location.1 + y + dy).round(),
+						w: w,
+						h: h,
+					}, params);
+				}
+			}
+		}
+	}
+
+	/// Submits the text to the render queue, with its top-left corner at the
+	/// given location.
+	pub fn queue(&self, phi: &mut Phi, layer: i32, depth: f64, location: (f64, f64)) {
+		let queue = &mut phi.queue;
+
+		self.each_glyph(location, |sprite, dest, params| {
+			queue.sprite_ex(layer, depth, sprite, dest, &params);
+		});
+	}
+}
+
+impl Renderable for Text {
+	/// Renders the text at the top of `dest`, aligned within its width.
+	fn render(&self, renderer: &mut Renderer, dest: Rectangle) {
+		let x = match self.style.align {
+			Align::Left => dest.x,
+			Align::Center => dest.x + ((dest.w - self.size().0) / 2.0).floor(),
+			Align::Right => dest.x + dest.w - self.size().0,
+		};
+
+		self.each_glyph((x, dest.y), |sprite, dest, params| {
+			sprite.render_ex(renderer, dest, &params);
+		});
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sdl2::pixels::Color;
+	use std::collections::HashMap;
+
+
+	/// An atlas of glyphs without ink, every one of them 10 pixels wide.
+	fn monospace_atlas(text: &str) -> GlyphAtlas {
+		let mut glyphs = HashMap::new();
+
+		for ch in text.chars() {
+			glyphs.insert(ch, Glyph { sprite: None, advance: 10.0 });
+		}
+		GlyphAtlas { glyphs: glyphs, line_height: 20.0 }
+	}
+
+	fn line_starts(layout: &TextLayout) -> Vec<(f64, f64)> {
+		let mut starts: Vec<(f64, f64)> = vec![];
+
+		for &(_, x, y) in &layout.glyphs {
+			if starts.last().map_or(true, |&(_, last_y)| last_y != y) {
+				starts.push((x, y));
+			}
+		}
+		starts
+	}
+
+
+	#[test]
+	fn layout_wraps_words_and_aligns_lines() {
+		let text = "aaa bb cccc\ndd";
+		let atlas = monospace_atlas("abcd ");
+		let mut style = TextStyle::new("font.ttf", 12, Color::RGB(255, 255, 255));
+		let mut layout = TextLayout::new();
+
+		layout.compute(&atlas, text, &style);
+		assert_eq!(layout.size(), (110.0, 40.0));
+
+		style.wrap = Some(65.0);
+		style.align = Align::Right;
+		layout.compute(&atlas, text, &style);
+
+		assert_eq!(layout.size(), (65.0, 60.0));
+		assert_eq!(line_starts(&layout), vec![(5.0, 0.0), (25.0, 20.0), (45.0, 40.0)]);
+	}
+}
@@ -0,0 +1,547 @@
+use phi::color::rgba;
+use phi::data::Rectangle;
+
+use sdl2::pixels::Color;
+
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::rc::Rc;
+
+
+/// Values which may be linearly interpolated, that is, blended together.
+pub trait Lerp {
+	/// Returns the value `t` of the way from `self` to `other`, where `t` is
+	/// usually in [0, 1]. Some easings overshoot that range.
+	fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+	fn lerp(&self, other: &f64, t: f64) -> f64 {
+		self + (other - self) * t
+	}
+}
+
+impl Lerp for (f64, f64) {
+	fn lerp(&self, other: &(f64, f64), t: f64) -> (f64, f64) {
+		(self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+	}
+}
+
+impl Lerp for Rectangle {
+	fn lerp(&self, other: &Rectangle, t: f64) -> Rectangle {
+		Rectangle {
+			x: self.x.lerp(&other.x, t),
+			y: self.y.lerp(&other.y, t),
+			w: self.w.lerp(&other.w, t),
+			h: self.h.lerp(&other.h, t),
+		}
+	}
+}
+
+impl Lerp for Color {
+	fn lerp(&self, other: &Color, t: f64) -> Color {
+		let (r_a, g_a, b_a, a_a) = self.rgba();
+		let (r_b, g_b, b_b, a_b) = other.rgba();
+		let channel = |a: u8, b: u8| (a as f64).lerp(&(b as f64), t).max(0.0).min(255.0).round() as u8;
+
+		rgba(channel(r_a, r_b), channel(g_a, g_b), channel(b_a, b_b), channel(a_a, a_b))
+	}
+}
+
+
+/// The standard easing functions, which map the progress of a tween, in
+/// [0, 1], to how far the value went from its start to its end.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+	Linear,
+	QuadIn, QuadOut, QuadInOut,
+	CubicIn, CubicOut, CubicInOut,
+	QuintIn, QuintOut, QuintInOut,
+	SineIn, SineOut, SineInOut,
+	ExpoIn, ExpoOut,
+	/// Goes slightly backwards before starting.
+	BackIn,
+	/// Overshoots the end, then settles on it.
+	BackOut,
+	ElasticOut,
+	BounceOut,
+}
+
+impl Easing {
+	pub fn apply(&self, t: f64) -> f64 {
+		let t = t.max(0.0).min(1.0);
+		// Turns an "in" easing into the matching "in-out" one.
+		let in_out = |ease: &Fn(f64) -> f64| {
+			if t < 0.5 { ease(2.0 * t) / 2.0 } else { 1.0 - ease(2.0 - 2.0 * t) / 2.0 }
+		};
+
+		match *self {
+			Easing::Linear => t,
+
+			Easing::QuadIn => t.powi(2),
+			Easing::QuadOut => 1.0 - (1.0 - t).powi(2),
+			Easing::QuadInOut => in_out(&|t| t.powi(2)),
+
+			Easing::CubicIn => t.powi(3),
+			Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+			Easing::CubicInOut => in_out(&|t| t.powi(3)),
+
+			Easing::QuintIn => t.powi(5),
+			Easing::QuintOut => 1.0 - (1.0 - t).powi(5),
+			Easing::QuintInOut => in_out(&|t| t.powi(5)),
+
+			Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+			Easing::SineOut => (t * PI / 2.0).sin(),
+			Easing::SineInOut => (1.0 - (t * PI).cos()) / 2.0,
+
+			Easing::ExpoIn => if t == 0.0 { 0.0 } else { 2.0f64.powf(10.0 * (t - 1.0)) },
+			Easing::ExpoOut => if t == 1.0 { 1.0 } else { 1.0 - 2.0f64.powf(-10.0 * t) },
+
+			Easing::BackIn => t * t * (2.70158 * t - 1.70158),
+			Easing::BackOut => 1.0 - Easing::BackIn.apply(1.0 - t),
+
+			Easing::ElasticOut => {
+				if t == 0.0 || t == 1.0 { t }
+				else { 2.0f64.powf(-10.0 * t) * ((t * 10.0 - 0.75) * 2.0 * PI / 3.0).sin() + 1.0 }
+			},
+			Easing::BounceOut => {
+				let (n, d) = (7.5625, 2.75);
+
+				if t < 1.0 / d { n * t * t }
+				else if t < 2.0 / d { let t = t - 1.5 / d; n * t * t + 0.75 }
+				else if t < 2.5 / d { let t = t - 2.25 / d; n * t * t + 0.9375 }
+				else { let t = t - 2.625 / d; n * t * t + 0.984375 }
+			},
+		}
+	}
+}
+
+
+/// A value shared between the tweens animating it and its owner.
+pub struct Tweened<T: Copy> {
+	value: Rc<Cell<T>>,
+}
+
+impl<T: Copy> Tweened<T> {
+	pub fn new(value: T) -> Tweened<T> {
+		Tweened {
+			value: Rc::new(Cell::new(value)),
+		}
+	}
+
+	pub fn get(&self) -> T {
+		self.value.get()
+	}
+
+	pub fn set(&self, value: T) {
+		self.value.set(value);
+	}
+}
+
+impl<T: Copy> Clone for Tweened<T> {
+	fn clone(&self) -> Tweened<T> {
+		Tweened {
+			value: self.value.clone(),
+		}
+	}
+}
+
+
+/// Something which plays over time, so that it may be grouped with others.
+pub trait Animation {
+	/// Moves the animation forward by `dt` seconds. Returns the part of `dt`
+	/// left over once the animation finished, if it did.
+	fn advance(&mut self, dt: f64) -> f64;
+
+	fn is_finished(&self) -> bool;
+
+	/// Rewinds the animation to its start.
+	fn reset(&mut self);
+}
+
+
+/// Animates a value from a start to an end, over some duration.
+///
+///     let pulse = Tween::new(1.0, 1.2, 0.5).easing(Easing::SineOut).yoyo(true).repeat_forever();
+pub struct Tween<T: Lerp + Copy> {
+	from: T,
+	to: T,
+	duration: f64,
+	easing: Easing,
+	delay: f64,
+	/// The number of times the tween plays again after the first one, if it
+	/// doesn't repeat forever.
+	repeats: Option<u32>,
+	yoyo: bool,
+
+	elapsed: f64,
+	delay_left: f64,
+	passes: u32,
+	finished: bool,
+
+	output: Tweened<T>,
+	on_complete: Option<Box<FnMut()>>,
+}
+
+impl<T: Lerp + Copy> Tween<T> {
+	/// Creates a linear tween, which plays once.
+	pub fn new(from: T, to: T, duration: f64) -> Tween<T> {
+		Tween {
+			from: from,
+			to: to,
+			duration: duration.max(0.0),
+			easing: Easing::Linear,
+			delay: 0.0,
+			repeats: Some(0),
+			yoyo: false,
+
+			elapsed: 0.0,
+			delay_left: 0.0,
+			passes: 0,
+			finished: false,
+
+			output: Tweened::new(from),
+			on_complete: None,
+		}
+	}
+
+	pub fn easing(mut self, easing: Easing) -> Tween<T> {
+		self.easing = easing;
+		self.write();
+		self
+	}
+
+	/// Waits for `delay` seconds before starting.
+	pub fn delay(mut self, delay: f64) -> Tween<T> {
+		self.delay = delay.max(0.0);
+		self.delay_left = self.delay;
+		self
+	}
+
+	/// Plays the tween `count` more times once it's done.
+	pub fn repeat(mut self, count: u32) -> Tween<T> {
+		self.repeats = Some(count);
+		self
+	}
+
+	pub fn repeat_forever(mut self) -> Tween<T> {
+		self.repeats = None;
+		self
+	}
+
+	/// Whether every other play goes from the end back to the start.
+	pub fn yoyo(mut self, yoyo: bool) -> Tween<T> {
+		self.yoyo = yoyo;
+		self
+	}
+
+	/// Calls `callback` once every play (repetitions included) is done.
+	pub fn on_complete<F>(mut self, callback: F) -> Tween<T> where F: FnMut() + 'static {
+		self.on_complete = Some(Box::new(callback));
+		self
+	}
+
+	/// Writes the animated value into the given shared value as well.
+	pub fn bind(mut self, output: &Tweened<T>) -> Tween<T> {
+		self.output = output.clone();
+		self.write();
+		self
+	}
+
+
+	/// The current value.
+	pub fn value(&self) -> T {
+		self.output.get()
+	}
+
+	/// A handle to the current value, which stays up to date.
+	pub fn output(&self) -> Tweened<T> {
+		self.output.clone()
+	}
+
+	pub fn from(&self) -> T {
+		self.from
+	}
+
+	pub fn to(&self) -> T {
+		self.to
+	}
+
+	/// Restarts the tween from its current value, towards another one.
+	pub fn retarget(&mut self, to: T, duration: f64) {
+		self.from = self.value();
+		self.to = to;
+		self.duration = duration.max(0.0);
+		self.reset();
+	}
+
+
+	/// Computes the value at the current time, and shares it.
+	fn write(&self) {
+		let progress = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+		let backwards = self.yoyo && self.passes % 2 == 1;
+		let t = self.easing.apply(if backwards { 1.0 - progress } else { progress });
+
+		self.output.set(self.from.lerp(&self.to, t));
+	}
+}
+
+impl<T: Lerp + Copy> Animation for Tween<T> {
+	fn advance(&mut self, dt: f64) -> f64 {
+		if self.finished {
+			return dt;
+		}
+		let mut dt = dt;
+
+		if self.delay_left > 0.0 {
+			let waited = self.delay_left.min(dt);
+
+			self.delay_left -= waited;
+			dt -= waited;
+
+			if self.delay_left > 0.0 {
+				return 0.0;
+			}
+		}
+		loop {
+			let left = self.duration - self.elapsed;
+
+			if dt < left {
+				self.elapsed += dt;
+				self.write();
+				return 0.0;
+			}
+			dt -= left;
+
+			let last = match self.repeats {
+				Some(count) => self.passes >= count,
+				//? A tween taking no time would repeat forever within a
+				//? single frame, so we stop it right away.
+				None => self.duration == 0.0,
+			};
+			if last {
+				self.elapsed = self.duration;
+				self.write();
+				self.finished = true;
+
+				if let Some(ref mut callback) = self.on_complete {
+					callback();
+				}
+				return dt;
+			}
+			self.passes += 1;
+			self.elapsed = 0.0;
+		}
+	}
+
+	fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	fn reset(&mut self) {
+		self.elapsed = 0.0;
+		self.delay_left = self.delay;
+		self.passes = 0;
+		self.finished = false;
+		self.write();
+	}
+}
+
+
+/// Plays animations one after the other.
+pub struct Sequence {
+	animations: Vec<Box<Animation>>,
+	current: usize,
+	repeats: Option<u32>,
+	passes: u32,
+	on_complete: Option<Box<FnMut()>>,
+}
+
+impl Sequence {
+	pub fn new(animations: Vec<Box<Animation>>) -> Sequence {
+		Sequence {
+			animations: animations,
+			current: 0,
+			repeats: Some(0),
+			passes: 0,
+			on_complete: None,
+		}
+	}
+
+	/// Plays the sequence `count` more times once it's done.
+	pub fn repeat(mut self, count: u32) -> Sequence {
+		self.repeats = Some(count);
+		self
+	}
+
+	pub fn repeat_forever(mut self) -> Sequence {
+		self.repeats = None;
+		self
+	}
+
+	pub fn on_complete<F>(mut self, callback: F) -> Sequence where F: FnMut() + 'static {
+		self.on_complete = Some(Box::new(callback));
+		self
+	}
+}
+
+impl Animation for Sequence {
+	fn advance(&mut self, dt: f64) -> f64 {
+		let mut dt = dt;
+		// Animations which take no time would loop forever within a frame.
+		let mut progressed = false;
+
+		while self.current < self.animations.len() {
+			let before = dt;
+
+			dt = self.animations[self.current].advance(dt);
+
+			if !self.animations[self.current].is_finished() {
+				return 0.0;
+			}
+			progressed |= dt < before;
+			self.current += 1;
+
+			if self.current == self.animations.len() {
+				let last = match self.repeats {
+					Some(count) => self.passes >= count,
+					None => !progressed,
+				};
+				if last {
+					if let Some(ref mut callback) = self.on_complete {
+						callback();
+					}
+					return dt;
+				}
+				self.passes += 1;
+				self.current = 0;
+				progressed = false;
+
+				for animation in &mut self.animations {
+					animation.reset();
+				}
+			}
+		}
+		dt
+	}
+
+	fn is_finished(&self) -> bool {
+		self.current >= self.animations.len()
+	}
+
+	fn reset(&mut self) {
+		self.current = 0;
+		self.passes = 0;
+
+		for animation in &mut self.animations {
+			animation.reset();
+		}
+	}
+}
+
+
+/// Plays animations at the same time. Done once all of them are.
+pub struct Parallel {
+	animations: Vec<Box<Animation>>,
+	finished: bool,
+	on_complete: Option<Box<FnMut()>>,
+}
+
+impl Parallel {
+	pub fn new(animations: Vec<Box<Animation>>) -> Parallel {
+		Parallel {
+			animations: animations,
+			finished: false,
+			on_complete: None,
+		}
+	}
+
+	pub fn on_complete<F>(mut self, callback: F) -> Parallel where F: FnMut() + 'static {
+		self.on_complete = Some(Box::new(callback));
+		self
+	}
+}
+
+impl Animation for Parallel {
+	fn advance(&mut self, dt: f64) -> f64 {
+		if self.finished {
+			return dt;
+		}
+		// What's left over is what the longest animation didn't use.
+		let left = self.animations.iter_mut()
+		.map(|animation| animation.advance(dt))
+		.fold(dt, |left, animation_left| left.min(animation_left));
+
+		if self.animations.iter().all(|animation| animation.is_finished()) {
+			self.finished = true;
+
+			if let Some(ref mut callback) = self.on_complete {
+				callback();
+			}
+			return left;
+		}
+		0.0
+	}
+
+	fn is_finished(&self) -> bool {
+		self.finished
+	}
+
+	fn reset(&mut self) {
+		self.finished = false;
+
+		for animation in &mut self.animations {
+			animation.reset();
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::Cell;
+	use std::rc::Rc;
+
+
+	#[test]
+	fn easings_start_at_zero_and_end_at_one() {
+		for easing in &[Easing::Linear, Easing::QuadInOut, Easing::CubicOut, Easing::QuintIn, Easing::SineInOut,
+			Easing::ExpoIn, Easing::ExpoOut, Easing::BackIn, Easing::BackOut, Easing::ElasticOut, Easing::BounceOut] {
+			assert!(easing.apply(0.0).abs() < 1e-9, "{:?}", easing);
+			assert!((easing.apply(1.0) - 1.0).abs() < 1e-9, "{:?}", easing);
+		}
+		assert_eq!(Easing::QuadInOut.apply(0.5), 0.5);
+	}
+
+	#[test]
+	fn yoyo_tweens_go_back_and_complete_once() {
+		let completed = Rc::new(Cell::new(0));
+		let counter = completed.clone();
+		let mut tween = Tween::new(0.0, 10.0, 1.0).yoyo(true).repeat(1)
+			.on_complete(move || counter.set(counter.get() + 1));
+
+		assert_eq!(tween.advance(0.5), 0.0);
+		assert_eq!(tween.value(), 5.0);
+		tween.advance(1.0);
+		assert_eq!(tween.value(), 5.0);
+		assert_eq!(tween.advance(1.0), 0.5);
+		assert_eq!(tween.value(), 0.0);
+		assert!(tween.is_finished());
+		assert_eq!(completed.get(), 1);
+	}
+
+	#[test]
+	fn sequences_carry_time_over() {
+		let value = Tweened::new(0.0);
+		let mut sequence = Sequence::new(vec![
+			Box::new(Tween::new(0.0, 1.0, 1.0).bind(&value)),
+			Box::new(Tween::new(1.0, 3.0, 1.0).bind(&value)),
+		]);
+
+		sequence.advance(1.5);
+		assert_eq!(value.get(), 2.0);
+		assert_eq!(sequence.advance(1.0), 0.5);
+		assert!(sequence.is_finished());
+	}
+}
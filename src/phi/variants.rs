@@ -0,0 +1,202 @@
+extern crate rand;
+
+
+use phi::assets::Handle;
+use phi::backend::Sound;
+use phi::synth::wav;
+
+use sdl2::mixer::{self, Chunk, AUDIO_S16LSB};
+
+
+use self::rand::Rng;
+
+
+/// How a sound varies every time it's played.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VariantConfig {
+	/// How far the pitch strays from the original, either way, in semitones.
+	pub pitch: f64,
+	/// The number of pitches every file is resampled to, the original one
+	/// included.
+	pub count: usize,
+	/// How much quieter than the original the sound may get, as a fraction
+	/// of its volume.
+	pub volume_jitter: f64,
+}
+
+impl Default for VariantConfig {
+	fn default() -> VariantConfig {
+		VariantConfig {
+			pitch: 1.0,
+			count: 4,
+			volume_jitter: 0.15,
+		}
+	}
+}
+
+
+/// The variants of a sound: its files are played in turn, each of them at one
+/// of the pitches it was resampled to when loaded.
+pub struct Variants {
+	/// The pitched variants of every file.
+	pools: Vec<Vec<Handle<Sound>>>,
+	/// The last variant played of every file.
+	last: Vec<Option<usize>>,
+	next: usize,
+	volume_jitter: f64,
+}
+
+impl Variants {
+	/// Resamples every one of the `sources` to the pitches of the config.
+	pub fn new(sources: &[Handle<Sound>], config: &VariantConfig) -> Result<Variants, String> {
+		let mut pools = Vec::with_capacity(sources.len());
+
+		for source in sources {
+			let mut pool = vec![source.clone()];
+
+			//? The pitches are spread evenly, the original one sitting in the
+			//? middle of them, so that no two variants sound the same.
+			for semitones in pitch_offsets(config.pitch, config.count) {
+				pool.push(Handle::new(try!(pitched(&source.borrow(), semitones))));
+			}
+			pools.push(pool);
+		}
+		if pools.is_empty() {
+			return Err("A sound needs at least one file to vary!".to_string());
+		}
+		Ok(Variants {
+			last: vec![None; pools.len()],
+			pools: pools,
+			next: 0,
+			volume_jitter: config.volume_jitter.max(0.0).min(1.0),
+		})
+	}
+
+	/// Returns the variant to play next, along with the factor its volume is
+	/// multiplied by.
+	pub fn pick<R: Rng>(&mut self, rng: &mut R) -> (Handle<Sound>, f64) {
+		let file = self.next;
+		let index = pick_index(rng, self.pools[file].len(), self.last[file]);
+
+		self.next = (self.next + 1) % self.pools.len();
+		self.last[file] = Some(index);
+
+		(self.pools[file][index].clone(), 1.0 - rng.gen::<f64>() * self.volume_jitter)
+	}
+}
+
+
+/// The semitones every variant but the original is shifted by.
+fn pitch_offsets(spread: f64, count: usize) -> Vec<f64> {
+	if count < 2 || spread <= 0.0 {
+		return vec![];
+	}
+	//? The original takes the place of the offset closest to zero.
+	let step = 2.0 * spread / (count - 1) as f64;
+	let mut offsets: Vec<f64> = (0..count).map(|i| -spread + step * i as f64).collect();
+	let closest = (0..count).min_by(|&a, &b| offsets[a].abs().partial_cmp(&offsets[b].abs()).unwrap()).unwrap();
+
+	offsets.remove(closest);
+	offsets
+}
+
+/// Picks a variant at random, other than the last one played.
+fn pick_index<R: Rng>(rng: &mut R, count: usize, last: Option<usize>) -> usize {
+	match last {
+		Some(last) if count > 1 => (last + rng.gen_range(1, count)) % count,
+		_ => rng.gen_range(0, count),
+	}
+}
+
+
+/// Copies the samples of the chunk, which are in the format the mixer was
+/// opened with.
+///
+/// Panics unless the mixer plays signed 16-bit samples.
+pub fn chunk_samples(chunk: &Chunk) -> Vec<i16> {
+	let (abuf, alen) = unsafe { ((*chunk.raw).abuf, (*chunk.raw).alen as usize) };
+	let bytes = unsafe { ::std::slice::from_raw_parts(abuf as *const u8, alen) };
+
+	bytes.chunks(2)
+	.filter(|pair| pair.len() == 2)
+	.map(|pair| (pair[0] as u16 | (pair[1] as u16) << 8) as i16)
+	.collect()
+}
+
+/// Stretches the samples, interleaved over the channels, so that they play
+/// `rate` times faster, and thus higher.
+pub fn resample(samples: &[i16], channels: usize, rate: f64) -> Vec<i16> {
+	let frames = samples.len() / channels;
+
+	if frames == 0 || rate <= 0.0 {
+		return vec![];
+	}
+	let length = (frames as f64 / rate).floor() as usize;
+	let mut result = Vec::with_capacity(length * channels);
+
+	for i in 0..length {
+		let position = i as f64 * rate;
+		let frame = (position.floor() as usize).min(frames - 1);
+		let next = (frame + 1).min(frames - 1);
+		let t = position - frame as f64;
+
+		for channel in 0..channels {
+			let (a, b) = (samples[frame * channels + channel] as f64, samples[next * channels + channel] as f64);
+			result.push((a + (b - a) * t).round() as i16);
+		}
+	}
+	result
+}
+
+/// Creates a copy of the sound, shifted by the given number of semitones.
+/// Silent sounds stay silent.
+pub fn pitched(sound: &Sound, semitones: f64) -> Result<Sound, String> {
+	let chunk = match sound.chunk() {
+		Some(chunk) => chunk,
+		None => return Ok(Sound::silent(sound.name()))
+	};
+	let (frequency, format, channels) = try!(mixer::query_spec());
+
+	if format != AUDIO_S16LSB {
+		return Err("Can't resample sounds unless the mixer plays 16-bit samples!".to_string());
+	}
+	let samples = resample(&chunk_samples(chunk), channels as usize, (semitones / 12.0).exp2());
+
+	Sound::from_wav(sound.name(), &wav(&samples, channels as u16, frequency as u32))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{pick_index, pitch_offsets, resample};
+	use phi::procgen::seeded_rng;
+
+
+	#[test]
+	fn pitches_are_spread_around_the_original() {
+		assert_eq!(pitch_offsets(2.0, 5), vec![-2.0, -1.0, 1.0, 2.0]);
+		assert_eq!(pitch_offsets(1.5, 4).len(), 3);
+		assert!(pitch_offsets(1.0, 1).is_empty());
+	}
+
+	#[test]
+	fn resampling_keeps_channels_apart() {
+		let samples = [0, 100, 10, 200, 20, 300, 30, 400];
+
+		assert_eq!(resample(&samples, 2, 2.0), vec![0, 100, 20, 300]);
+		assert_eq!(resample(&samples, 2, 0.5)[0..6].to_vec(), vec![0, 100, 5, 150, 10, 200]);
+	}
+
+	#[test]
+	fn variants_dont_repeat() {
+		let mut rng = seeded_rng(1);
+		let mut last = None;
+
+		for _ in 0..100 {
+			let index = pick_index(&mut rng, 4, last);
+
+			assert!(index < 4 && Some(index) != last);
+			last = Some(index);
+		}
+	}
+}
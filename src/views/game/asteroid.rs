@@ -1,26 +1,21 @@
 use phi::Phi;
+use phi::assets::Handle;
 use phi::data::Rectangle;
-use phi::gfx::{AlphaChannel, AnimatedSprite, AnimatedSpriteDescr, Renderable};
+use phi::gfx::{AlphaChannel, AnimatedSprite};
 
 use sdl2::pixels::Color;
 
-use std::rc::Rc;
+use std::cell::Ref;
 
 
-use super::{GameObject, HitBox};
-
-
-const ASTEROIDS_WIDE: usize = 21;
-const ASTEROIDS_HIGH: usize = 7;
-const ASTEROIDS_TOTAL: usize = ASTEROIDS_WIDE * ASTEROIDS_HIGH - 4;
-const ASTEROID_SIDE: f64 = 96.0;
+use super::{layers, GameObject, HitBox};
 
 
 pub struct Asteroid {
 	rect: Rectangle,
 	sprite: AnimatedSprite,
 
-	alpha: AlphaChannel,
+	alpha: Handle<AlphaChannel>,
 
 	velocity: f64,
 }
@@ -28,21 +23,12 @@ pub struct Asteroid {
 impl Asteroid {
 
 	pub fn factory(context: &mut Phi) -> AsteroidFactory {
-		let (alpha, spritesheet) = super::load_spritesheet_with_alpha(context, "assets/sprites/asteroid.png", 0.5).unwrap();
+		let atlas = context.assets.atlas(&context.renderer, "assets/sprites/asteroid.atlas").unwrap();
+		let alpha = context.assets.atlas_mask(&atlas, 0.5).unwrap();
 
 		AsteroidFactory {
-			alpha: Rc::new(alpha),
-			sprite: AnimatedSprite::new(
-				AnimatedSprite::load_frames(
-					&spritesheet, 
-					AnimatedSpriteDescr {
-						total_frames: ASTEROIDS_TOTAL,
-						frames_high: ASTEROIDS_HIGH,
-						frames_wide: ASTEROIDS_WIDE,
-						frame_w: ASTEROID_SIDE,
-						frame_h: ASTEROID_SIDE,
-					}),
-				0.0),
+			alpha: alpha,
+			sprite: atlas.clip("spin").unwrap(),
 		}
 	}
 }
@@ -50,7 +36,7 @@ impl Asteroid {
 impl GameObject<Asteroid> for Asteroid {
 
 	fn is_alive(&self) -> bool {
-		self.rect.x > -ASTEROID_SIDE
+		self.rect.x > -self.rect.w
 	}
 
 	fn location(&self) -> (f64, f64) {
@@ -72,10 +58,10 @@ impl GameObject<Asteroid> for Asteroid {
 
 		if ::DEBUG {
 			// Render the bounding box
-			context.renderer.set_draw_color(Color::RGB(200, 200, 50));
-			context.renderer.fill_rect(self.rect.to_sdl().unwrap()).unwrap();
+			context.queue.fill_rect(layers::ASTEROIDS, -1.0, self.rect, Color::RGB(200, 200, 50));
 		}
-		self.sprite.render(&mut context.renderer, self.rect);
+		context.queue.sprite(layers::ASTEROIDS, 0.0,
+			self.sprite.get_frame_at(self.sprite.current_frame_index()), self.rect);
 	}
 }
 
@@ -88,14 +74,14 @@ impl HitBox for Asteroid {
 		self.sprite.get_frame_at(self.sprite.current_frame_index()).frame()
 	}
 
-	fn collision_mask(&self) -> &AlphaChannel {
-		&self.alpha
+	fn collision_mask<'a>(&'a self) -> Ref<'a, AlphaChannel> {
+		self.alpha.borrow()
 	}
 }
 
 
 pub struct AsteroidFactory {
-	alpha: Rc<AlphaChannel>,
+	alpha: Handle<AlphaChannel>,
 	sprite: AnimatedSprite,
 }
 
@@ -103,24 +89,25 @@ impl AsteroidFactory {
 
 	pub fn random(&self, context: &mut Phi) -> Asteroid {
 		let (w, h) = context.output_size();
+		let (side_w, side_h) = self.sprite.get_frame_at(0).size();
 
-		// FPS in [10.0, 30.0)
+		// Playback speed in [0.5, 1.5), that is, 10 to 30 FPS for the clip.
 		let mut sprite = self.sprite.clone();
 
-		sprite.set_fps(super::rand::random::<f64>().abs() * 20.0 + 10.0);
+		sprite.set_speed(super::rand::random::<f64>().abs() + 0.5);
 
 		Asteroid {
 			// In the screen vertically, and over the right of the screen
 			// horizontally.
 			rect: Rectangle {
-				w: ASTEROID_SIDE,
-				h: ASTEROID_SIDE,
+				w: side_w,
+				h: side_h,
 				x: w,
-				y: super::rand::random::<f64>().abs() * (h - ASTEROID_SIDE),
+				y: super::rand::random::<f64>().abs() * (h - side_h),
 			},
 			sprite: sprite,
 
-			alpha: self.alpha.as_ref().clone(),
+			alpha: self.alpha.clone(),
 
 			// velocity in [50.0, 150.0)
 			velocity: super::rand::random::<f64>().abs() * 100.0 + 50.0,
@@ -1,27 +1,22 @@
 use phi::{Phi, RendererExtensions};
+use phi::tween::{Animation, Easing, Tween};
 
 use sdl2::pixels::Color;
 
 
-use super::GameObject;
+use super::{layers, GameObject};
 
 
 const BLAST_RADIUS_MAX: f64 = 53.568;
 const BLAST_RADIUS_MIN: f64 = 32.448;
-const BLAST_DURATION: f64 = 121.3;
-
-macro_rules! lifetime_scaled (
-	( $value_expr: expr ) => { 
-		(1000.0 * ($value_expr))
-	}
-);
+const BLAST_DURATION: f64 = 0.1213;
 
 
 pub struct Blast {
 	center: (f64, f64),
 
-	blast_radius: f64,
-	lifetime: f64,
+	//? The blast shrinks slowly at first, then faster and faster.
+	radius: Tween<f64>,
 }
 
 
@@ -30,15 +25,16 @@ impl Blast {
 		Blast {
 			center: center,
 
-			blast_radius: BLAST_RADIUS_MAX,
-			lifetime: 0.0,
+			radius: Tween::new(BLAST_RADIUS_MAX, BLAST_RADIUS_MIN, BLAST_DURATION).easing(Easing::QuintIn),
 		}
 	}
 
 	pub fn hits_at(&self, location: (f64, f64)) -> bool {
 		let location = (location.0 - self.center.0, location.1 - self.center.1);
 
-		return (location.0 * location.0 + location.1 * location.1) < self.blast_radius * self.blast_radius;			
+		let radius = self.radius.value();
+
+		return (location.0 * location.0 + location.1 * location.1) < radius * radius;
 	}
 }
 
@@ -46,7 +42,7 @@ impl Blast {
 impl GameObject<Blast> for Blast {
 
 	fn is_alive(&self) -> bool {
-		lifetime_scaled!(self.lifetime) <= BLAST_DURATION
+		!self.radius.is_finished()
 	}
 
 	fn location(&self) -> (f64, f64) {
@@ -54,14 +50,9 @@ impl GameObject<Blast> for Blast {
 	}
 
 	fn update(mut self: Box<Blast>, _: &mut Phi, dt: f64) -> Option<Box<Blast>> {
-		self.lifetime += dt;
+		self.radius.advance(dt);
 
 		if self.is_alive() {
-			let t = lifetime_scaled!(self.lifetime) / BLAST_DURATION;
-			let value = BLAST_RADIUS_MAX - (BLAST_RADIUS_MAX - BLAST_RADIUS_MIN) * (t * t * t * t * t);
-
-			self.blast_radius = value;
-
 			return Some(self);
 		}
 		None		
@@ -70,7 +61,11 @@ impl GameObject<Blast> for Blast {
 	fn render(&self, context: &mut Phi) {
 		assert!(self.is_alive());
 		
-		context.renderer.set_draw_color(Color::RGB(200, 50, 10));
-		context.renderer.fill_circle(self.center.0, self.center.1, self.blast_radius).unwrap();	
+		let (center, radius) = (self.center, self.radius.value());
+
+		context.queue.custom(layers::BLASTS, 0.0, move |renderer| {
+			renderer.set_draw_color(Color::RGB(200, 50, 10));
+			renderer.fill_circle(center.0, center.1, radius).unwrap();
+		});
 	}
 }
\ No newline at end of file
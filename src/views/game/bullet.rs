@@ -1,6 +1,9 @@
 use phi::Phi;
+use phi::color::Hsv;
 use phi::data::Rectangle;
-use phi::gfx::AlphaChannel;
+use phi::gfx::{AlphaChannel, Contact};
+use phi::procgen::seeded_rng;
+use phi::synth::{Preset, SynthParams, Waveform};
 
 use sdl2::pixels::Color;
 
@@ -8,7 +11,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 
-use super::{GameObject, HitBox};
+use super::{layers, GameObject, HitBox};
 
 
 const BULLET_W: f64 = 8.0;
@@ -31,18 +34,21 @@ impl Bullet {
 		self.ballistics.borrow().hit_rect(self).center()
 	}
 
-	pub fn hits_at(&mut self, body: &HitBox) -> Option<(f64, f64)> {
+	/// Returns the contact set of the bullet and the `body`, if the former
+	/// hits the latter. The bullet dies on hit.
+	pub fn hits_at(&mut self, body: &HitBox) -> Option<Contact> {
 		let hit_rect = self.ballistics.borrow().hit_rect(self);
 
 		Rectangle::intersection(&hit_rect, body.frame())
 		.map_or(None, |intersection| {
+			let (x, y) = (body.frame().x - body.bounds().x, body.frame().y - body.bounds().y);
 
-			if AlphaChannel::intersect_box(body.collision_mask(), body.frame().x - body.bounds().x, body.frame().y - body.bounds().y, intersection) {
+			AlphaChannel::contact_box(&body.collision_mask(), x, y, intersection)
+			.map(|contact| {
 				self.is_dead |= true;
 
-				return Some(intersection.center());
-			}
-			None
+				contact.oriented(hit_rect.center(), body.frame().center())
+			})
 		})
 	}
 }
@@ -64,8 +70,9 @@ impl GameObject<Bullet> for Bullet {
 		// We will render this kind of bullet in bullet_color(time).
 		//? This is exactly how we drew our first moving rectangle in the
 		//? seventh part of this series.
-		phi.renderer.set_draw_color(bullet_color(self.lifetime));
-		phi.renderer.fill_rect(self.ballistics.borrow().hit_rect(self).to_sdl().unwrap()).unwrap();
+		let rect = self.ballistics.borrow().hit_rect(self);
+
+		phi.queue.fill_rect(layers::BULLETS, 0.0, rect, bullet_color(self.lifetime));
 	}
 
 	fn update(mut self: Box<Bullet>, context: &mut Phi, dt: f64) -> Option<Box<Bullet>> {	
@@ -90,6 +97,36 @@ pub enum CannonType {
 	DivergentBullet { a: f64, b: f64 },
 }
 
+impl CannonType {
+	/// The sound the cannon makes when firing, which follows the way its
+	/// bullets move.
+	pub fn firing_sound(&self) -> SynthParams {
+		//? The sounds are seeded, so that they're the same in every game.
+		let mut sound = SynthParams::preset(Preset::Laser, &mut seeded_rng(0xb1e7));
+		sound.volume = 0.3;
+
+		match *self {
+			CannonType::RectBullet => {
+				sound.wave = Waveform::Square;
+			},
+			CannonType::SineBullet { amplitude, angular_vel } => {
+				//? The pitch wobbles as the bullets do.
+				sound.wave = Waveform::Sine;
+				sound.vibrato_depth = (amplitude / 40.0).min(1.0);
+				sound.vibrato_speed = angular_vel;
+			},
+			CannonType::DivergentBullet { b, .. } => {
+				//? Two bullets spreading apart: a lower, longer sweep.
+				sound.wave = Waveform::Sawtooth;
+				sound.frequency *= 0.6;
+				sound.min_frequency *= 0.5;
+				sound.decay *= b;
+			},
+		}
+		sound
+	}
+}
+
 
 pub fn spawn(cannon: CannonType, cannons_x: f64, cannon1_y: f64, cannon2_y: f64) -> Vec<Box<Bullet>> {
 	let (ballistics_a, ballistics_b) = match cannon {
@@ -223,18 +260,7 @@ impl Ballistics for DivergentBulletBallistics {
 }
 
 
+/// Goes around the hue wheel every PI seconds.
 fn bullet_color(t: f64) -> Color {
-	let angle = (6.0 * t / ::std::f64::consts::PI) % 6.0;
-	let x = (255.0 * (1.0 - (angle % 2.0 - 1.0).abs())) as u8;
-
-	match angle as i32 {
-		0 => Color::RGB(255, x, 0),
-		1 => Color::RGB(x, 255, 0),
-		2 => Color::RGB(0, 255, x),
-		3 => Color::RGB(0, x, 255),
-		4 => Color::RGB(x, 0, 255),
-		5 => Color::RGB(255, 0, x),
-		// This is impossible.
-		_ => unreachable!()
-	}
-}
\ No newline at end of file
+	Hsv::new(t * 360.0 / ::std::f64::consts::PI, 1.0, 1.0).to_color()
+}
@@ -1,46 +1,24 @@
 use phi::Phi;
 use phi::data::Rectangle;
-use phi::gfx::{AnimatedSprite, AnimatedSpriteDescr, Renderable};
+use phi::gfx::{AnimatedSprite, Blend, DrawParams};
 
 
-use super::GameObject;
-
-
-const EXPLOSIONS_WIDE: usize = 5;
-const EXPLOSIONS_HIGH: usize = 4;
-const EXPLOSIONS_TOTAL: usize = 17;
-const EXPLOSION_SIDE: f64 = 96.0;
-const EXPLOSION_FPS: f64 = 16.0;
-const EXPLOSION_DURATION: f64 = 1.0 / EXPLOSION_FPS * EXPLOSIONS_TOTAL as f64;
-
-const BLAST_RADIUS_MAX: f64 = EXPLOSION_SIDE * 0.558;
-const BLAST_RADIUS_MIN: f64 = EXPLOSION_SIDE * 0.338;
-const BLAST_DURATION: f64 = EXPLOSION_DURATION / 8.76;
+use super::{layers, GameObject};
 
 
 pub struct Explosion {
 	rect: Rectangle,
 	sprite: AnimatedSprite,
-
-	//? Keep how long its been arrived, so that we destroy the explosion once
-	//? its animation is finished.
-	lifetime: f64,
+	params: DrawParams,
 }
 
 impl Explosion {
 
 	pub fn factory(phi: &mut Phi) -> ExplosionFactory {
+		let atlas = phi.assets.atlas(&phi.renderer, "assets/sprites/explosion.atlas").unwrap();
+
 		ExplosionFactory {
-			sprite: AnimatedSprite::load_with_fps(
-				"assets/sprites/explosion.png", phi,
-				AnimatedSpriteDescr {
-					total_frames: EXPLOSIONS_TOTAL,
-					frames_high: EXPLOSIONS_HIGH,
-					frames_wide: EXPLOSIONS_WIDE,
-					frame_w: EXPLOSION_SIDE,
-					frame_h: EXPLOSION_SIDE,
-				},
-				EXPLOSION_FPS),
+			sprite: atlas.clip("explode").unwrap(),
 		}
 	}
 }
@@ -48,7 +26,9 @@ impl Explosion {
 impl GameObject<Explosion> for Explosion {
 
 	fn is_alive(&self) -> bool {
-		self.lifetime <= EXPLOSION_DURATION
+		//? The clip plays once, so we destroy the explosion as soon as its
+		//? animation is finished.
+		!self.sprite.is_finished()
 	}
 
 	fn location(&self) -> (f64, f64) {
@@ -56,7 +36,6 @@ impl GameObject<Explosion> for Explosion {
 	}
 
 	fn update(mut self: Box<Explosion>, _: &mut Phi, dt: f64) -> Option<Box<Explosion>> {
-		self.lifetime += dt;
 		self.sprite.add_time(dt);
 
 		if self.is_alive() {
@@ -66,7 +45,9 @@ impl GameObject<Explosion> for Explosion {
 	}
 
 	fn render(&self, context: &mut Phi) {
-		self.sprite.render(&mut context.renderer, self.rect);
+		let frame = self.sprite.get_frame_at(self.sprite.current_frame_index());
+
+		context.queue.sprite_ex(layers::EXPLOSIONS, 0.0, frame, self.rect, &self.params);
 	}
 }
 
@@ -77,18 +58,21 @@ pub struct ExplosionFactory {
 
 impl ExplosionFactory {
 	pub fn at_center(&self, center: (f64, f64)) -> Explosion {
-		// FPS in [10.0, 30.0)
 		let sprite = self.sprite.clone();
+		let (w, h) = sprite.get_frame_at(0).size();
 
 		Explosion {
 			sprite: sprite,
-
-			// In the screen vertically, and over the right of the screen
-			// horizontally.
-			rect: Rectangle::with_size(EXPLOSION_SIDE, EXPLOSION_SIDE)
+			//? Explosions glow over whatever they're drawn on, and every one of
+			//? them is turned at random, so that they don't look all alike.
+			params: DrawParams {
+				angle: super::rand::random::<f64>() * 360.0,
+				blend: Blend::Additive,
+				..DrawParams::default()
+			},
+
+			rect: Rectangle::with_size(w, h)
 			.center_at(center),
-
-			lifetime: 0.0,
 		}
 	}
 }
\ No newline at end of file
@@ -9,31 +9,105 @@ mod player;
 
 
 use phi::{Phi, View, ViewAction};
+use phi::assets::{Asset, Handle};
+use phi::audio::{Audio, SoundLimits, Steal};
+use phi::backend::Sound;
+use phi::color::Palette;
 use phi::data::Rectangle;
-use phi::gfx::{AlphaChannel, Renderable, Sprite};
+use phi::gfx::{AlphaChannel, Contact};
+use phi::music::DEFAULT_FADE;
+use phi::particles::{Curve, Emitter, EmitterConfig};
+use phi::stems::LayeredMusic;
+use phi::text::{Text, TextStyle};
+use phi::tween::{Animation, Tween};
+use phi::variants::{VariantConfig, Variants};
 
 use sdl2::pixels::Color;
-use sdl2::rwops::RWops;
-use sdl2::image::ImageRWops;
-use sdl2::mixer::Chunk;
 
-use std::cell::RefCell;
-use std::path::Path;
+use std::cell::{Ref, RefCell};
 use std::rc::Rc;
 
-use hud::background::Background;
 use hud::button::Button;
+use hud::parallax::ParallaxStack;
+use hud::starfield::Starfield;
+use hud::widgets::{Alignment, Anchor, Anchored, HBox, ImageButton, Ui};
 
 
 use self::asteroid::{Asteroid, AsteroidFactory};
 use self::blast::Blast;
 use self::bullet::Bullet;
 use self::explosion::{Explosion, ExplosionFactory};
-use self::player::Player;
+use self::player::{Player, CANNONS};
 
 
 const SHOT_DELAY: f64 = 1.0 / 7.62;
 
+const BUTTON_LABEL_FONT: &'static str = "assets/fonts/BlackOpsOne-Regular.ttf";
+/// The largest size of the labels of the buttons; they shrink to fit.
+const BUTTON_LABEL_SIZE: u16 = 9;
+const BUTTON_SIZE: (f64, f64) = (32.0, 32.0);
+/// The space kept between the label and the bottom-right corner of a button.
+const BUTTON_LABEL_MARGIN: (f64, f64) = (3.5, 1.5);
+const BUTTON_SPACING: f64 = 2.0;
+/// The distance from the buttons to the bottom-left corner of the window.
+const BUTTONS_MARGIN: (f64, f64) = (8.0, 8.0);
+/// How fast the buttons fade in and out, in opacity per second.
+const BUTTONS_FADE_SPEED: f64 = 4.0;
+
+/// The number of asteroids on screen past which the game is as intense as it
+/// gets, as far as they're concerned.
+const CROWDED_ASTEROIDS: f64 = 12.0;
+/// The distance, in pixels, under which asteroids endanger the player.
+const DANGER_DISTANCE: f64 = 240.0;
+/// How much every explosion excites the game, and how fast it calms down, per
+/// second.
+const EXCITEMENT_PER_EXPLOSION: f64 = 0.25;
+const EXCITEMENT_DECAY: f64 = 0.5;
+
+/// The faint stars flying out of the backdrop, far fewer and slower than in
+/// the menus so that they don't distract from the game.
+const COUNT_STARS_MAX: usize = 256;
+const VELOCITY_STAR: f64 = 12.0;
+/// Between the ambient backdrop, the first parallax layer, and the others.
+const STARS_DEPTH: f64 = 0.5;
+
+/// The render queue layers of the game, back to front.
+mod layers {
+	pub const BACKGROUND: i32 = 0;
+	pub const ASTEROIDS: i32 = 10;
+	pub const EXHAUST: i32 = 15;
+	pub const PLAYER: i32 = 20;
+	pub const BULLETS: i32 = 30;
+	pub const BLASTS: i32 = 40;
+	pub const DEBRIS: i32 = 45;
+	pub const EXPLOSIONS: i32 = 50;
+	pub const SPARKS: i32 = 55;
+	pub const FOREGROUND: i32 = 60;
+	pub const HUD: i32 = 70;
+}
+
+/// The assets bound to the lifetime of the view.
+const GAME_ASSETS: [Asset<'static>; 15] = [
+	Asset::Atlas("assets/sprites/asteroid.atlas"),
+	Asset::Atlas("assets/sprites/explosion.atlas"),
+	Asset::Atlas("assets/sprites/spaceship.atlas"),
+	Asset::AtlasMask("assets/sprites/asteroid.atlas", 0.5),
+	Asset::AtlasMask("assets/sprites/spaceship.atlas", 0.5),
+	Asset::Texture("assets/backgrounds/starAMB.png"),
+	Asset::Texture("assets/backgrounds/starBG.png"),
+	Asset::Texture("assets/backgrounds/starMG.png"),
+	Asset::Texture("assets/backgrounds/starFG.png"),
+	Asset::Texture("assets/sprites/button_ammo0.png"),
+	Asset::Texture("assets/sprites/button_ammo1.png"),
+	Asset::Texture("assets/sprites/button_ammo2.png"),
+	Asset::Font("assets/fonts/BlackOpsOne-Regular.ttf", 9),
+	Asset::Sound("assets/sounds/explosion.ogg"),
+	Asset::Palette(GAME_PALETTE),
+];
+
+const GAME_PALETTE: &'static str = "assets/palettes/game.palette";
+const GAME_MUSIC: &'static str = "assets/sounds/game/game.stems";
+
 
 pub struct GameView {
 	player: Rc<RefCell<Box<Player>>>,
@@ -47,24 +121,77 @@ pub struct GameView {
 	bullets: Vec<Box<Bullet>>,
 	explosions: Vec<Box<Explosion>>,
 
-	bg_ambient: Background,
-	bg_back: Background,
-	bg_middle: Background,
-	bg_front: Background,
+	exhaust: Emitter,
+	sparks: Emitter,
+	debris: Emitter,
+
+	backgrounds: ParallaxStack,
+	stars: Starfield,
+
+	/// The ammo buttons, the one of the current ammo being focused.
+	hud: Ui,
+	buttons_alpha: Tween<f64>,
+
+	palette: Handle<Palette>,
 
-	buttons_ammo: Vec<GameButton>,
+	/// The firing sound of every cannon, indexed by the player's ammo.
+	cannon_sounds: Vec<Handle<Sound>>,
+	explosion_sound: Handle<Sound>,
 
-	bullet_sound: Chunk,
-	explosion_sound: Chunk,
+	music: LayeredMusic,
+	/// How much the recent explosions excite the game, in [0, 1].
+	excitement: f64,
 }
 
 impl GameView {
 	pub fn new (phi: &mut Phi) -> GameView {
-		let mut buttons_ammo = Vec::with_capacity(3);
+		phi.assets.preload(&phi.renderer, "game", &GAME_ASSETS).unwrap();
+		phi.effects.set_vignette(Some(0.7));
+		//? The menu's theme fades out as the game starts.
+		phi.music.stop(&mut phi.assets, DEFAULT_FADE);
+
+		let palette = phi.assets.palette(GAME_PALETTE).unwrap();
+		let (exhaust_colors, sparks_colors, debris_color) = {
+			let colors = palette.borrow();
+
+			(colors.gradient("exhaust").unwrap().stops().to_vec(),
+				colors.gradient("sparks").unwrap().stops().to_vec(),
+				colors.color_or("debris", Color::RGB(150, 130, 110)))
+		};
+
+		let mut music = LayeredMusic::load(&mut phi.assets, GAME_MUSIC).unwrap();
+		music.start(&mut phi.audio);
+
+		let buttons_ammo = HBox::new(BUTTON_SPACING)
+			.align(Alignment::End)
+			.child(ammo_button(phi, "assets/sprites/button_ammo0.png", "1"))
+			.child(ammo_button(phi, "assets/sprites/button_ammo1.png", "2"))
+			.child(ammo_button(phi, "assets/sprites/button_ammo2.png", "3"));
+		let hud = Ui::new(Anchored::new(Anchor::BottomLeft, buttons_ammo).margin(BUTTONS_MARGIN.0, BUTTONS_MARGIN.1))
+			.layer(layers::HUD);
+
+		let cannon_sounds: Vec<Handle<Sound>> = CANNONS.iter().enumerate()
+			.map(|(i, cannon)| Handle::new(cannon.firing_sound().to_sound(&format!("cannon {}", i)).unwrap()))
+			.collect();
+		let explosion_sound = phi.assets.sound("assets/sounds/explosion.ogg").unwrap();
 
-		buttons_ammo.push(GameButton::new(phi, "assets/sprites/button_ammo0.png", "1", (32.0, 32.0), (1.5, 1.5, 3.5, 1.5)));
-		buttons_ammo.push(GameButton::new(phi, "assets/sprites/button_ammo1.png", "2", (32.0, 32.0), (1.5, 1.5, 3.5, 1.5)));
-		buttons_ammo.push(GameButton::new(phi, "assets/sprites/button_ammo2.png", "3", (32.0, 32.0), (1.5, 1.5, 3.5, 1.5)));
+		//? A volley of bullets hitting a crowd of asteroids would otherwise
+		//? play dozens of explosions at once.
+		for sound in &cannon_sounds {
+			phi.audio.set_limits(sound, SoundLimits { max_voices: 4, steal: Steal::Oldest, cooldown: 0.03 });
+		}
+		phi.audio.set_limits(&explosion_sound, SoundLimits { max_voices: 6, steal: Steal::Oldest, cooldown: 0.05 });
+
+		//? Sounds repeated many times a second are tiring when they're
+		//? exactly the same: they vary in pitch and volume instead.
+		for sound in &cannon_sounds {
+			phi.audio.set_variants(sound, Variants::new(&[sound.clone()], &VariantConfig::default()).unwrap());
+		}
+		phi.audio.set_variants(&explosion_sound, Variants::new(&[explosion_sound.clone()], &VariantConfig {
+			pitch: 2.0,
+			count: 5,
+			volume_jitter: 0.2,
+		}).unwrap());
 
 		GameView {
 			player: Rc::new(RefCell::new(Box::new(Player::new(phi)))),
@@ -78,26 +205,75 @@ impl GameView {
 			bullets: vec![],
 			explosions: vec![],
 
-			bg_ambient: Background::load(&phi.renderer, "assets/backgrounds/starAMB.png", 0.0).unwrap(),
-			bg_back: Background::load(&phi.renderer, "assets/backgrounds/starBG.png", 20.0).unwrap(),
-			bg_middle: Background::load(&phi.renderer, "assets/backgrounds/starMG.png", 40.0).unwrap(),
-			bg_front: Background::load(&phi.renderer, "assets/backgrounds/starFG.png", 80.0).unwrap(),
-
-			buttons_ammo: buttons_ammo,
+			exhaust: Emitter::new(EmitterConfig {
+				rate: 90.0,
+				lifetime: (0.15, 0.35),
+				speed: (80.0, 140.0),
+				direction: 180.0,
+				spread: 25.0,
+				size: Curve::between(5.0, 1.0),
+				color: Curve::new(exhaust_colors),
+				layer: layers::EXHAUST,
+				..EmitterConfig::default()
+			}),
+			sparks: Emitter::new(EmitterConfig {
+				lifetime: (0.1, 0.3),
+				speed: (150.0, 300.0),
+				spread: 70.0,
+				drag: 4.0,
+				size: Curve::between(3.0, 1.0),
+				color: Curve::new(sparks_colors),
+				layer: layers::SPARKS,
+				..EmitterConfig::default()
+			}),
+			debris: Emitter::new(EmitterConfig {
+				max_particles: 512,
+				lifetime: (0.6, 1.4),
+				speed: (20.0, 120.0),
+				drag: 1.5,
+				size: Curve::between(4.0, 2.0),
+				color: Curve::constant(debris_color),
+				layer: layers::DEBRIS,
+				..EmitterConfig::default()
+			}),
+
+			backgrounds: ParallaxStack::load(phi, "assets/backgrounds/game.parallax").unwrap(),
+			stars: Starfield::new(COUNT_STARS_MAX, VELOCITY_STAR, Color::RGB(110, 112, 130)),
+
+			hud: hud,
+			buttons_alpha: Tween::new(1.0, 1.0, 0.0),
+
+			palette: palette,
+
+			cannon_sounds: cannon_sounds,
+			explosion_sound: explosion_sound,
+
+			music: music,
+			excitement: 0.0,
+		}
+	}
 
-			bullet_sound: Chunk::from_file(Path::new("assets/sounds/bullet.ogg")).unwrap(),
-			explosion_sound: Chunk::from_file(Path::new("assets/sounds/explosion.ogg")).unwrap()
+	/// Lets go of what the game holds outside of the view, before leaving it.
+	fn leave(&mut self, context: &mut Phi) {
+		for sound in self.cannon_sounds.iter().chain(Some(&self.explosion_sound)) {
+			context.audio.forget(sound);
 		}
+		self.music.stop(&mut context.audio);
+		context.effects.clear();
+		context.assets.unload("game");
 	}
 }
 
 macro_rules! explode (
 	( $game_ident: ident : $context_ident: ident @ $center_expr: expr ) => { 
 		{
+			let center = $center_expr;
+
 			$game_ident.explosions.push(Box::new(
-				$game_ident.explosion_factory.at_center($center_expr)));
+				$game_ident.explosion_factory.at_center(center)));
+			$game_ident.debris.burst_at(center, 24);
 
-			$context_ident.play_sound(&$game_ident.explosion_sound);
+			$game_ident.excitement = sound_explosion(&mut $context_ident.audio, &$game_ident.explosion_sound, center, $game_ident.excitement);
 		}
 	}
 );
@@ -105,9 +281,13 @@ macro_rules! explode (
 impl View for GameView {
 	fn update(mut self: Box<Self>, context: &mut Phi, elapsed: f64) -> ViewAction {
 		if context.events.now.quit {
+			self.leave(context);
+
 			return ViewAction::Quit;
 		}
 		if context.events.now.key_escape == Some(true) {
+			self.leave(context);
+
 			return ViewAction::Render(Box::new(::views::menu_main::MainMenuView::new(context)));
 		}
 		// This is a tricky 'game' update block, as we have troubles
@@ -117,32 +297,31 @@ impl View for GameView {
 
 			let asteroids_left: Vec<Box<Asteroid>> = ::std::mem::replace(&mut game.asteroids, vec![]).into_iter()
 			.filter_map(|asteroid| {
-				// By default, the asteroid has not been in a collision.
-				let mut hits_count = 0;
-
-				for bullet in &mut game.bullets {
-					//? Notice that we refer to the bullet as `bullet.value`
-					//? because it has been wrapped in `MaybeAlive`.
-					match bullet.hits_at(&*asteroid) {
-						Some(hit_location) => {
-							game.blasts.push(Box::new(Blast::new(hit_location)));
-							explode!(game:context @ hit_location);
-
-							hits_count += 1;
-						},
-						_ => {}
-					}
+				let contacts = bullet_contacts(&mut game.bullets, &*asteroid);
+
+				for contact in &contacts {
+					//? Sparks bounce back off the asteroid, towards the bullet.
+					game.sparks.config_mut().direction = (-contact.normal.1).atan2(-contact.normal.0).to_degrees();
+					game.sparks.burst_at(contact.centroid, 12);
+
+					game.blasts.push(Box::new(Blast::new(contact.centroid)));
+					explode!(game:context @ contact.centroid);
 				}
 				let mut player = game.player.borrow_mut();
 				//? Then, we use the magic of `filter_map` to keep only the asteroids
 				//? that didn't explode.
-				if hits_count == 0 {
+				if contacts.is_empty() {
 					// The player's Player is destroyed if it is hit by an asteroid.
 					// In which case, the asteroid is also destroyed.
-					if !player.is_alive() || !player.is_hit_by(&*asteroid) {
-						return asteroid.update(context, elapsed);
+					match if player.is_alive() { player.hit_by(&*asteroid) } else { None } {
+						Some(contact) => {
+							let flash = game.palette.borrow().color_or("hit_flash", Color::RGB(255, 255, 255));
+
+							context.effects.flash(flash, 0.4);
+							explode!(game:context @ contact.centroid)
+						},
+						None => return asteroid.update(context, elapsed),
 					}
-					explode!(game:context @ player.frame().center());
 				}
 				None
 			})
@@ -191,8 +370,13 @@ impl View for GameView {
 
 			let mut player = game.player.borrow_mut();
 
+			game.exhaust.set_active(player.is_alive());
+
 			if player.is_alive() {
 				player.update_ref(context, elapsed);
+
+				let frame = *player.frame();
+				game.exhaust.set_position((frame.x + 8.0, frame.center().1));
 				// Allow the player to shoot after the bullets are updated, so that,
 				// when rendered for the first time, they are drawn wherever they
 				// spawned.
@@ -212,7 +396,7 @@ impl View for GameView {
 						while shots_fired > 0 {
 							game.bullets.append(&mut player.shoot());
 
-							context.play_sound(&game.bullet_sound);
+							context.play_sound_at(&game.cannon_sounds[player.get_ammo()], player.frame().center());
 							shots_fired -= 1;
 						}					
 					} else {
@@ -223,29 +407,25 @@ impl View for GameView {
 				}
 				let output_size = context.output_size();
 
-				let mut opaque = true;
-				let mut changed = false;
-				for i in 0..game.buttons_ammo.len() {
-					let button = &mut game.buttons_ammo[i];
-					let button_frame = *button.frame();
+				let ammo = player.get_ammo();
+				let changed = game.hud.focused() != Some(ammo);
 
-					let is_selected = player.get_ammo() == i;
-					let vertical_offset = if is_selected { 10.5 } else { 8.0 };
+				game.hud.focus(ammo);
+				game.hud.layout(Rectangle::with_size(output_size.0, output_size.1));
 
-					button.set_location(8.0 + i as f64 * (button_frame.w + 2.0), output_size.1 - vertical_offset - button_frame.h);
-					changed |= button.set_state(is_selected as usize);
+				let opaque = !game.hud.root().children()[0].frame().overlaps(player.frame());
+				//? The buttons fade out while the player is behind them, and
+				//? show up again right away when the ammo changes.
+				let target = if opaque { 1.0 } else { 0.0 };
 
-					if opaque && button.frame().overlaps(player.frame()) {
-						opaque = false;
-					}
-				}
-				let alpha_delta = if opaque { 4.0 * elapsed } else { -4.0 * elapsed };
+				if changed || game.buttons_alpha.to() != target {
+					let from = if changed { 1.0 } else { game.buttons_alpha.value() };
 
-				for button in &mut game.buttons_ammo {
-					let alpha = if !changed { button.get_alpha() } else { 1.0 };
+					game.buttons_alpha = Tween::new(from, target, (target - from).abs() / BUTTONS_FADE_SPEED);
+				}
+				game.buttons_alpha.advance(elapsed);
 
-					button.set_alpha(alpha + alpha_delta);
-				}						
+				game.hud.root_mut().set_alpha(game.buttons_alpha.value());						
 			} else {
 				// TODO
 				// For the moment, we won't do anything about the player dying. This will be
@@ -258,10 +438,30 @@ impl View for GameView {
 			if self::rand::random::<usize>() % 100 == 0 {
 				game.asteroids.push(Box::new(game.asteroid_factory.random(context)));
 			}
-			game.bg_ambient.update(elapsed);
-			game.bg_back.update(elapsed);
-			game.bg_middle.update(elapsed);
-			game.bg_front.update(elapsed);
+			game.exhaust.update(elapsed);
+			game.sparks.update(elapsed);
+			game.debris.update(elapsed);
+
+			game.backgrounds.update(context, elapsed);
+			game.stars.update(context.output_size(), elapsed);
+
+			//? The soundtrack follows how crowded the screen is, how much has
+			//? been blowing up lately, and how close the player is to harm.
+			let closest = if player.is_alive() {
+				let center = player.frame().center();
+
+				game.asteroids.iter()
+				.map(|asteroid| {
+					let (x, y) = asteroid.frame().center();
+					((x - center.0).powi(2) + (y - center.1).powi(2)).sqrt()
+				})
+				.fold(None, |closest: Option<f64>, distance| Some(closest.map_or(distance, |closest| closest.min(distance))))
+			} else {
+				None
+			};
+			game.excitement = (game.excitement - EXCITEMENT_DECAY * elapsed).max(0.0);
+			game.music.set_intensity(intensity(game.asteroids.len(), game.excitement, closest));
+			game.music.update(&mut context.audio, elapsed);
 		}
 		ViewAction::Render(self)
 	}
@@ -271,107 +471,73 @@ impl View for GameView {
 		context.renderer.set_draw_color(Color::RGB(0, 0, 0));
 		context.renderer.clear();
 
-		// Render Backgrounds
-		self.bg_ambient.render(&mut context.renderer);
-		self.bg_back.render(&mut context.renderer);
-		self.bg_middle.render(&mut context.renderer);
+		//? Every object submits its draw commands to the render queue, along
+		//? with the layer it belongs to, so that the order in which they're
+		//? visited doesn't matter. The queue is flushed once we're done.
+		self.backgrounds.render(context, layers::BACKGROUND, layers::FOREGROUND);
+		self.stars.queue(context, layers::BACKGROUND, STARS_DEPTH);
 
-		// Render asteroids
 		for asteroid in &self.asteroids {
 			asteroid.render(context);
 		}
-		let player = self.player.borrow();
-
-		if player.is_alive() {
-			player.render(context);
-		}
-		// Render bullets
 		for bullet in &self.bullets {
 			bullet.render(context);
 		}
-		// Render blasts
 		for blast in &self.blasts {
 			blast.render(context);
 		}
-		// Render explosions
 		for explosion in &self.explosions {
 			explosion.render(context);
 		}
-		// Render the foreground
-		self.bg_front.render(&mut context.renderer);
-
-		// Render HUD
-		if self.player.borrow().is_alive() {
-			for button in &self.buttons_ammo {
-				button.render(context);
-			}			
-		}
-	}
-}
-
+		self.exhaust.render(context);
+		self.sparks.render(context);
+		self.debris.render(context);
 
-struct GameButton {
-	button: Button,
-	label: Sprite,
-	label_frame: Rectangle,
-}
+		let player = self.player.borrow();
 
-impl GameButton {
-
-	pub fn new (context: &mut Phi, path: &str, label: &str, size: (f64, f64), padding: (f64, f64, f64, f64)) -> GameButton {
-		let label_sprite = context.ttf_str_sprite(label, "assets/fonts/BlackOpsOne-Regular.ttf", (size.1 / 3.36) as u16, Color::RGB(255, 255, 255)).unwrap();
-		let scale = ((size.0 - padding.0 - padding.2) / label_sprite.size().0).min((size.1 - padding.1 - padding.3) / label_sprite.size().1).min(1.0);
-		let label_size = (label_sprite.size().0 * scale, label_sprite.size().1 * scale);
-
-		GameButton {
-			button: Button::load(&context.renderer, path, size).unwrap(),
-			label: label_sprite,
-			label_frame: Rectangle {
-				x: size.0 - label_size.0 - padding.2,
-				y: size.1 - label_size.1 - padding.3,
-				w: label_size.0,
-				h: label_size.1
-			}
+		if player.is_alive() {
+			player.render(context);
+			self.hud.render(context);
 		}
 	}
+}
 
 
-	pub fn set_location(&mut self, x: f64, y:f64) {
-		self.button.set_location(x, y);
-	}
-
-	pub fn get_alpha(&self) -> f64 {
-		self.label.get_alpha()
-	}
+/// The contacts of the bullets hitting the `body`. Those bullets die.
+fn bullet_contacts(bullets: &mut [Box<Bullet>], body: &HitBox) -> Vec<Contact> {
+	bullets.iter_mut().filter_map(|bullet| bullet.hits_at(body)).collect()
+}
 
-	pub fn set_alpha(&mut self, alpha: f64) {
-		self.button.set_alpha(alpha);
-		self.label.set_alpha(alpha);
-	}
+/// Plays the explosion sound where it happened, and returns how excited the
+/// game gets from the given `excitement`.
+fn sound_explosion(audio: &mut Audio, sound: &Handle<Sound>, center: (f64, f64), excitement: f64) -> f64 {
+	audio.play_at(sound, center);
+	(excitement + EXCITEMENT_PER_EXPLOSION).min(1.0)
+}
 
-	pub fn set_state(&mut self, state: usize) -> bool {
-		if self.button.get_state() != state {
-			self.button.set_state(state);
+/// How intense the game is, in [0, 1], given the number of asteroids on
+/// screen, how excited the game is by the recent explosions, and the distance
+/// from the player to the closest asteroid, if any.
+fn intensity(asteroids: usize, excitement: f64, closest: Option<f64>) -> f64 {
+	let crowd = (asteroids as f64 / CROWDED_ASTEROIDS).min(1.0);
+	let danger = closest.map_or(0.0, |distance| (1.0 - distance / DANGER_DISTANCE).max(0.0));
 
-			return true;
-		}
-		false
-	}
+	(0.4 * crowd + 0.3 * excitement.max(0.0).min(1.0) + 0.3 * danger).min(1.0)
+}
 
 
-	pub fn frame(&self) -> &Rectangle {
-		self.button.frame()
-	}
+/// A button showing one of the ammo, with its key in the bottom-right corner.
+fn ammo_button(phi: &mut Phi, path: &str, key: &str) -> ImageButton {
+	let (w, h) = BUTTON_SIZE;
+	let (margin_x, margin_y) = BUTTON_LABEL_MARGIN;
+	let label = Text::fit(phi, key,
+		TextStyle::new(BUTTON_LABEL_FONT, BUTTON_LABEL_SIZE, Color::RGB(255, 255, 255)),
+		(w - 2.0 * margin_x, h - 2.0 * margin_y)).unwrap();
+	let button = Button::new(&phi.assets.texture(&phi.renderer, path).unwrap(), BUTTON_SIZE).unwrap();
 
-
-	pub fn render(&self, context: &mut Phi) {
-		self.button.render(&mut context.renderer);
-		self.label.render(&mut context.renderer, Rectangle {
-			x: self.button.frame().x + self.label_frame.x,
-			y: self.button.frame().y + self.label_frame.y,
-			..self.label_frame
-		});
-	}
+	ImageButton::new(button)
+		.label(label)
+		.label_anchor(Anchor::BottomRight, BUTTON_LABEL_MARGIN)
 }
 
 
@@ -396,16 +562,21 @@ pub trait HitBox {
 	fn bounds(&self) -> &Rectangle;
 
 
-	fn collision_mask(&self) -> &AlphaChannel;
+	/// The mask is borrowed from the assets, so that it follows hot reloads.
+	fn collision_mask<'a>(&'a self) -> Ref<'a, AlphaChannel>;
 
-	fn collides_with(&self, another: &HitBox) -> Option<Rectangle> {
+	/// Returns the contact set of both bodies, if they collide. The contact
+	/// normal points from `self` towards `another`.
+	fn collides_with(&self, another: &HitBox) -> Option<Contact> {
 		Rectangle::intersection(self.frame(), another.frame())
 		.map_or(None, |intersection| {
+			let (x_a, y_a) = (self.frame().x - self.bounds().x, self.frame().y - self.bounds().y);
+			let (x_b, y_b) = (another.frame().x - another.bounds().x, another.frame().y - another.bounds().y);
+			let (mask_a, mask_b) = (self.collision_mask(), another.collision_mask());
 
-			if AlphaChannel::intersect(self.collision_mask(), self.frame().x - self.bounds().x, self.frame().y - self.bounds().y, 
-				another.collision_mask(), another.frame().x - another.bounds().x, another.frame().y - another.bounds().y, intersection) {
-
-				return Some(intersection);
+			if AlphaChannel::intersect(&mask_a, x_a, y_a, &mask_b, x_b, y_b, intersection) {
+				return AlphaChannel::contact(&mask_a, x_a, y_a, &mask_b, x_b, y_b, intersection)
+				.map(|contact| contact.oriented(self.frame().center(), another.frame().center()));
 			}
 			None
 		})
@@ -413,33 +584,14 @@ pub trait HitBox {
 }
 
 
-fn load_spritesheet_with_alpha (phi: &Phi, path: &str, alpha_threshold: f64) -> Result<(AlphaChannel, Sprite), ::std::io::Error> {
-	let alpha_path = ::std::path::Path::new(path).with_extension("acl0");
-
-	match AlphaChannel::from_file(&alpha_path) {
-		Ok(alpha) => { 
-			Ok((alpha, Sprite::load(&phi.renderer, path).unwrap()))
-		},
-		_ => {
-			let surface_reader = RWops::from_file(path, "rb").unwrap();
-			let surface = surface_reader.load().unwrap();
-
-			let alpha = unsafe { AlphaChannel::from_surface(&surface, Some(alpha_threshold)).unwrap() };
-
-			try!(alpha.save_to(&alpha_path));
-
-			Ok((alpha, Sprite::from_surface(&phi.renderer, &surface).unwrap()))
-		}
-	}
-}
-
-
 #[cfg(test)]
 mod tests {
 	extern crate time;
 
 	use super::*;
 	use ::phi::{ Phi, View, ViewAction };
+	use ::phi::audio::Audio;
+	use ::phi::backend::{ AudioLog, NullAudio };
 	use self::time::{ PreciseTime, Duration };
 
 	struct EmptyView {
@@ -466,6 +618,63 @@ mod tests {
 	}
 
 
+	#[test]
+	fn intensity_follows_the_game() {
+		assert_eq!(intensity(0, 0.0, None), 0.0);
+		assert_eq!(intensity(24, 2.0, Some(0.0)), 1.0);
+		assert!(intensity(6, 0.0, None) < intensity(6, 0.0, Some(DANGER_DISTANCE / 2.0)));
+		assert_eq!(intensity(6, 0.0, Some(DANGER_DISTANCE * 2.0)), intensity(6, 0.0, None));
+	}
+
+
+	/// A square rock, opaque all over, standing in for an asteroid.
+	struct Rock {
+		frame: Rectangle,
+		bounds: Rectangle,
+		mask: RefCell<AlphaChannel>,
+	}
+
+	impl HitBox for Rock {
+		fn frame(&self) -> &Rectangle {
+			&self.frame
+		}
+
+		fn bounds(&self) -> &Rectangle {
+			&self.bounds
+		}
+
+		fn collision_mask<'a>(&'a self) -> Ref<'a, AlphaChannel> {
+			self.mask.borrow()
+		}
+	}
+
+	#[test]
+	fn asteroids_explode_audibly() {
+		let log = AudioLog::new();
+		let mut audio = Audio::new(Box::new(NullAudio::recording(log.clone())));
+		let explosion = Handle::new(Sound::silent("assets/sounds/explosion.ogg"));
+		let rock = Rock {
+			frame: Rectangle { x: 368.0, y: 268.0, w: 64.0, h: 64.0 },
+			bounds: Rectangle { x: 0.0, y: 0.0, w: 64.0, h: 64.0 },
+			mask: RefCell::new(AlphaChannel::from_fn(64, 64, |_, _| true)),
+		};
+		audio.set_listener(Rectangle { x: 0.0, y: 0.0, w: 800.0, h: 600.0 });
+
+		let mut bullets = bullet::spawn(CANNONS[0], 400.0, 300.0, 300.0);
+		let contacts = bullet_contacts(&mut bullets, &rock);
+		let mut excitement = 0.0;
+
+		assert!(!contacts.is_empty());
+		assert!(bullets.iter().all(|bullet| !bullet.is_alive()));
+
+		for contact in &contacts {
+			excitement = sound_explosion(&mut audio, &explosion, contact.centroid, excitement);
+		}
+		assert!(log.was_played("assets/sounds/explosion.ogg"));
+		assert!(excitement > 0.0);
+	}
+
+
 	const GAME_VIEW_LOAD_REPEAT_COUNT: u32 = 1000;
 
 	#[test]
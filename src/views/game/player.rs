@@ -1,13 +1,16 @@
 use phi::Phi;
+use phi::assets::Handle;
 use phi::data::Rectangle;
-use phi::gfx::{AlphaChannel, Renderable, Sprite};
+use phi::gfx::{AlphaChannel, Contact, Sprite};
 
 use sdl2::pixels::Color;
 
+use std::cell::Ref;
+
 use views::game::bullet::{Bullet, CannonType};
 
 
-use super::{GameObject, HitBox};
+use super::{layers, GameObject, HitBox};
 
 
 const PLAYER_SPEED: f64 = 180.0;
@@ -17,9 +20,17 @@ const PLAYER_CANNON_TAKEAWAY: f64 = 30.0;
 const PLAYER_CANNON1_OFFSET: f64 = 8.0;
 const PLAYER_CANNON2_OFFSET: f64 = PLAYER_H - 8.0;
 
+/// The cannons the player switches between with the number keys, indexed by
+/// their ammo.
+pub const CANNONS: [CannonType; 3] = [
+	CannonType::RectBullet,
+	CannonType::SineBullet { amplitude: 10.0, angular_vel: 15.0 },
+	CannonType::DivergentBullet { a: 100.0, b: 1.2 },
+];
+
 
-/// The different states our Player might be in. In the image, they're ordered
-/// from left to right, then from top to bottom.
+/// The different states our Player might be in. Each one indexes its frame
+/// name in `PLAYER_FRAMES`.
 #[derive(Clone, Copy)]
 pub enum PlayerFrame {
 	UpNorm   = 1,
@@ -33,14 +44,20 @@ pub enum PlayerFrame {
 	DownSlow = 6
 }
 
+const PLAYER_FRAMES: [&'static str; 9] = [
+	"ship_up_slow",   "ship_up_norm",   "ship_up_fast",
+	"ship_mid_slow",  "ship_mid_norm",  "ship_mid_fast",
+	"ship_down_slow", "ship_down_norm", "ship_down_fast",
+];
+
 pub struct Player {
 	rect: Rectangle,
 	sprites: Vec<Sprite>,
 
-	alpha: AlphaChannel,
+	alpha: Handle<AlphaChannel>,
 
+	/// The index of the cannon in `CANNONS`.
 	ammo: usize,
-	cannon: CannonType,
 	current: PlayerFrame,
 	is_dead: bool,
 }
@@ -48,25 +65,12 @@ pub struct Player {
 impl Player {
 
 	pub fn new(phi: &mut Phi) -> Player {
-		let (alpha, spritesheet) = super::load_spritesheet_with_alpha(phi, "assets/sprites/spaceship.png", 0.5).unwrap();
-		//? When we know in advance how many elements the `Vec` we contain, we
-		//? can allocate the good amount of data up-front.
-		let mut sprites = Vec::with_capacity(9);
-
-		let (w, h) = spritesheet.size();
-		let w = w / 3.0;
-		let h = h / 3.0;
-
-		for y in 0..3 {
-			for x in 0..3 {
-				sprites.push(spritesheet.region(Rectangle {
-					w: w,
-					h: h,
-					x: w * x as f64,
-					y: h * y as f64,
-				}).unwrap());
-			}
-		}
+		let atlas = phi.assets.atlas(&phi.renderer, "assets/sprites/spaceship.atlas").unwrap();
+		let alpha = phi.assets.atlas_mask(&atlas, 0.5).unwrap();
+		let sprites = PLAYER_FRAMES.iter()
+		.map(|name| atlas.frame(name).unwrap().clone())
+		.collect();
+
 		Player {
 			rect: Rectangle {
 				x: 64.0,
@@ -80,7 +84,6 @@ impl Player {
 
 			//? Let `RectBullet` be the default kind of bullet.
 			ammo: 0,
-			cannon: CannonType::RectBullet,
 			current: PlayerFrame::MidNorm,
 			is_dead: false,
 		}
@@ -93,21 +96,12 @@ impl Player {
 		// Change the player's cannons
 		if context.events.now.key_1 == Some(true) {
 			self.ammo = 0;
-			self.cannon = CannonType::RectBullet;
 		}
 		if context.events.now.key_2 == Some(true) {
 			self.ammo = 1;
-			self.cannon = CannonType::SineBullet {
-				amplitude: 10.0,
-				angular_vel: 15.0,
-			};
 		}
 		if context.events.now.key_3 == Some(true) {
 			self.ammo = 2;
-			self.cannon = CannonType::DivergentBullet {
-				a: 100.0,
-				b: 1.2,
-			};
 		}
 		// Move the player's ship
 		let diagonal =
@@ -167,16 +161,20 @@ impl Player {
 
 	pub fn shoot(&self) -> Vec<Box<Bullet>> {
 		super::bullet::spawn(
-			self.cannon, 
+			CANNONS[self.ammo],
 			self.rect.x + PLAYER_CANNON_TAKEAWAY, 
 			self.rect.y + PLAYER_CANNON1_OFFSET, 
 			self.rect.y + PLAYER_CANNON2_OFFSET)
 	}
 
-	pub fn is_hit_by(&mut self, body: &HitBox) -> bool {
-		self.is_dead |= self.collides_with(body) != None;
+	/// Returns the contact set of the ship and the `body`, if the latter hits
+	/// the former. The ship gets destroyed on hit.
+	pub fn hit_by(&mut self, body: &HitBox) -> Option<Contact> {
+		let contact = self.collides_with(body);
+
+		self.is_dead |= contact.is_some();
 
-		self.is_dead
+		contact
 	}
 }
 
@@ -204,12 +202,10 @@ impl GameObject<Player> for Player {
 
 		// Render the bounding box (for debugging purposes)
 		if ::DEBUG {
-			context.renderer.set_draw_color(Color::RGB(10, 200, 50));
-			context.renderer.fill_rect(self.rect.to_sdl().unwrap()).unwrap();
+			context.queue.fill_rect(layers::PLAYER, -1.0, self.rect, Color::RGB(10, 200, 50));
 		}
 		// Render the ship's current sprite.
-		self.sprites[self.current as usize]
-		.render(&mut context.renderer, self.rect);
+		context.queue.sprite(layers::PLAYER, 0.0, &self.sprites[self.current as usize], self.rect);
 	}
 }
 
@@ -222,7 +218,7 @@ impl HitBox for Player {
 		self.sprites[self.current as usize].frame()
 	}
 
-	fn collision_mask(&self) -> &AlphaChannel {
-		&self.alpha
+	fn collision_mask<'a>(&'a self) -> Ref<'a, AlphaChannel> {
+		self.alpha.borrow()
 	}
 }
\ No newline at end of file
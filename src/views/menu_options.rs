@@ -0,0 +1,91 @@
+use phi::{Phi, View, ViewAction};
+use phi::audio::Bus;
+use phi::text::TextStyle;
+
+use sdl2::pixels::Color;
+
+use hud::starfield::Starfield;
+use hud::widgets::{Alignment, Anchor, Anchored, Checkbox, HBox, Label, List, Panel, Slider, Spacer, Ui, VBox, Widget};
+
+use views::menu_main::{menu_stars, menu_theme, MainMenuView};
+
+
+/// The font size of the options, smaller than the one of the menu's actions.
+const OPTION_FONT_SIZE: u16 = 24;
+/// The width of the names of the options, so that their sliders line up.
+const OPTION_NAME_WIDTH: f64 = 160.0;
+const SLIDER_SIZE: (f64, f64) = (240.0, 16.0);
+
+
+/// Lets the player set the volumes of the game.
+pub struct OptionsView {
+	ui: Ui,
+	stars: Starfield,
+}
+
+impl OptionsView {
+	pub fn new(phi: &mut Phi) -> OptionsView {
+		let theme = menu_theme(phi);
+		let style = TextStyle { size: OPTION_FONT_SIZE, ..theme.text_style() };
+		let settings = phi.audio.settings().clone();
+
+		let volume = |bus: Bus| Slider::new(&theme, SLIDER_SIZE, settings.volume(bus))
+			.on_change(move |phi, volume| phi.audio.set_volume(bus, volume));
+
+		let mut back = List::new(&theme);
+		back.push(phi, "Back", |phi| Some(ViewAction::Render(Box::new(MainMenuView::new(phi))))).unwrap();
+
+		let options = VBox::new(theme.spacing)
+			.align(Alignment::Stretch)
+			.child(Label::new(phi, &theme, "Options").unwrap())
+			.child(option(phi, &style, "Volume", volume(Bus::Master)))
+			.child(option(phi, &style, "Music", volume(Bus::Music)))
+			.child(option(phi, &style, "Effects", volume(Bus::Sfx)))
+			.child(Checkbox::with_style(phi, &theme, "Mute", style.clone()).unwrap()
+				.checked(settings.muted)
+				.on_toggle(|phi, muted| phi.audio.set_muted(muted)))
+			.child(back);
+
+		OptionsView {
+			ui: Ui::new(Anchored::new(Anchor::Center, Panel::new(&theme, options))),
+			stars: menu_stars(),
+		}
+	}
+}
+
+/// Lines the widget up after the name of the option it sets.
+fn option<W: Widget + 'static>(phi: &mut Phi, style: &TextStyle, name: &str, widget: W) -> HBox {
+	let label = Label::with_style(phi, name, style.clone()).unwrap();
+	let (w, _) = label.preferred_size();
+
+	HBox::new(0.0)
+		.align(Alignment::Center)
+		.child(label)
+		.child(Spacer::new((OPTION_NAME_WIDTH - w).max(0.0), 0.0))
+		.child(widget)
+}
+
+impl View for OptionsView {
+	fn update(mut self: Box<Self>, phi: &mut Phi, elapsed: f64) -> ViewAction {
+		if phi.events.now.quit {
+			return ViewAction::Quit;
+		}
+		if phi.events.now.key_escape == Some(true) {
+			return ViewAction::Render(Box::new(MainMenuView::new(phi)));
+		}
+		if let Some(action) = self.ui.update(phi, elapsed) {
+			return action;
+		}
+		self.stars.update(phi.output_size(), elapsed);
+
+		ViewAction::Render(self)
+	}
+
+	fn render(&self, phi: &mut Phi) {
+		phi.renderer.set_draw_color(Color::RGB(0, 0, 0));
+		phi.renderer.clear();
+
+		self.stars.render(&mut phi.renderer);
+		self.ui.render(phi);
+	}
+}
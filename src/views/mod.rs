@@ -1,3 +1,3 @@
-
 pub mod game;
-pub mod menu_main;
\ No newline at end of file
+pub mod menu_main;
+pub mod menu_options;
\ No newline at end of file